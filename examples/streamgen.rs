@@ -0,0 +1,48 @@
+//! Stand-in live data source. Start the viewer with `--listen 127.0.0.1:5555` (or
+//! `--listen unix:/tmp/wavygravy.sock`), then run
+//! `cargo run --example streamgen -- 127.0.0.1:5555` to stream a clock, a slow strobe
+//! and a sine wave in real time.
+use std::f64::consts::PI;
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use wavefmt::{FieldInfo, FieldType};
+use wavefmt::stream::{write_msg, StreamMsg};
+
+/// Sample period (us)
+const PERIOD : u64 = 10;
+
+fn connect(addr: &str) -> std::io::Result<Box<dyn Write>> {
+    #[cfg(unix)]
+    if let Some(path) = addr.strip_prefix("unix:") {
+        return Ok(Box::new(std::os::unix::net::UnixStream::connect(path)?));
+    }
+    Ok(Box::new(TcpStream::connect(addr)?))
+}
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:5555".to_string());
+    let mut conn = BufWriter::new(connect(&addr)?);
+    let fields = vec![
+        FieldInfo { name: "gen.clk".to_string(), ftype: FieldType::Digital },
+        FieldInfo { name: "gen.strobe".to_string(), ftype: FieldType::Digital },
+        FieldInfo { name: "gen.sine".to_string(), ftype: FieldType::Analog },
+    ];
+    write_msg(&mut conn, &StreamMsg::Declare { timescale: 1e-6, fields })?;
+
+    let start = Instant::now();
+    let mut n : u64 = 0;
+    loop {
+        let now = start.elapsed().as_micros() as u64;
+        while n * PERIOD < now {
+            let t = (n * PERIOD) as f64;
+            let clk = (n % 2) as f32;
+            let strobe = if n % 1000 < 10 { 1. } else { 0. };
+            let sine = (2. * PI * t / 50000.).sin() as f32;
+            write_msg(&mut conn, &StreamMsg::Batch { time: t, values: vec![(0, clk), (1, strobe), (2, sine)] })?;
+            n += 1;
+        }
+        conn.flush()?;
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -1,13 +1,13 @@
 use std::cell::{RefCell, RefMut};
 
 use vello::{
-    kurbo::{Affine, /*Line,*/ PathEl, Rect},
+    kurbo::{Affine, BezPath, /*Line,*/ PathEl, Rect},
     peniko::{BlendMode, Brush, Color, Fill, Stroke},
-    /*BumpAllocators,*/ SceneBuilder,
+    /*BumpAllocators,*/
 };
 use crate::{
     TimeRel, TimeUnit, TimeScale, Vec2,
-    Sampler, DataStore, SimpleText,
+    Sampler, DataStore, Decoder, DrawSink,
     RULE_HEIGHT, SCROLL_WIDTH,
 };
 
@@ -21,10 +21,27 @@ const YSCRLBOX_COL : Brush = Brush::Solid(Color::rgba8(200, 0, 200, 255));
 const YSCRLARR_COL : Brush = Brush::Solid(Color::rgba8(0, 0, 0, 255));
 const XSCRLLOC_COL : Brush = Brush::Solid(Color::rgba8(0, 180, 0, 180));
 const CURS_COL : Brush = Brush::Solid(Color::YELLOW);
+const CURS2_COL : Brush = Brush::Solid(Color::rgba8(0, 220, 220, 255));
 const XSCRLCURS_COL : Brush = Brush::Solid(Color::RED);
+/// Marker colours, cycled through as markers are added
+const MARKER_COLS : [Color; 6] = [
+    Color::rgba8(255, 80, 80, 255),
+    Color::rgba8(80, 255, 80, 255),
+    Color::rgba8(80, 160, 255, 255),
+    Color::rgba8(255, 160, 0, 255),
+    Color::rgba8(220, 80, 255, 255),
+    Color::rgba8(255, 255, 255, 255),
+];
+const SEL_COL : Brush = Brush::Solid(Color::rgba8(255, 255, 255, 40));
+const DIFF_COL : Brush = Brush::Solid(Color::rgba8(200, 0, 0, 110));
+const SEG_COL : Brush = Brush::Solid(Color::rgba8(0, 160, 160, 255));
+const SEGERR_COL : Brush = Brush::Solid(Color::rgba8(220, 40, 40, 255));
+const GROUP_COL : Brush = Brush::Solid(Color::rgba8(40, 40, 80, 255));
 
 /// How close (in pixels) we have to be to grab column header adjustment
 const COLHDR_REACH : f64 = 10.;
+/// How close (in pixels) the cursor has to be to a marker to rename or delete it
+const MARKER_REACH : f64 = 5.;
 const COLWIDTH_MIN : f64 = 16.;
 const SIGWIDTH_MIN : f64 = 32.;
 
@@ -38,12 +55,73 @@ enum MouseRegion {
     ColValueHdr,
 }
 
+/// Transitions stepped to when moving the cursor between edges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeFilter {
+    Any,
+    Rising,
+    Falling,
+}
+
+impl EdgeFilter {
+    pub fn next(self) -> Self {
+        match self {
+            EdgeFilter::Any => EdgeFilter::Rising,
+            EdgeFilter::Rising => EdgeFilter::Falling,
+            EdgeFilter::Falling => EdgeFilter::Any,
+        }
+    }
+
+    /// Suffix of the selected signal's label, when stepping to rising or falling edges only
+    fn label_suffix(self) -> &'static str {
+        match self {
+            EdgeFilter::Any => "",
+            EdgeFilter::Rising => " [rising]",
+            EdgeFilter::Falling => " [falling]",
+        }
+    }
+
+    fn matches(self, rising: bool) -> bool {
+        match self {
+            EdgeFilter::Any => true,
+            EdgeFilter::Rising => rising,
+            EdgeFilter::Falling => !rising,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MouseCursor {
     Normal,
     Column,
 }
 
+/// Named time marker
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub time: TimeRel,
+    pub name: String,
+    /// Index into marker colours
+    pub colour: usize,
+}
+
+/// Named group of signals under a header row, collapsible to that row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub name: String,
+    /// Member signals in display order
+    pub signals: Vec<usize>,
+    pub collapsed: bool,
+}
+
+/// Row of the displayed signal tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row {
+    /// Header of the group with this index
+    Group(usize),
+    Signal(usize),
+}
+
 #[derive(Debug)]
 pub struct Chart {
     pub time_range: [TimeRel; 2],
@@ -52,7 +130,22 @@ pub struct Chart {
     pub col_signame : f64,
     pub col_value : f64,
     pub cursor: Option<TimeRel>,
+    /// Second cursor, measured against the main one
+    pub cursor2: Option<TimeRel>,
+    /// Scroll to keep newest data in view as `max_range` grows (tail mode)
+    pub follow: bool,
+    /// Selected signal
+    pub selected: Option<usize>,
+    pub edge_filter: EdgeFilter,
+    /// Named markers in time order
+    pub markers: Vec<Marker>,
+    /// Signal groups, each drawn from the position of its first member
+    pub groups: Vec<Group>,
+    /// Time spans where a signal differs from the file it is compared with
+    pub mismatches: Vec<(usize, [TimeRel; 2])>,
     mregion: MouseRegion,
+    /// Waveform clicks place the second cursor
+    secondary: bool,
 }
 
 impl Chart {
@@ -64,7 +157,15 @@ impl Chart {
             col_signame: 0.2,
             col_value: 0.05,
             cursor: None,
+            cursor2: None,
+            follow: false,
+            selected: None,
+            edge_filter: EdgeFilter::Any,
+            markers: Vec::new(),
+            groups: Vec::new(),
+            mismatches: Vec::new(),
             mregion: MouseRegion::None,
+            secondary: false,
         }
     }
 
@@ -78,6 +179,17 @@ impl Chart {
         //self.max_scale = *scale;
     }
 
+    /// Update global range as data is appended, scrolling the view to the newest data
+    /// when following
+    pub fn extend_max_range(&mut self, range: &[f64; 2]) {
+        self.max_range = *range;
+        if self.follow {
+            let zoom_range = self.time_range[1] - self.time_range[0];
+            self.time_range[1] = range[1];
+            self.time_range[0] = (range[1] - zoom_range).max(range[0]);
+        }
+    }
+
     pub fn set_cursor(&mut self, t: f64) {
         println!("set_cursor({})", t);
         self.cursor = Some(t);
@@ -88,6 +200,276 @@ impl Chart {
         self.cursor = None;
     }
 
+    pub fn set_cursor2(&mut self, t: f64) {
+        self.cursor2 = Some(t);
+    }
+
+    /// Window measured: between the cursors when both are placed, else the visible range
+    pub fn measure_range(&self) -> [TimeRel; 2] {
+        match (self.cursor, self.cursor2) {
+            (Some(t0), Some(t1)) if t0 != t1 => [t0.min(t1), t0.max(t1)],
+            _ => self.time_range,
+        }
+    }
+
+    /// Fit time range to the span between the two cursors
+    pub fn zoom_to_cursors(&mut self) {
+        let (Some(t0), Some(t1)) = (self.cursor, self.cursor2) else { return };
+        if t0 == t1 { return; }
+        self.time_range[0] = t0.min(t1).max(self.max_range[0]);
+        self.time_range[1] = t0.max(t1).min(self.max_range[1]);
+    }
+
+    /// Format value of a signal at the cursor (or start of view), and at the second cursor
+    fn cursor_values<T>(&self, value_at: impl Fn(TimeRel) -> T, fmt: impl Fn(T) -> String) -> String {
+        let meas_pos = if let Some(curs) = self.cursor { curs } else { self.time_range[0] };
+        match self.cursor2 {
+            Some(curs2) => format!("{} | {}", fmt(value_at(meas_pos)), fmt(value_at(curs2))),
+            None => fmt(value_at(meas_pos)),
+        }
+    }
+
+    /// Move cursor to the start of the next (or previous) mismatch, scrolling it into view
+    pub fn goto_mismatch(&mut self, forward: bool) {
+        let curs = self.cursor.unwrap_or(self.time_range[0]);
+        let starts = self.mismatches.iter().map(|m| m.1[0]);
+        let t = if forward {
+            starts.filter(|&t| t > curs).min_by(|a, b| a.total_cmp(b))
+        } else {
+            starts.filter(|&t| t < curs).max_by(|a, b| a.total_cmp(b))
+        };
+        let Some(t) = t else { return };
+        self.goto_time(t);
+    }
+
+    /// Move cursor to `t`, scrolling it into view
+    pub fn goto_time(&mut self, t: TimeRel) {
+        self.cursor = Some(t);
+        if t < self.time_range[0] || t > self.time_range[1] {
+            let zoom_range = self.time_range[1] - self.time_range[0];
+            self.time_range[0] = (t - 0.5 * zoom_range).max(self.max_range[0]);
+            self.time_range[1] = (self.time_range[0] + zoom_range).min(self.max_range[1]);
+        }
+    }
+
+    /// Rows displayed for `num_signals` signals: ungrouped signals in order, and each group
+    /// at its first member with the other members following unless it is collapsed
+    pub fn rows(&self, num_signals: usize) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut shown = vec![false; self.groups.len()];
+        for sig in 0..num_signals {
+            match self.groups.iter().position(|g| g.signals.contains(&sig)) {
+                None => rows.push(Row::Signal(sig)),
+                Some(num) if !shown[num] => {
+                    shown[num] = true;
+                    rows.push(Row::Group(num));
+                    let group = &self.groups[num];
+                    if !group.collapsed {
+                        rows.extend(group.signals.iter().filter(|&&s| s < num_signals).map(|&s| Row::Signal(s)));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        rows
+    }
+
+    /// Signals to export: the selected one, else those of the displayed rows in order,
+    /// including the members of collapsed groups
+    pub fn export_signals(&self, num_signals: usize) -> Vec<usize> {
+        if let Some(sig) = self.selected {
+            return vec![sig];
+        }
+        self.rows(num_signals).into_iter().flat_map(|row| match row {
+            Row::Group(num) if self.groups[num].collapsed => {
+                self.groups[num].signals.iter().copied().filter(|&s| s < num_signals).collect()
+            }
+            Row::Group(_) => Vec::new(),
+            Row::Signal(sig) => vec![sig],
+        }).collect()
+    }
+
+    /// Height of a row, decoded rows counting as part of the signal above them
+    pub fn row_height(datas: &DataStore, row: Row) -> f64 {
+        match row {
+            Row::Group(_) => crate::HEIGHT_DIGITAL,
+            Row::Signal(sig) => datas.get_signal_height(sig) + datas.get_signal_decoders(sig).len() as f64 * crate::HEIGHT_DIGITAL,
+        }
+    }
+
+    /// Total height of the displayed rows
+    pub fn rows_height(&self, datas: &DataStore) -> f64 {
+        self.rows(datas.get_num_signals()).into_iter().map(|row| Self::row_height(datas, row)).sum()
+    }
+
+    /// Move signal `sig` into the group named `name`, creating it, or out of any group when
+    /// the name is empty. Groups left empty are removed.
+    pub fn group_signal(&mut self, sig: usize, name: &str) {
+        for group in &mut self.groups {
+            group.signals.retain(|&s| s != sig);
+        }
+        let name = name.trim();
+        if !name.is_empty() {
+            let num = match self.groups.iter().position(|g| g.name == name) {
+                Some(num) => num,
+                None => {
+                    self.groups.push(Group { name: name.to_string(), signals: Vec::new(), collapsed: false });
+                    self.groups.len() - 1
+                }
+            };
+            let signals = &mut self.groups[num].signals;
+            signals.push(sig);
+            signals.sort_unstable();
+        }
+        self.groups.retain(|g| !g.signals.is_empty());
+    }
+
+    /// Name of the group signal `sig` is in
+    pub fn group_of(&self, sig: usize) -> Option<&str> {
+        self.groups.iter().find(|g| g.signals.contains(&sig)).map(|g| g.name.as_str())
+    }
+
+    /// Select next (or previous) displayed signal, the first (or last) when none is
+    /// selected or the selected one is in a collapsed group
+    pub fn select_next(&mut self, num_signals: usize, forward: bool) {
+        let shown : Vec<usize> = self.rows(num_signals).into_iter()
+            .filter_map(|row| if let Row::Signal(sig) = row { Some(sig) } else { None })
+            .collect();
+        if shown.is_empty() { return; }
+        let pos = self.selected.and_then(|sel| shown.iter().position(|&sig| sig == sel));
+        self.selected = Some(shown[match (pos, forward) {
+            (Some(pos), true) => (pos + 1).min(shown.len() - 1),
+            (Some(pos), false) => pos.saturating_sub(1),
+            (None, true) => 0,
+            (None, false) => shown.len() - 1,
+        }]);
+    }
+
+    /// Row drawn at height `y`
+    pub fn row_at(&self, datas: &DataStore, y: f64) -> Option<Row> {
+        if y < RULE_HEIGHT { return None; }
+        let mut height_acc = RULE_HEIGHT;
+        for row in self.rows(datas.get_num_signals()) {
+            height_acc += Self::row_height(datas, row);
+            if y < height_acc {
+                return Some(row);
+            }
+        }
+        None
+    }
+
+    /// Signal drawn at height `y`, decoded rows count as part of the signal above them
+    pub fn signal_at(&self, datas: &DataStore, y: f64) -> Option<usize> {
+        match self.row_at(datas, y)? {
+            Row::Signal(sig) => Some(sig),
+            Row::Group(_) => None,
+        }
+    }
+
+    /// Select signal clicked in the name or value columns, return true if handled
+    pub fn handle_select_click(&mut self, datas: &DataStore, pos: &Vec2, width: f64) -> bool {
+        let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
+        if pos.x >= sig_xoffs { return false; }
+        match self.signal_at(datas, pos.y) {
+            Some(sig) => {
+                self.selected = Some(sig);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Collapse or expand group whose header is clicked in the name or value columns,
+    /// return true if handled
+    pub fn handle_group_click(&mut self, datas: &DataStore, pos: &Vec2, width: f64) -> bool {
+        let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
+        if pos.x >= sig_xoffs { return false; }
+        match self.row_at(datas, pos.y) {
+            Some(Row::Group(num)) => {
+                self.groups[num].collapsed = !self.groups[num].collapsed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move cursor to the next (or previous) transition of the selected signal passing the
+    /// edge filter, scrolling it into view. Return false when there is none.
+    pub fn goto_edge(&mut self, datas: &DataStore, forward: bool) -> bool {
+        let Some(sig) = self.selected else { return false };
+        let curs = self.cursor.unwrap_or(self.time_range[0]);
+        // Search windows growing away from the cursor
+        let mut span = (self.time_range[1] - self.time_range[0]).max(1.);
+        loop {
+            let range = if forward {
+                [curs, (curs + span).min(self.max_range[1])]
+            } else {
+                [(curs - span).max(self.max_range[0]), curs]
+            };
+            let Ok((mut prev, changes)) = crate::export::read_changes(datas, sig, &range) else { return false };
+            let mut found = None;
+            for (t, val) in changes {
+                let rising = val > prev;
+                prev = val;
+                if self.edge_filter.matches(rising) && (if forward { t > curs } else { t < curs }) {
+                    found = Some(t);
+                    if forward { break; }
+                }
+            }
+            if let Some(t) = found {
+                self.goto_time(t);
+                return true;
+            }
+            if (forward && range[1] >= self.max_range[1]) || (!forward && range[0] <= self.max_range[0]) {
+                return false;
+            }
+            span *= 4.;
+        }
+    }
+
+    /// Add marker, keeping markers in time order, return its index
+    pub fn add_marker(&mut self, time: TimeRel, name: &str) -> usize {
+        let colour = self.markers.len() % MARKER_COLS.len();
+        let idx = self.markers.partition_point(|m| m.time <= time);
+        self.markers.insert(idx, Marker { time, name: name.to_string(), colour });
+        idx
+    }
+
+    /// Add marker at the cursor named `M1`, `M2`... return its index
+    pub fn add_marker_at_cursor(&mut self) -> Option<usize> {
+        let curs = self.cursor?;
+        let num = (1..).find(|n| !self.markers.iter().any(|m| m.name == format!("M{n}"))).unwrap_or(1);
+        Some(self.add_marker(curs, &format!("M{num}")))
+    }
+
+    /// Marker nearest to the cursor, if within a few pixels of it in a window `width` wide
+    pub fn current_marker(&self, width: f64) -> Option<usize> {
+        let curs = self.cursor?;
+        let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
+        let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
+        let reach = MARKER_REACH * (self.time_range[1] - self.time_range[0]) / sig_width;
+        (0..self.markers.len())
+            .min_by(|&a, &b| (self.markers[a].time - curs).abs().total_cmp(&(self.markers[b].time - curs).abs()))
+            .filter(|&idx| (self.markers[idx].time - curs).abs() <= reach)
+    }
+
+    pub fn delete_marker(&mut self, idx: usize) {
+        self.markers.remove(idx);
+    }
+
+    /// Move cursor to the next (or previous) marker, scrolling it into view
+    pub fn goto_marker(&mut self, forward: bool) {
+        let curs = self.cursor.unwrap_or(self.time_range[0]);
+        let marker = if forward {
+            self.markers.iter().find(|m| m.time > curs)
+        } else {
+            self.markers.iter().rev().find(|m| m.time < curs)
+        };
+        if let Some(t) = marker.map(|m| m.time) {
+            self.goto_time(t);
+        }
+    }
+
     /// Convert time to screen x position
     pub fn time_to_xpos(&self, t: TimeRel, range: &[TimeRel; 2], sig_xoffs: f64, sig_width: f64) -> f64 {
         sig_xoffs + sig_width * (t - range[0]) / (range[1] - range[0])
@@ -157,7 +539,11 @@ impl Chart {
         let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
         let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
         let t = self.xpos_to_time(pos.x, &self.time_range, sig_xoffs, sig_width);
-        self.set_cursor(t);
+        if self.secondary {
+            self.set_cursor2(t);
+        } else {
+            self.set_cursor(t);
+        }
         true
     }
 
@@ -219,8 +605,10 @@ impl Chart {
         }
     }
 
-    /// Handle mouse down event, return true if handled
-    pub fn handle_mousedown(&mut self, prior: &Option<Vec2>, width: f64, height: f64) -> bool {
+    /// Handle mouse down event, return true if handled. With `secondary` (another button or a
+    /// modifier held), clicks in the waveform place the second cursor.
+    pub fn handle_mousedown(&mut self, prior: &Option<Vec2>, width: f64, height: f64, secondary: bool) -> bool {
+        self.secondary = secondary;
         if let Some(pos) = prior {
             //let col_signame_x : f64 = (width - SCROLL_WIDTH) * (self.col_signame);
             let col_value_x : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
@@ -297,8 +685,7 @@ impl Chart {
     /// Draw horizontal ruler, at top/bottom of waveform window
     pub fn draw_ruler(
         &self,
-        sb: &mut SceneBuilder,
-        text: &mut SimpleText,
+        sb: &mut impl DrawSink,
         width: f64,
         _height: f64,
         region_offset: Affine,
@@ -375,9 +762,7 @@ impl Chart {
                     if xpos - last_str_xpos > textwidth {
                         let label = crate::fmt_time(xv_step_round); //self.time_range[0] * self.time_scale.time);
                         //print!(" {}[{}]", xv_step_round, label);
-                        text.add(
-                            sb,
-                            None,
+                        sb.text(
                             label_height,
                             Some(&Brush::Solid(Color::YELLOW)),
                             offset * Affine::translate((xoffs + 1., yoffs + RULE_HEIGHT - 5.)),
@@ -425,8 +810,7 @@ impl Chart {
     /// Draw column headers at top of waveform window
     pub fn draw_colhdr(
         &self,
-        sb: &mut SceneBuilder,
-        _text: &mut SimpleText,
+        sb: &mut impl DrawSink,
         width: f64,
         _height: f64,
         offset: Affine,
@@ -460,14 +844,14 @@ impl Chart {
     #[inline(never)]
     pub fn draw_digital(
         &self,
-        sb: &mut SceneBuilder,
-        text: &mut SimpleText,
+        sb: &mut impl DrawSink,
         width: f64,
         _height: f64,
         region_offset: Affine,
         label_height: f32,
         signal_height: f64,
         y_offs : f64,
+        label: &str,
         smpl: RefMut<dyn Sampler<bool>>,
     )
     {
@@ -475,22 +859,15 @@ impl Chart {
         let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
         let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
 
-        let label = smpl.get_label();
-        text.add(
-            sb,
-            None,
+        sb.text(
             label_height,
             Some(&Brush::Solid(Color::WHITE)),
             region_offset * Affine::translate((0., y_offs + signal_height - 2.0)),
-            &label,
+            label,
         );
 
-        let meas_pos = if let Some(curs) = self.cursor { curs } else { self.time_range[0] };
-        let value_bool = smpl.get_value_at(meas_pos, self.time_scale);
-        let value = if value_bool { "1" } else { "0" };
-        text.add(
-            sb,
-            None,
+        let value = self.cursor_values(|t| smpl.get_value_at(t, self.time_scale), |v| if v { "1" } else { "0" }.to_string());
+        sb.text(
             label_height,
             Some(&Brush::Solid(Color::WHITE)),
             region_offset * Affine::translate((self.col_signame * width, y_offs + signal_height - 2.0)),
@@ -594,18 +971,97 @@ impl Chart {
         }
     }
 
+    /// Draw row of decoded segments at a specific location
+    #[inline(never)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_decoded(
+        &self,
+        sb: &mut impl DrawSink,
+        width: f64,
+        region_offset: Affine,
+        label_height: f32,
+        row_height: f64,
+        y_offs : f64,
+        decoder: &dyn Decoder,
+    )
+    {
+        use PathEl::*;
+        let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
+        let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
+
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(Color::WHITE)),
+            region_offset * Affine::translate((0., y_offs + row_height - 2.0)),
+            &decoder.get_label(),
+        );
+        let segments = match decoder.decode(&self.time_range) {
+            Ok(segments) => segments,
+            Err(_) => return,
+        };
+
+        let value = self.cursor_values(
+            |t| segments.iter().find(|seg| seg.span[0] <= t && t < seg.span[1]),
+            |seg| seg.map_or(String::new(), |seg| seg.label.clone()),
+        );
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(Color::WHITE)),
+            region_offset * Affine::translate((self.col_signame * width, y_offs + row_height - 2.0)),
+            &value,
+        );
+
+        let y_hi : f64 = y_offs + 2.;
+        let y_lo : f64 = y_offs + row_height - 1.;
+        let y_mid = 0.5 * (y_hi + y_lo);
+        for seg in &segments {
+            if seg.span[1] < self.time_range[0] || seg.span[0] > self.time_range[1] { continue; }
+            let x0 = self.time_to_xpos(seg.span[0].max(self.time_range[0]), &self.time_range, sig_xoffs, sig_width);
+            let x1 = self.time_to_xpos(seg.span[1].min(self.time_range[1]), &self.time_range, sig_xoffs, sig_width);
+            // Bus style outline with slanted ends
+            let slant = (0.5 * (y_lo - y_hi)).min(0.5 * (x1 - x0));
+            let outline = [
+                MoveTo((x0, y_mid).into()),
+                LineTo((x0 + slant, y_hi).into()),
+                LineTo((x1 - slant, y_hi).into()),
+                LineTo((x1, y_mid).into()),
+                LineTo((x1 - slant, y_lo).into()),
+                LineTo((x0 + slant, y_lo).into()),
+                ClosePath,
+            ];
+            let col = if seg.error { &SEGERR_COL } else { &SEG_COL };
+            sb.stroke(
+                &Stroke::new((1.0) as f32),
+                region_offset,
+                col,
+                None,
+                &outline,
+            );
+            // Only label segments wide enough for the text
+            let text_width = 0.6 * label_height as f64 * seg.label.len() as f64;
+            if x1 - x0 - 2. * slant > text_width {
+                sb.text(
+                    label_height,
+                    Some(col),
+                    region_offset * Affine::translate((0.5 * (x0 + x1 - text_width), y_offs + row_height - 2.0)),
+                    &seg.label,
+                );
+            }
+        }
+    }
+
     /// Draw individual analog waveform at a specific location
     #[inline(never)]
     pub fn draw_analog(
         &self,
-        sb: &mut SceneBuilder,
-        text: &mut SimpleText,
+        sb: &mut impl DrawSink,
         width: f64,
         _height: f64,
         region_offset: Affine,
         label_height: f32,
         signal_height: f64,
         y_offs : f64,
+        label: &str,
         smpl: RefMut<dyn Sampler<f32>>,
     )
     {
@@ -619,23 +1075,16 @@ impl Chart {
             height / yscale * (-val as f64) / 2. + yoffs + height/2.
         }
 
-        let label = smpl.get_label();
-        text.add(
-            sb,
-            None,
+        sb.text(
             label_height,
             Some(&Brush::Solid(Color::WHITE)),
             region_offset * Affine::translate((0., y_offs + label_yoffs)),
-            &label,
+            label,
         );
 
-        let meas_pos = if let Some(curs) = self.cursor { curs } else { self.time_range[0] };
-        let sigval = smpl.get_value_at(meas_pos, self.time_scale);
-        let value = format!("{:.2}", sigval);
+        let value = self.cursor_values(|t| smpl.get_value_at(t, self.time_scale), |v| format!("{:.2}", v));
         //let value = value_to_ypos(sigval, yscale, y_offs, );
-        text.add(
-            sb,
-            None,
+        sb.text(
             label_height,
             Some(&Brush::Solid(Color::WHITE)),
             region_offset * Affine::translate((self.col_signame * width, y_offs + label_yoffs)),
@@ -669,8 +1118,7 @@ impl Chart {
     /// Draw y scrollbar
     pub fn draw_yscroll(
         &self,
-        sb: &mut SceneBuilder,
-        _text: &mut SimpleText,
+        sb: &mut impl DrawSink,
         width: f64,
         height: f64,
         region_offset: Affine,
@@ -746,13 +1194,77 @@ impl Chart {
         );
     }
 
+    /// Draw group header row, and the combined activity of its members when collapsed
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_group(
+        &self,
+        sb: &mut impl DrawSink,
+        datas: &DataStore,
+        width: f64,
+        region_offset: Affine,
+        label_height: f32,
+        row_height: f64,
+        y_offs : f64,
+        group: &Group,
+    )
+    {
+        let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
+        let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
+
+        sb.fill(
+            Fill::NonZero,
+            region_offset,
+            &GROUP_COL,
+            None,
+            &Rect::new(0., y_offs, sig_xoffs, y_offs + row_height),
+        );
+        let label = if group.collapsed {
+            format!("[+] {} ({})", group.name, group.signals.len())
+        } else {
+            format!("[-] {}", group.name)
+        };
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(Color::WHITE)),
+            region_offset * Affine::translate((0., y_offs + row_height - 2.0)),
+            &label,
+        );
+        if !group.collapsed { return; }
+
+        // A tick at each pixel column where any member changes, over a baseline
+        let y_hi : f64 = y_offs + 2.;
+        let y_lo : f64 = y_offs + row_height - 1.;
+        let num_signals = datas.get_num_signals();
+        let mut columns = Vec::new();
+        for &sig in group.signals.iter().filter(|&&s| s < num_signals) {
+            let Ok((_, changes)) = crate::export::read_changes(datas, sig, &self.time_range) else { continue };
+            columns.extend(changes.into_iter()
+                .map(|(t, _)| self.time_to_xpos(t, &self.time_range, sig_xoffs, sig_width).round() as i64));
+        }
+        columns.sort_unstable();
+        columns.dedup();
+        let mut path = BezPath::new();
+        path.move_to((sig_xoffs, y_lo));
+        path.line_to((sig_xoffs + sig_width, y_lo));
+        for x in columns {
+            path.move_to((x as f64, y_lo));
+            path.line_to((x as f64, y_hi));
+        }
+        sb.stroke(
+            &Stroke::new(1.0),
+            region_offset,
+            &WVUP_COL,
+            None,
+            &path,
+        );
+    }
+
     /// Draw chart layer
     #[allow(clippy::too_many_arguments)]
     pub fn draw_layer<'a, T>(
         &self,
-        sb: &mut SceneBuilder,
-        text: &mut SimpleText,
-        datas: &mut DataStore,
+        sb: &mut impl DrawSink,
+        datas: &DataStore,
         viewport_width: f64,
         viewport_height: f64,
     )
@@ -777,9 +1289,9 @@ impl Chart {
         let rule_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
         let rule_width : f64 = (width - SCROLL_WIDTH) - rule_xoffs;
         // Top ruler
-        self.draw_ruler(sb, text, rule_width, height, offset, label_height, RULE_HEIGHT, rule_xoffs, 0., &self.time_range, &self.time_scale);
+        self.draw_ruler(sb, rule_width, height, offset, label_height, RULE_HEIGHT, rule_xoffs, 0., &self.time_range, &self.time_scale);
         // Bottom ruler
-        self.draw_ruler(sb, text, rule_width, height, offset, label_height, RULE_HEIGHT, rule_xoffs, height - RULE_HEIGHT, &self.max_range, &self.time_scale);
+        self.draw_ruler(sb, rule_width, height, offset, label_height, RULE_HEIGHT, rule_xoffs, height - RULE_HEIGHT, &self.max_range, &self.time_scale);
         // Draw locator box for current zoom level
         let sig_xoffs : f64 = (width - SCROLL_WIDTH) * (self.col_signame + self.col_value);
         let sig_width : f64 = (width - SCROLL_WIDTH) - sig_xoffs;
@@ -810,37 +1322,132 @@ impl Chart {
         //const SIGNAL_HEIGHT : f64 = 16.;
         let mut height_acc : f64 = 0.0;
         let num_signals = datas.get_num_signals();
-        for sig in 0..num_signals {
+        for row in self.rows(num_signals) {
             use crate::datastore::SigType;
+            let sig = match row {
+                Row::Signal(sig) => sig,
+                Row::Group(num) => {
+                    let y_pos = RULE_HEIGHT + height_acc;
+                    self.draw_group(sb, datas, width, offset, label_height, crate::HEIGHT_DIGITAL, y_pos, &self.groups[num]);
+                    height_acc += crate::HEIGHT_DIGITAL;
+                    continue;
+                }
+            };
             let (sigtype, idx) = datas.get_signal_type_idx(sig);
 
+            // Highlight mismatches behind the waveform
+            let y_pos = RULE_HEIGHT + height_acc;
+            let signal_height = datas.get_signal_height(sig);
+            for (_, span) in self.mismatches.iter().filter(|m| m.0 == sig) {
+                if span[1] < self.time_range[0] || span[0] > self.time_range[1] { continue; }
+                let x0 = self.time_to_xpos(span[0].max(self.time_range[0]), &self.time_range, sig_xoffs, sig_width);
+                let x1 = self.time_to_xpos(span[1].min(self.time_range[1]), &self.time_range, sig_xoffs, sig_width);
+                sb.fill(
+                    Fill::NonZero,
+                    offset,
+                    &DIFF_COL,
+                    None,
+                    &Rect::new(x0, y_pos, x1.max(x0 + 1.), y_pos + signal_height),
+                );
+            }
+
+            if self.selected == Some(sig) {
+                sb.fill(
+                    Fill::NonZero,
+                    offset,
+                    &SEL_COL,
+                    None,
+                    &Rect::new(0., y_pos, sig_xoffs, y_pos + signal_height),
+                );
+            }
+
+            // Selected signal is labelled with the edges stepped to
+            let label = |smpl_label: String| if self.selected == Some(sig) {
+                smpl_label + self.edge_filter.label_suffix()
+            } else {
+                smpl_label
+            };
+
             // Digital signal(s)
             let signal_height = if sigtype == SigType::Digital {
-                let smpl_ref : &mut RefCell<dyn Sampler<bool>> = datas.get_dig_sampler(idx).unwrap();
+                let smpl_ref : &RefCell<dyn Sampler<bool>> = datas.get_dig_sampler(idx).unwrap();
                 let smpl = smpl_ref.borrow_mut();
                 let signal_height = smpl.get_height();
                 let y_pos = RULE_HEIGHT + height_acc;
-                self.draw_digital(sb, text, width, height, offset, label_height, signal_height, y_pos, smpl);
+                let label = label(smpl.get_label());
+                self.draw_digital(sb, width, height, offset, label_height, signal_height, y_pos, &label, smpl);
                 signal_height
 
             // Analog signal(s)
             } else {
-                let smpl_ref : &mut RefCell<dyn Sampler<f32>> = datas.get_ana_sampler(idx).unwrap();
+                let smpl_ref : &RefCell<dyn Sampler<f32>> = datas.get_ana_sampler(idx).unwrap();
                 let mut smpl = smpl_ref.borrow_mut();
                 smpl.set_iter_scale(&self.time_range, &self.time_scale, sig_width);
                 let signal_height = smpl.get_height();
                 let y_pos = RULE_HEIGHT + height_acc;
-                self.draw_analog(sb, text, width, height, offset, label_height, signal_height, y_pos, smpl);
+                let label = label(smpl.get_label());
+                self.draw_analog(sb, width, height, offset, label_height, signal_height, y_pos, &label, smpl);
                 signal_height
             };
             height_acc += signal_height;
+
+            // Decoded rows under the signal
+            for dec in datas.get_signal_decoders(sig) {
+                let y_pos = RULE_HEIGHT + height_acc;
+                self.draw_decoded(sb, width, offset, label_height, crate::HEIGHT_DIGITAL, y_pos, datas.get_decoder(dec));
+                height_acc += crate::HEIGHT_DIGITAL;
+            }
         }
 
         sb.pop_layer();
 
-        // Cursor
-        if let Some(curs) = self.cursor {
-            // Draw main cursor across top scrollbar and waveform window
+        // Markers with labels in the top ruler, and as ticks on the bottom ruler
+        for marker in &self.markers {
+            let col = Brush::Solid(MARKER_COLS[marker.colour % MARKER_COLS.len()]);
+            if marker.time >= self.time_range[0] && marker.time <= self.time_range[1] {
+                let marker_x = (marker.time - self.time_range[0]) * rule_width / (self.time_range[1] - self.time_range[0]) + rule_xoffs;
+                let vline = [
+                    MoveTo((marker_x, 0.).into()),
+                    LineTo((marker_x, height - RULE_HEIGHT).into()),
+                ];
+                sb.stroke(
+                    &Stroke::new(1.0),
+                    offset,
+                    &col,
+                    None,
+                    &vline,
+                );
+                sb.fill(
+                    Fill::NonZero,
+                    offset,
+                    &BG_COL,
+                    None,
+                    &Rect::new(marker_x + 1., 1., marker_x + 2. + 0.6 * label_height as f64 * marker.name.len() as f64, RULE_HEIGHT - 1.),
+                );
+                sb.text(
+                    label_height,
+                    Some(&col),
+                    offset * Affine::translate((marker_x + 2., RULE_HEIGHT - 5.)),
+                    &marker.name,
+                );
+            }
+            let marker_x = (marker.time - self.max_range[0]) * rule_width / (self.max_range[1] - self.max_range[0]) + rule_xoffs;
+            let tick = [
+                MoveTo((marker_x, height - RULE_HEIGHT).into()),
+                LineTo((marker_x, height - 0.5 * RULE_HEIGHT).into()),
+            ];
+            sb.stroke(
+                &Stroke::new(2.0),
+                offset,
+                &col,
+                None,
+                &tick,
+            );
+        }
+
+        // Cursors across top scrollbar and waveform window, and on the bottom scrollbar
+        for (curs, col, global_col) in [(self.cursor, &CURS_COL, &XSCRLCURS_COL), (self.cursor2, &CURS2_COL, &CURS2_COL)] {
+            let Some(curs) = curs else { continue };
             if curs >= self.time_range[0] && curs <= self.time_range[1] {
                 let cursor_x = (curs - self.time_range[0]) * rule_width / (self.time_range[1] - self.time_range[0]) + rule_xoffs;
                 //println!("Cursor {} -> {} [{}]", self.cursor, cursor_x, width);
@@ -851,28 +1458,13 @@ impl Chart {
                 sb.stroke(
                     &Stroke::new((0.6) as f32),
                     offset,
-                    &CURS_COL,
+                    col,
                     None,
                     &vline,
                 );
             }
-            // Cursor time is displayed at top left
-            let curs_unit = TimeScale {
-                time: curs,
-                unit: self.time_scale.unit,
-            };
-            let label = crate::fmt_time_unit(curs_unit);
-            text.add(
-                sb,
-                None,
-                label_height,
-                Some(&Brush::Solid(Color::YELLOW)),
-                offset * Affine::translate((0., RULE_HEIGHT - 5.)),
-                &label,
-            );
             // Draw global cursor (bottom scrollbar)
             let cursor_x = (curs - self.max_range[0]) * rule_width / (self.max_range[1] - self.max_range[0]) + rule_xoffs;
-            //println!("Cursor {} -> {} [{}]", self.cursor, cursor_x, width);
             let vline = [
                 MoveTo((cursor_x, height - RULE_HEIGHT).into()),
                 LineTo((cursor_x, height).into()),
@@ -880,17 +1472,40 @@ impl Chart {
             sb.stroke(
                 &Stroke::new((1.0) as f32),
                 offset,
-                &XSCRLCURS_COL,
+                global_col,
                 None,
                 &vline,
             );
         }
+        // Cursor time is displayed at top left, with the time to the second cursor and its
+        // frequency
+        if let Some(curs) = self.cursor {
+            let curs_unit = TimeScale {
+                time: curs,
+                unit: self.time_scale.unit,
+            };
+            let mut label = crate::fmt_time_unit(curs_unit);
+            if let Some(curs2) = self.cursor2 {
+                let delta = curs2 - curs;
+                let delta_unit = TimeScale { time: delta, unit: self.time_scale.unit };
+                label += &format!("  \u{394} {}", crate::fmt_time_unit(delta_unit));
+                if delta != 0. {
+                    label += &format!("  {}", crate::fmt_freq(1. / (delta.abs() * self.time_scale.secs())));
+                }
+            }
+            sb.text(
+                label_height,
+                Some(&Brush::Solid(Color::YELLOW)),
+                offset * Affine::translate((0., RULE_HEIGHT - 5.)),
+                &label,
+            );
+        }
 
         // Column headers
-        self.draw_colhdr(sb, text, width, height, offset);
+        self.draw_colhdr(sb, width, height, offset);
 
         // Y scrollbar
-        self.draw_yscroll(sb, text, width, height, offset);
+        self.draw_yscroll(sb, width, height, offset);
     }
 }
 
@@ -0,0 +1,693 @@
+//! Headless commands, run without opening a window
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use clap::{Args, Subcommand};
+use wavefmt::FieldType;
+use wavefmt::wavedrom::quote;
+use crate::{diff, export, eye, fmt_freq, jitter, measure, parse_time, spectrum, xy, DataStore, FileType, Result, TimeRel, HEIGHT_DIGITAL, RULE_HEIGHT, TIMESCALE};
+use crate::chart::Chart;
+use crate::datastore::SigType;
+use crate::search::Search;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Export signals over a time window to a VCD file
+    ExportVcd {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        window: TimeWindow,
+    },
+    /// Draw chart view of a time window to an SVG file
+    ExportSvg {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Cursor time
+        #[arg(long)]
+        cursor: Option<String>,
+
+        /// Image width (pixels)
+        #[arg(long, default_value_t = 1044.)]
+        width: f64,
+
+        /// Image height (pixels), defaults to fitting all signals
+        #[arg(long)]
+        height: Option<f64>,
+    },
+    /// Sample digital and bus signals on a clock into a WaveDrom (WaveJSON) diagram
+    ExportWavedrom {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Clock signal name, defaults to the first digital signal named like a clock
+        #[arg(long)]
+        clock: Option<String>,
+
+        /// Signal or scope to export (repeatable), defaults to all
+        #[arg(short, long = "signal", value_name = "NAME")]
+        signals: Vec<String>,
+    },
+    /// Export signals over a time window to a CSV file, a row per transition unless a
+    /// sampling interval or clock is given
+    ExportCsv {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Sample at a fixed interval, e.g. `10ns`
+        #[arg(long, value_name = "TIME", conflicts_with = "clock")]
+        interval: Option<String>,
+
+        /// Sample on rising edges of a clock signal
+        #[arg(long, value_name = "NAME")]
+        clock: Option<String>,
+
+        /// Signal or scope to export (repeatable), defaults to all
+        #[arg(short, long = "signal", value_name = "NAME")]
+        signals: Vec<String>,
+    },
+    /// List signals with their types and scopes
+    List {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the value of a signal at a time
+    Value {
+        /// Signal name
+        signal: String,
+
+        /// Time, e.g. `150ns` (plain numbers are in ps)
+        time: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the value of a signal at `t0` and its changes up to `t1`, with times in ps
+    Changes {
+        /// Signal name
+        signal: String,
+
+        /// Start time
+        t0: String,
+
+        /// End time
+        t1: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compare two wave files, print the first mismatches and fail if there are any
+    Diff {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Largest difference between analog values treated as a match
+        #[arg(long, default_value_t = 0.)]
+        tolerance: f64,
+
+        /// Number of mismatches printed
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Decode a protocol from digital signals, printing the start and end (in ps) and
+    /// label of each decoded item, e.g. bytes
+    Decode {
+        /// Decoder, e.g. `uart:top.tx:115200:8N1`, `spi:sclk:mosi:miso:cs:0:8` or `i2c:scl:sda`
+        #[arg(value_name = "PROTOCOL:SIGNALS...")]
+        spec: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the times (in ps) a condition over signals becomes true, e.g.
+    /// `state == 5 && valid`, and fail if it never does
+    Search {
+        /// Condition
+        condition: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Number of match times printed, all by default
+        #[arg(short = 'n', long)]
+        count: Option<usize>,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Measure a signal over a time window. Analog: min, max, mean, RMS, peak-to-peak and
+    /// integral (in value seconds). Digital: edges, average, min and max period, frequency
+    /// (Hz), duty cycle (fraction) and min and max high and low pulse widths.
+    Measure {
+        /// Signal name
+        signal: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Analyse clock jitter from the rising edges of a signal over a time window: period
+    /// statistics, cycle-to-cycle jitter and time interval error (in ps)
+    Jitter {
+        /// Signal name
+        signal: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Level an analog signal rises through, midway between its min and max by default
+        #[arg(long)]
+        level: Option<f64>,
+
+        /// Write a row per edge with its period, cycle-to-cycle change and time interval error
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Print JSON, with the period histogram
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fold an analog signal on its unit interval into an eye diagram over a time window,
+    /// and print the unit interval, eye height and eye width (in ps)
+    Eye {
+        /// Signal name
+        signal: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Bit period such as `400ps`, or a clock signal to recover it from, estimated from
+        /// the shortest bit by default
+        #[arg(long, value_name = "PERIOD|CLOCK")]
+        ui: Option<String>,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the spectrum of an analog signal over a time window, a line of frequency (Hz)
+    /// and magnitude (dB) per bin, and report its peak
+    Spectrum {
+        /// Signal name
+        signal: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Window function: hann, blackman or flattop
+        #[arg(long = "window", value_name = "FUNCTION", default_value = "hann")]
+        function: spectrum::Window,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one analog signal against another over a time window, a line of time (in ps),
+    /// x and y at each time either changes
+    Xy {
+        /// Signal on the x axis
+        x: String,
+
+        /// Signal on the y axis
+        y: String,
+
+        #[command(flatten)]
+        inputs: Inputs,
+
+        #[command(flatten)]
+        window: TimeWindow,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the time range (in ps) covered by the waves
+    Range {
+        #[command(flatten)]
+        inputs: Inputs,
+
+        /// Print JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Wave files to read
+#[derive(Args, Debug)]
+pub struct Inputs {
+    /// Wave files (VCD, CSV or WaveJSON), `-` reads from stdin
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    wavedrom: WaveDromCell,
+
+    /// Add a signal derived from an expression, e.g. `busy = valid & !ready` (repeatable)
+    #[arg(long = "derive", value_name = "[NAME=]EXPR")]
+    derived: Vec<String>,
+
+    /// Add a digital signal comparing an analog one with high and low (hysteresis)
+    /// thresholds, e.g. `rx=top.vrx:1.6:1.2` (repeatable)
+    #[arg(long = "threshold", value_name = "[NAME=]SIGNAL:HIGH[:LOW]")]
+    thresholds: Vec<String>,
+}
+
+/// Time length of the cells of loaded WaveJSON diagrams
+#[derive(Args, Debug)]
+pub struct WaveDromCell {
+    /// Length of a WaveDrom diagram cell
+    #[arg(long, value_name = "TIME", default_value = "10ns")]
+    wavedrom_cell: String,
+}
+
+impl WaveDromCell {
+    pub fn apply(&self, datas: &mut DataStore) -> Result<()> {
+        let Some(period) = parse_time(&self.wavedrom_cell, &datas.get_timescale()) else {
+            let msg = format!("Invalid WaveDrom cell length: {}", self.wavedrom_cell);
+            return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+        };
+        datas.set_wavedrom_period(period);
+        Ok(())
+    }
+}
+
+/// Time window, defaults to the whole wave
+#[derive(Args, Debug)]
+pub struct TimeWindow {
+    /// Start time, e.g. `150ns` (plain numbers are in ps)
+    #[arg(long)]
+    from: Option<String>,
+
+    /// End time
+    #[arg(long)]
+    to: Option<String>,
+}
+
+impl TimeWindow {
+    fn range(&self, datas: &DataStore) -> Result<[TimeRel; 2]> {
+        let (tstart, tend) = datas.get_range();
+        let tstart = match &self.from { Some(s) => time_arg(s, datas)?, None => tstart };
+        let tend = match &self.to { Some(s) => time_arg(s, datas)?, None => tend };
+        Ok([tstart, tend])
+    }
+}
+
+fn time_arg(s: &str, datas: &DataStore) -> Result<TimeRel> {
+    match parse_time(s, &datas.get_timescale()) {
+        Some(t) => Ok(t),
+        None => Err(Box::new(Error::new(ErrorKind::InvalidInput, format!("Invalid time: {s}")))),
+    }
+}
+
+pub fn run(cmd: Command) -> Result<()> {
+    match cmd {
+        Command::ExportVcd { inputs, output, window } => {
+            let datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let sigs : Vec<usize> = (0..datas.get_num_signals()).collect();
+            export::export_vcd(&datas, &sigs, &range, &output)
+        }
+        Command::ExportSvg { inputs, output, window, cursor, width, height } => {
+            let datas = load(inputs)?;
+            let mut chart = Chart::new();
+            let (tstart, tend) = datas.get_range();
+            chart.set_range(&window.range(&datas)?, &TIMESCALE);
+            chart.set_max_range(&[tstart, tend], &TIMESCALE);
+            chart.cursor = cursor.map(|s| time_arg(&s, &datas)).transpose()?;
+            let height = height.unwrap_or_else(|| {
+                let signals : f64 = (0..datas.get_num_signals()).map(|sig| datas.get_signal_height(sig)).sum();
+                signals + datas.get_num_decoders() as f64 * HEIGHT_DIGITAL + 2. * RULE_HEIGHT
+            });
+            export::export_svg(&chart, &datas, width, height, &output)
+        }
+        Command::ExportWavedrom { inputs, output, window, clock, signals } => {
+            let datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let Some(clock) = export::find_clock(&datas, clock.as_deref()) else {
+                return Err(Box::new(Error::new(ErrorKind::NotFound, "Clock signal not found")));
+            };
+            let sigs = select(&datas, &signals);
+            export::export_wavedrom(&datas, clock, &sigs, &range, &output)
+        }
+        Command::ExportCsv { inputs, output, window, interval, clock, signals } => {
+            let datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let mode = if let Some(interval) = interval {
+                let step = time_arg(&interval, &datas)?;
+                if step <= 0. {
+                    return Err(Box::new(Error::new(ErrorKind::InvalidInput, "Sampling interval must be positive")));
+                }
+                export::CsvMode::Interval(step)
+            } else if let Some(clock) = clock {
+                let Some(clock) = export::find_clock(&datas, Some(&clock)) else {
+                    return Err(Box::new(Error::new(ErrorKind::NotFound, "Clock signal not found")));
+                };
+                export::CsvMode::Clock(clock)
+            } else {
+                export::CsvMode::Transitions
+            };
+            let sigs = select(&datas, &signals);
+            export::export_csv(&datas, &sigs, &range, mode, &output)
+        }
+        Command::List { inputs, json } => {
+            let datas = load(inputs)?;
+            let mut items = Vec::new();
+            for sig in 0..datas.get_num_signals() {
+                let field = datas.get_signal_field(sig);
+                let scope = field.name.rsplit_once('.').map(|(scope, _)| scope).unwrap_or_default();
+                let (ftype, width) = match field.ftype {
+                    FieldType::DigiBus(width) => ("bus", width),
+                    FieldType::Analog => ("analog", 1),
+                    _ => ("digital", 1),
+                };
+                if json {
+                    items.push(format!("{{\"name\": {}, \"scope\": {}, \"type\": \"{ftype}\", \"width\": {width}}}",
+                        quote(&field.name), quote(scope)));
+                } else {
+                    println!("{}\t{ftype}\t{width}\t{scope}", field.name);
+                }
+            }
+            if json {
+                println!("[{}]", items.join(", "));
+            }
+            Ok(())
+        }
+        Command::Value { signal, time, inputs, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let t = time_arg(&time, &datas)?;
+            let ftype = datas.get_signal_field(sig).ftype;
+            let val = export::value_at(&datas, sig, t);
+            if json {
+                println!("{{\"signal\": {}, \"time\": {}, \"value\": {}}}", quote(&signal), json_num(t), json_value(ftype, val));
+            } else {
+                println!("{}", export::fmt_value(ftype, val));
+            }
+            Ok(())
+        }
+        Command::Changes { signal, t0, t1, inputs, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let range = [time_arg(&t0, &datas)?, time_arg(&t1, &datas)?];
+            let ftype = datas.get_signal_field(sig).ftype;
+            let (initial, changes) = export::read_changes(&datas, sig, &range)?;
+            let rows = std::iter::once((range[0], initial)).chain(changes);
+            if json {
+                let rows : Vec<String> = rows.map(|(t, val)| format!("[{}, {}]", json_num(t), json_value(ftype, val))).collect();
+                println!("{{\"signal\": {}, \"changes\": [{}]}}", quote(&signal), rows.join(", "));
+            } else {
+                for (t, val) in rows {
+                    println!("{t}\t{}", export::fmt_value(ftype, val));
+                }
+            }
+            Ok(())
+        }
+        Command::Diff { inputs, window, tolerance, count, json } => {
+            if inputs.paths.len() != 2 {
+                return Err(Box::new(Error::new(ErrorKind::InvalidInput, "Comparing waves needs two wave files")));
+            }
+            let datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let wdiff = diff::compare(&datas, [0, 1], &range, tolerance)?;
+            for name in &wdiff.unpaired {
+                eprintln!("Only in one file: {name}");
+            }
+            let mismatches = wdiff.mismatches();
+            if json {
+                let items : Vec<String> = mismatches.iter().take(count).map(|(name, span)| {
+                    format!("{{\"signal\": {}, \"start\": {}, \"end\": {}}}", quote(name), json_num(span[0]), json_num(span[1]))
+                }).collect();
+                println!("{{\"mismatches\": {}, \"first\": [{}]}}", mismatches.len(), items.join(", "));
+            } else {
+                for (name, span) in mismatches.iter().take(count) {
+                    println!("{}\t{}\t{name}", span[0], span[1]);
+                }
+            }
+            if mismatches.is_empty() {
+                Ok(())
+            } else {
+                let msg = format!("{} mismatches in {} signals", mismatches.len(), wdiff.diffs.len());
+                Err(Box::new(Error::other(msg)))
+            }
+        }
+        Command::Decode { spec, inputs, window, json } => {
+            let mut datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let dec = datas.add_decoder(&spec)?;
+            let segments = datas.get_decoder(dec).decode(&range)?;
+            if json {
+                let items : Vec<String> = segments.iter().map(|seg| {
+                    let value = seg.value.map_or("null".to_string(), |v| v.to_string());
+                    format!("{{\"start\": {}, \"end\": {}, \"value\": {value}, \"label\": {}, \"error\": {}}}",
+                        json_num(seg.span[0]), json_num(seg.span[1]), quote(&seg.label), seg.error)
+                }).collect();
+                println!("{{\"decoder\": {}, \"events\": [{}]}}", quote(&datas.get_decoder(dec).get_label()), items.join(", "));
+            } else {
+                for seg in segments {
+                    println!("{}\t{}\t{}", seg.span[0], seg.span[1], seg.label);
+                }
+            }
+            Ok(())
+        }
+        Command::Search { condition, inputs, window, count, json } => {
+            let datas = load(inputs)?;
+            let range = window.range(&datas)?;
+            let found = Search::new(&datas, &condition, &range)?;
+            let times = &found.matches()[..count.unwrap_or(usize::MAX).min(found.matches().len())];
+            if json {
+                let times : Vec<String> = times.iter().map(|&t| json_num(t)).collect();
+                println!("{{\"condition\": {}, \"matches\": {}, \"times\": [{}]}}",
+                    quote(&found.text), found.matches().len(), times.join(", "));
+            } else {
+                for t in times {
+                    println!("{t}");
+                }
+            }
+            if found.matches().is_empty() {
+                Err(Box::new(Error::other(format!("No matches of {}", found.text))))
+            } else {
+                eprintln!("{} matches", found.matches().len());
+                Ok(())
+            }
+        }
+        Command::Measure { signal, inputs, window, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let range = window.range(&datas)?;
+            let items : Vec<(&str, Option<f64>)> = match datas.get_signal_type_idx(sig).0 {
+                SigType::Digital => measure::digital(&datas, sig, &range)?.items().to_vec(),
+                SigType::Analog => measure::analog(&datas, sig, &range)?.items().iter().map(|&(name, val)| (name, Some(val))).collect(),
+            };
+            if json {
+                let items : Vec<String> = items.iter().map(|(name, val)| {
+                    format!("{}: {}", quote(name), val.map_or("null".to_string(), json_num))
+                }).collect();
+                println!("{{\"signal\": {}, \"start\": {}, \"end\": {}, {}}}", quote(&signal), json_num(range[0]), json_num(range[1]), items.join(", "));
+            } else {
+                for (name, val) in items {
+                    println!("{name}\t{}", val.map_or("-".to_string(), |val| val.to_string()));
+                }
+            }
+            Ok(())
+        }
+        Command::Jitter { signal, inputs, window, level, csv, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let range = window.range(&datas)?;
+            let jit = jitter::analyse(&datas, sig, &range, level)?;
+            if let Some(path) = csv {
+                jit.write_csv(datas.get_timescale(), &path)?;
+                eprintln!("Wrote jitter CSV to path {path:?}");
+            }
+            if json {
+                let items : Vec<String> = jit.items().iter().map(|&(name, val)| format!("{}: {}", quote(name), json_num(val))).collect();
+                let hist = jit.histogram(jitter::BINS);
+                let counts : Vec<String> = hist.counts.iter().map(|c| c.to_string()).collect();
+                println!("{{\"signal\": {}, \"edges\": {}, {}, \"histogram\": {{\"start\": {}, \"bin_width\": {}, \"counts\": [{}]}}}}",
+                    quote(&signal), jit.edges.len(), items.join(", "), json_num(hist.start), json_num(hist.bin_width), counts.join(", "));
+            } else {
+                println!("edges\t{}", jit.edges.len());
+                for (name, val) in jit.items() {
+                    println!("{name}\t{val}");
+                }
+            }
+            Ok(())
+        }
+        Command::Eye { signal, inputs, window, ui, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let range = window.range(&datas)?;
+            let folding = ui.map(|text| eye::Folding::parse(&datas, &text)).transpose()?;
+            let eye = eye::eye(&datas, sig, &range, folding, eye::BINS)?;
+            let items = [("ui", eye.ui), ("height", eye.height()), ("width", eye.width()), ("threshold", eye.threshold)];
+            if json {
+                let items : Vec<String> = items.iter().map(|&(name, val)| format!("{}: {}", quote(name), json_num(val))).collect();
+                println!("{{\"signal\": {}, {}}}", quote(&signal), items.join(", "));
+            } else {
+                for (name, val) in items {
+                    println!("{name}\t{val}");
+                }
+            }
+            Ok(())
+        }
+        Command::Spectrum { signal, inputs, window, function, json } => {
+            let datas = load(inputs)?;
+            let sig = find_signal(&datas, &signal)?;
+            let range = window.range(&datas)?;
+            let spec = spectrum::spectrum(&datas, sig, &range, function)?;
+            let (peak_hz, peak_db) = spec.peak().unwrap_or((0., spec.db[0]));
+            if json {
+                let db : Vec<String> = spec.db.iter().map(|&db| json_num(db)).collect();
+                println!("{{\"signal\": {}, \"window\": {}, \"bin_hz\": {}, \"peak_hz\": {}, \"peak_db\": {}, \"db\": [{}]}}",
+                    quote(&signal), quote(&function.to_string()), json_num(spec.bin_hz), json_num(peak_hz), json_num(peak_db), db.join(", "));
+            } else {
+                for (k, db) in spec.db.iter().enumerate() {
+                    println!("{}\t{db}", k as f64 * spec.bin_hz);
+                }
+            }
+            eprintln!("Peak {} at {peak_db:.1} dB", fmt_freq(peak_hz));
+            Ok(())
+        }
+        Command::Xy { x, y, inputs, window, json } => {
+            let datas = load(inputs)?;
+            let (x_sig, y_sig) = (find_signal(&datas, &x)?, find_signal(&datas, &y)?);
+            let range = window.range(&datas)?;
+            let traj = xy::trajectory(&datas, x_sig, y_sig, &range)?;
+            if json {
+                let points : Vec<String> = traj.points.iter().map(|(t, x, y)| format!("[{}, {}, {}]", json_num(*t), json_num(*x), json_num(*y))).collect();
+                println!("{{\"x\": {}, \"y\": {}, \"points\": [{}]}}", quote(&x), quote(&y), points.join(", "));
+            } else {
+                for (t, x, y) in traj.points {
+                    println!("{t}\t{x}\t{y}");
+                }
+            }
+            Ok(())
+        }
+        Command::Range { inputs, json } => {
+            let datas = load(inputs)?;
+            let (tstart, tend) = datas.get_range();
+            if json {
+                println!("{{\"start\": {}, \"end\": {}}}", json_num(tstart), json_num(tend));
+            } else {
+                println!("{tstart}\t{tend}");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn find_signal(datas: &DataStore, name: &str) -> Result<usize> {
+    match (0..datas.get_num_signals()).find(|&sig| datas.get_signal_field(sig).name == name) {
+        Some(sig) => Ok(sig),
+        None => Err(Box::new(Error::new(ErrorKind::NotFound, format!("Signal not found: {name}")))),
+    }
+}
+
+/// Format a number for JSON output, NaN and infinities as null
+pub(crate) fn json_num(val: f64) -> String {
+    if val.is_finite() { val.to_string() } else { "null".to_string() }
+}
+
+/// Format a signal value for JSON output
+fn json_value(ftype: FieldType, val: f64) -> String {
+    if val.is_finite() { export::fmt_value(ftype, val) } else { "null".to_string() }
+}
+
+/// Signals matching names (or scopes), all signals when no names are given
+fn select(datas: &DataStore, names: &[String]) -> Vec<usize> {
+    (0..datas.get_num_signals()).filter(|&sig| {
+        let name = datas.get_signal_field(sig).name;
+        names.is_empty() || names.iter().any(|n| name == *n || name.starts_with(&format!("{n}.")))
+    }).collect()
+}
+
+/// Load wave files completely
+fn load(inputs: Inputs) -> Result<DataStore> {
+    let mut datas = DataStore::new(TIMESCALE);
+    inputs.wavedrom.apply(&mut datas)?;
+    for path in inputs.paths {
+        if let Err(e) = datas.load_wave(path.clone(), FileType::TryAny) {
+            let msg = format!("Unable to load wave {}: {e}", path.display());
+            return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+        }
+    }
+    datas.read_all()?;
+    for def in inputs.derived {
+        datas.add_derived(&def)?;
+    }
+    for def in inputs.thresholds {
+        datas.add_threshold(&def)?;
+    }
+    Ok(datas)
+}
@@ -1,14 +1,29 @@
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
+use std::ops::Range;
 use std::path::PathBuf;
-use crate::{AnaSigGen, DigiSig, DigiSigGen, Result, Sampler, SineGen, /*TimeRel,*/ TimeScale};
-use wavefmt::{FieldType, WaveFile};
+use std::sync::mpsc::Receiver;
+use crate::{AnaSig, AnaSigGen, Decoder, Derived, DerivedInput, DigiSig, DigiSigGen, Expr, PatternGen, Result, RingBuf, RingSig, Sampler, SineGen, Threshold, TimeRel, TimeScale};
+use crate::decode::{DecoderInput, I2c, Spi, Uart, UartConfig};
+use crate::stream::ConnMsg;
+use wavefmt::{CsvFile, FieldInfo, FieldType, Format, IndexedWave, VcdFile, WaveFile, WaveInput};
+use wavefmt::stream::StreamMsg;
+use wavefmt::wavedrom;
+
+/// Number of bytes read from the start of a file to detect its format
+const DETECT_LEN : usize = 1024;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FileType {
+    TryAny,
+    #[allow(dead_code)]
+    Vcd,
+    #[allow(dead_code)]
+    Csv,
     #[allow(dead_code)]
-    TryAny, // Not yet
+    WaveDrom,
 }
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SigType {
@@ -16,11 +31,35 @@ pub enum SigType {
     Analog,
 }
 
+/// Live sample streams, with the state of each connection
+struct StreamSource {
+    rx: Receiver<ConnMsg>,
+    capacity: usize,
+    conns: HashMap<usize, StreamConn>,
+}
+
+/// Signal buffers of a stream connection
+struct StreamConn {
+    /// Ratio of the connection's time unit to the timescale
+    ratio: f64,
+    /// Time of the latest batch, earlier batches are dropped
+    last: TimeRel,
+    bufs: Vec<Rc<RefCell<RingBuf>>>,
+}
+
 pub struct DataStore {
     timescale: TimeScale,
     sigs   : Vec<(SigType, usize)>,
+    srcs   : Vec<Option<(usize, usize)>>, // Wave and field each signal was read from
+    files  : Vec<Range<usize>>, // Signals added by each `load_wave`
+    derived: HashMap<usize, FieldInfo>, // Name and type of signals derived from others
+    decoders: Vec<(usize, Box<dyn Decoder>)>, // Protocol decoders and the signal drawn above each
     digsam : Vec<Rc<RefCell<dyn Sampler<bool>>>>,
     anasam : Vec<Rc<RefCell<dyn Sampler<f32>>>>,
+    waves  : Vec<Rc<RefCell<dyn WaveFile>>>,
+    stream : Option<StreamSource>,
+    gen_ranges : Vec<(f64, f64)>, // Time ranges of signals generated from diagrams
+    wavedrom_period : TimeRel,
 }
 
 impl Default for DataStore {
@@ -28,8 +67,16 @@ impl Default for DataStore {
         Self {
             timescale: Default::default(),
             sigs   : Vec::new(),
+            srcs   : Vec::new(),
+            files  : Vec::new(),
+            derived: HashMap::new(),
+            decoders: Vec::new(),
             digsam : Vec::new(),
             anasam : Vec::new(),
+            waves  : Vec::new(),
+            stream : None,
+            gen_ranges : Vec::new(),
+            wavedrom_period : wavefmt::time_ratio(1e-8, TimeScale::default().secs()),
         }
     }
 }
@@ -39,18 +86,357 @@ impl DataStore {
         Self {
             timescale,
             sigs   : Vec::new(),
+            srcs   : Vec::new(),
+            files  : Vec::new(),
+            derived: HashMap::new(),
+            decoders: Vec::new(),
             digsam : Vec::new(),
             anasam : Vec::new(),
+            waves  : Vec::new(),
+            stream : None,
+            gen_ranges : Vec::new(),
+            wavedrom_period : wavefmt::time_ratio(1e-8, timescale.secs()),
         }
     }
 
+    /// Set length of a WaveDrom diagram cell, used when loading WaveJSON (default 10ns)
+    pub fn set_wavedrom_period(&mut self, period: TimeRel) {
+        self.wavedrom_period = period;
+    }
+
+    /// Load wave file, `-` reads a stream from stdin
     pub fn load_wave(&mut self, path: PathBuf, ftype: FileType) -> Result<()> {
-        match ftype {
+        let mut input = if path.as_os_str() == "-" {
+            WaveInput::stdin()
+        } else {
+            WaveInput::open(&path)?
+        };
+        let format = match ftype {
+            FileType::Vcd => Some(Format::Vcd),
+            FileType::Csv => Some(Format::Csv),
+            FileType::WaveDrom => Some(Format::WaveDrom),
+            FileType::TryAny => wavefmt::detect_format(input.peek(DETECT_LEN)?),
+        };
+        let first = self.sigs.len();
+        match format {
+            Some(Format::Vcd) => {
+                let mut wave = VcdFile::new(input);
+                wave.set_time_unit(self.timescale.secs());
+                self.add_wave(wave, false)?;
+            }
+            Some(Format::Csv) => {
+                let mut wave = CsvFile::new(input);
+                wave.set_time_unit(self.timescale.secs());
+                self.add_wave(wave, true)?;
+            }
+            Some(Format::WaveDrom) => self.load_wavedrom(input)?,
             _ => {
                 let msg = format!("File type: {:?}", ftype);
                 return Err(Box::new(Error::new(ErrorKind::Unsupported, msg)));
             }
         }
+        self.files.push(first..self.sigs.len());
+        Ok(())
+    }
+
+    /// Read wave header and current contents, add a sampler for each of its fields.
+    /// Analog fields are drawn as straight lines between samples when `interpolate` is set.
+    fn add_wave<W: IndexedWave + 'static>(&mut self, mut wave: W, interpolate: bool) -> Result<()> {
+        if !wave.check_format()? {
+            let msg = "Incomplete or invalid wave header";
+            return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+        }
+        wave.poll()?;
+        let wave = Rc::new(RefCell::new(wave));
+        let num_fields = wave.borrow().get_num_fields();
+        for field in 0..num_fields {
+            let ftype = wave.borrow().get_field_info(field).ftype;
+            match ftype {
+                FieldType::Digital => {
+                    let smpl : Rc<RefCell<dyn Sampler<bool>>> = Rc::new(RefCell::new(DigiSig::new(wave.clone(), field)));
+                    self.sigs.push((SigType::Digital, self.digsam.len()));
+                    self.digsam.push(smpl);
+                }
+                FieldType::Analog | FieldType::DigiBus(_) => {
+                    let step = !(interpolate && ftype == FieldType::Analog);
+                    let smpl : Rc<RefCell<dyn Sampler<f32>>> = Rc::new(RefCell::new(AnaSig::new(wave.clone(), field, step)));
+                    self.sigs.push((SigType::Analog, self.anasam.len()));
+                    self.anasam.push(smpl);
+                }
+                _ => continue,
+            }
+            self.srcs.push(Some((self.waves.len(), field)));
+        }
+        self.waves.push(wave);
+        Ok(())
+    }
+
+    /// Add a generated signal for each lane of a WaveDrom diagram
+    fn load_wavedrom(&mut self, mut input: WaveInput) -> Result<()> {
+        let mut buf = Vec::new();
+        while input.read_chunk(&mut buf, true)? > 0 {}
+        let lanes = wavedrom::read_wavejson(&String::from_utf8_lossy(&buf))?;
+        let period = self.wavedrom_period;
+        for lane in lanes {
+            let (changes, end) = lane.changes();
+            let changes : Vec<(TimeRel, f32)> = changes.into_iter().map(|(t, val)| (t * period, val as f32)).collect();
+            if lane.is_bus() {
+                let smpl = PatternGen::<f32>::new(lane.name, changes, end * period);
+                self.gen_ranges.push(smpl.get_range());
+                self.sigs.push((SigType::Analog, self.anasam.len()));
+                self.anasam.push(Rc::new(RefCell::new(smpl)));
+            } else {
+                let smpl = PatternGen::<bool>::new(lane.name, changes, end * period);
+                self.gen_ranges.push(smpl.get_range());
+                self.sigs.push((SigType::Digital, self.digsam.len()));
+                self.digsam.push(Rc::new(RefCell::new(smpl)));
+            }
+            self.srcs.push(None);
+        }
+        Ok(())
+    }
+
+    /// Add a signal evaluating an expression over other signals, e.g. `valid & ready` or
+    /// `hi = addr[7:4]` to name it, return its signal number
+    pub fn add_derived(&mut self, def: &str) -> Result<usize> {
+        let (name, text) = match def.split_once('=') {
+            Some((name, text)) if !text.starts_with('=') && !name.ends_with(['!', '<', '>']) && !name.contains('"') => (name.trim(), text.trim()),
+            _ => (def.trim(), def.trim()),
+        };
+        let (expr, inputs, ftype) = self.parse_expr(text)?;
+        let sig = self.sigs.len();
+        if ftype == FieldType::Digital {
+            let smpl = Derived::<bool>::new(name.to_string(), expr, inputs, self.timescale);
+            self.sigs.push((SigType::Digital, self.digsam.len()));
+            self.digsam.push(Rc::new(RefCell::new(smpl)));
+        } else {
+            let smpl = Derived::<f32>::new(name.to_string(), expr, inputs, self.timescale);
+            self.sigs.push((SigType::Analog, self.anasam.len()));
+            self.anasam.push(Rc::new(RefCell::new(smpl)));
+        }
+        self.srcs.push(None);
+        self.derived.insert(sig, FieldInfo { name: name.to_string(), ftype });
+        Ok(sig)
+    }
+
+    /// Signal that is high while a condition over other signals holds, e.g. `state == 5 && valid`
+    pub fn new_condition(&self, text: &str) -> Result<Derived<bool>> {
+        let (expr, inputs, _) = self.parse_expr(text)?;
+        Ok(Derived::new(text.trim().to_string(), expr, inputs, self.timescale))
+    }
+
+    /// Parse expression over signals, return it with its inputs and result type
+    fn parse_expr(&self, text: &str) -> Result<(Expr, Vec<DerivedInput>, FieldType)> {
+        let mut expr = Expr::parse(text)?;
+        let mut inputs = Vec::new();
+        let mut ftypes = Vec::new();
+        for input in expr.get_names() {
+            let Some(sig) = (0..self.sigs.len()).find(|&sig| self.get_signal_field(sig).name == *input) else {
+                let msg = format!("Unknown signal in expression: {input}");
+                return Err(Box::new(Error::new(ErrorKind::NotFound, msg)));
+            };
+            ftypes.push(self.get_signal_field(sig).ftype);
+            inputs.push(match self.sigs[sig] {
+                (SigType::Digital, idx) => DerivedInput::Digital(self.digsam[idx].clone()),
+                (SigType::Analog, idx) => DerivedInput::Analog(self.anasam[idx].clone()),
+            });
+        }
+        let ftype = expr.bind(&ftypes);
+        Ok((expr, inputs, ftype))
+    }
+
+    /// Add a digital signal from an analog one, going high at a threshold and low at
+    /// another, e.g. `rx=top.vrx:1.6:1.2`. Return its signal number.
+    pub fn add_threshold(&mut self, def: &str) -> Result<usize> {
+        let invalid = || -> Box<dyn std::error::Error> {
+            let msg = format!("Expected [NAME=]SIGNAL:HIGH[:LOW], got {def}");
+            Box::new(Error::new(ErrorKind::InvalidInput, msg))
+        };
+        let (name, spec) = match def.split_once('=') {
+            Some((name, spec)) => (Some(name.trim()), spec.trim()),
+            None => (None, def.trim()),
+        };
+        let (rest, last) = spec.rsplit_once(':').ok_or_else(invalid)?;
+        let last : f32 = last.trim().parse().map_err(|_| invalid())?;
+        let (input, high, low) = match rest.rsplit_once(':').map(|(input, high)| (input, high.trim().parse::<f32>())) {
+            Some((input, Ok(high))) => (input, high, last),
+            _ => (rest, last, last),
+        };
+        let Some(sig) = (0..self.sigs.len()).find(|&sig| self.get_signal_field(sig).name == input) else {
+            let msg = format!("Unknown signal: {input}");
+            return Err(Box::new(Error::new(ErrorKind::NotFound, msg)));
+        };
+        let (SigType::Analog, idx) = self.sigs[sig] else {
+            let msg = format!("Signal is not analog: {input}");
+            return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+        };
+        let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{input}_logic"));
+        let (origin, _) = self.get_range();
+        let smpl = Threshold::new(name.clone(), self.anasam[idx].clone(), high, low, origin, self.timescale);
+        let sig = self.sigs.len();
+        self.sigs.push((SigType::Digital, self.digsam.len()));
+        self.digsam.push(Rc::new(RefCell::new(smpl)));
+        self.srcs.push(None);
+        self.derived.insert(sig, FieldInfo { name, ftype: FieldType::Digital });
+        Ok(sig)
+    }
+
+    /// Add a protocol decoder, drawn under the last of its input signals: UART e.g.
+    /// `con=uart:top.tx:115200:8N1`, SPI (chip select active low, `-` for an unused data
+    /// line) e.g. `spi:sclk:mosi:miso:cs:0:8` with mode and word length, or I2C e.g.
+    /// `i2c:scl:sda`. Return its decoder number.
+    pub fn add_decoder(&mut self, def: &str) -> Result<usize> {
+        let invalid = || -> Box<dyn std::error::Error> {
+            let msg = format!("Expected [NAME=]uart:SIGNAL:BAUD[:FORMAT], spi:SCLK:MOSI:MISO:CS[:MODE[:BITS]] or i2c:SCL:SDA, got {def}");
+            Box::new(Error::new(ErrorKind::InvalidInput, msg))
+        };
+        let (name, spec) = match def.split_once('=') {
+            Some((name, spec)) => (Some(name.trim()), spec.trim()),
+            None => (None, def.trim()),
+        };
+        let (kind, args) = spec.split_once(':').ok_or_else(invalid)?;
+        let args : Vec<&str> = args.split(':').map(|a| a.trim()).collect();
+        let (origin, _) = self.get_range();
+        let (sig, decoder) : (usize, Box<dyn Decoder>) = match (kind.trim(), args.as_slice()) {
+            ("uart", [input, baud, format @ ..]) if format.len() <= 1 => {
+                let config = UartConfig::parse(baud, format.first().unwrap_or(&"8N1"))?;
+                let (sig, smpl) = self.find_digital(input)?;
+                let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{input} uart"));
+                (sig, Box::new(Uart::new(name, smpl, config, origin, self.timescale)))
+            }
+            ("spi", [sclk, mosi, miso, cs, opts @ ..]) if opts.len() <= 2 => {
+                let mode : u32 = opts.first().map_or(Ok(0), |m| m.parse()).ok().filter(|&m| m < 4).ok_or_else(invalid)?;
+                let bits : usize = opts.get(1).map_or(Ok(8), |b| b.parse()).ok().filter(|b| (1..=64).contains(b)).ok_or_else(invalid)?;
+                if *mosi == "-" && *miso == "-" { return Err(invalid()); }
+                let (sclk_sig, sclk) = self.find_digital(sclk)?;
+                let (cs_sig, cs_smpl) = self.find_digital(cs)?;
+                let mut sig = sclk_sig.max(cs_sig);
+                let mut data = |input: &str| -> Result<Option<DecoderInput>> {
+                    if input == "-" { return Ok(None); }
+                    let (data_sig, smpl) = self.find_digital(input)?;
+                    sig = sig.max(data_sig);
+                    Ok(Some(smpl))
+                };
+                let (mosi, miso) = (data(mosi)?, data(miso)?);
+                let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{cs} spi"));
+                (sig, Box::new(Spi::new(name, sclk, mosi, miso, cs_smpl, mode, bits, origin, self.timescale)))
+            }
+            ("i2c", [scl, sda]) => {
+                let (scl_sig, scl_smpl) = self.find_digital(scl)?;
+                let (sda_sig, sda_smpl) = self.find_digital(sda)?;
+                let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{sda} i2c"));
+                (scl_sig.max(sda_sig), Box::new(I2c::new(name, scl_smpl, sda_smpl, origin, self.timescale)))
+            }
+            _ => return Err(invalid()),
+        };
+        self.decoders.push((sig, decoder));
+        Ok(self.decoders.len() - 1)
+    }
+
+    /// Find digital signal by name, return its signal number and sampler
+    fn find_digital(&self, name: &str) -> Result<(usize, DecoderInput)> {
+        let Some(sig) = (0..self.sigs.len()).find(|&sig| self.get_signal_field(sig).name == name) else {
+            let msg = format!("Unknown signal: {name}");
+            return Err(Box::new(Error::new(ErrorKind::NotFound, msg)));
+        };
+        let (SigType::Digital, idx) = self.sigs[sig] else {
+            let msg = format!("Signal is not digital: {name}");
+            return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+        };
+        Ok((sig, self.digsam[idx].clone()))
+    }
+
+    /// Accept live sample streams on `addr` (see `stream::listen`), keeping the latest
+    /// `capacity` samples of each signal
+    pub fn listen(&mut self, addr: &str, capacity: usize) -> Result<()> {
+        let rx = crate::stream::listen(addr)?;
+        self.stream = Some(StreamSource { rx, capacity, conns: HashMap::new() });
+        Ok(())
+    }
+
+    /// Read any data appended to loaded waves (tail mode) or received from streams,
+    /// return true if there was new data
+    pub fn poll(&mut self) -> Result<bool> {
+        let mut added = false;
+        for w in self.waves.iter() {
+            added |= w.borrow_mut().poll()?;
+        }
+        added |= self.poll_stream();
+        if added {
+            self.invalidate_decoders();
+        }
+        Ok(added)
+    }
+
+    /// Read loaded waves to the end, waiting for stdin to be closed
+    pub fn read_all(&mut self) -> Result<()> {
+        for w in self.waves.iter() {
+            w.borrow_mut().read_to_end()?;
+        }
+        self.invalidate_decoders();
+        Ok(())
+    }
+
+    fn invalidate_decoders(&self) {
+        for (_, decoder) in self.decoders.iter() {
+            decoder.invalidate();
+        }
+    }
+
+    fn poll_stream(&mut self) -> bool {
+        let Some(stream) = &self.stream else { return false };
+        let msgs : Vec<ConnMsg> = stream.rx.try_iter().collect();
+        let added = !msgs.is_empty();
+        for (conn, msg) in msgs {
+            match msg {
+                StreamMsg::Declare { timescale, fields } => {
+                    if !(timescale.is_finite() && timescale > 0.) {
+                        eprintln!("Stream connection {conn}: invalid timescale {timescale}");
+                        continue;
+                    }
+                    let ratio = wavefmt::time_ratio(timescale, self.timescale.secs());
+                    let capacity = self.stream.as_ref().unwrap().capacity;
+                    let mut bufs = Vec::new();
+                    for fi in fields {
+                        let digital = fi.ftype == FieldType::Digital;
+                        let buf = Rc::new(RefCell::new(RingBuf::new(capacity, digital)));
+                        if digital {
+                            let smpl : Rc<RefCell<dyn Sampler<bool>>> = Rc::new(RefCell::new(RingSig::<bool>::new(buf.clone(), fi.name)));
+                            self.sigs.push((SigType::Digital, self.digsam.len()));
+                            self.digsam.push(smpl);
+                        } else {
+                            let smpl : Rc<RefCell<dyn Sampler<f32>>> = Rc::new(RefCell::new(RingSig::<f32>::new(buf.clone(), fi.name)));
+                            self.sigs.push((SigType::Analog, self.anasam.len()));
+                            self.anasam.push(smpl);
+                        }
+                        self.srcs.push(None);
+                        bufs.push(buf);
+                    }
+                    let stream = self.stream.as_mut().unwrap();
+                    let entry = stream.conns.entry(conn)
+                        .or_insert(StreamConn { ratio, last: f64::NEG_INFINITY, bufs: Vec::new() });
+                    entry.ratio = ratio;
+                    entry.bufs.extend(bufs);
+                }
+                StreamMsg::Batch { time, values } => {
+                    let stream = self.stream.as_mut().unwrap();
+                    let Some(sc) = stream.conns.get_mut(&conn) else { continue };
+                    // Buffers are searched by time, so it must not go back
+                    let time = time * sc.ratio;
+                    if !time.is_finite() || time < sc.last {
+                        eprintln!("Stream connection {conn}: dropped batch at invalid time {time}");
+                        continue;
+                    }
+                    sc.last = time;
+                    for (sig, val) in values {
+                        if let Some(buf) = sc.bufs.get(sig as usize) {
+                            buf.borrow_mut().push(time, val);
+                        }
+                    }
+                }
+            }
+        }
+        added
     }
 
     pub fn new_test(timescale: TimeScale) -> Self {
@@ -90,30 +476,88 @@ impl DataStore {
         }
         Self {
             timescale,
+            srcs: vec![None; sigs.len()],
+            files: Vec::new(),
+            derived: HashMap::new(),
+            decoders: Vec::new(),
             sigs,
             digsam,
             anasam,
+            waves: Vec::new(),
+            stream: None,
+            gen_ranges: Vec::new(),
+            wavedrom_period: wavefmt::time_ratio(1e-8, timescale.secs()),
         }
     }
 
     /// Get maximum start, end time of all waveforms
     pub fn get_range(&self) -> (f64, f64) {
-        let mut start = 0.0f64;
-        let mut end = 10000000000.0f64;
-        /*
-        for w in self.ws_XXX.iter() {
-            let (tstart, tend) = w.borrow().get_range();
+        let mut ranges : Vec<(f64, f64)> = self.waves.iter().map(|w| w.borrow().get_range()).collect();
+        ranges.extend(self.gen_ranges.iter().copied());
+        if let Some(stream) = &self.stream {
+            for sc in stream.conns.values() {
+                ranges.extend(sc.bufs.iter().filter_map(|b| b.borrow().get_range()));
+            }
+        }
+        if ranges.is_empty() {
+            // Generated test signals, or no data yet
+            return (0.0, 10000000000.0);
+        }
+        let mut start = f64::MAX;
+        let mut end = f64::MIN;
+        for (tstart, tend) in ranges {
             if start > tstart { start = tstart; }
             if end < tend { end = tend; }
         }
-        */
         (start, end)
     }
 
+    pub fn get_timescale(&self) -> TimeScale {
+        self.timescale
+    }
+
     pub fn get_num_signals(&self) -> usize {
         self.sigs.len()
     }
 
+    pub fn get_num_files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Get signals read from the `file`th loaded wave file
+    pub fn get_file_signals(&self, file: usize) -> Range<usize> {
+        self.files[file].clone()
+    }
+
+    /// Get name and type of signal, signals without a source file or expression (generated
+    /// or streamed) are described by their label
+    pub fn get_signal_field(&self, sig: usize) -> FieldInfo {
+        if let Some((wave, field)) = self.srcs[sig] {
+            return self.waves[wave].borrow().get_field_info(field).clone();
+        }
+        if let Some(fi) = self.derived.get(&sig) {
+            return fi.clone();
+        }
+        match self.sigs[sig] {
+            (SigType::Digital, idx) => FieldInfo { name: self.digsam[idx].borrow().get_label(), ftype: FieldType::Digital },
+            (SigType::Analog, idx) => FieldInfo { name: self.anasam[idx].borrow().get_label(), ftype: FieldType::Analog },
+        }
+    }
+
+    /// Get time resolution (in seconds) of the file a signal was read from
+    pub fn get_signal_timescale(&self, sig: usize) -> Option<f64> {
+        let (wave, _) = self.srcs[sig]?;
+        self.waves[wave].borrow().get_timescale()
+    }
+
+    /// Get display height of signal (in pixels)
+    pub fn get_signal_height(&self, sig: usize) -> f64 {
+        match self.sigs[sig] {
+            (SigType::Digital, idx) => self.digsam[idx].borrow().get_height(),
+            (SigType::Analog, idx) => self.anasam[idx].borrow().get_height(),
+        }
+    }
+
     #[allow(dead_code)]
     pub fn get_signal_ypos(&self, sig: usize) -> f64 {
         let mut acc = 0.;
@@ -128,12 +572,25 @@ impl DataStore {
         self.sigs[sig]
     }
 
-    pub fn get_dig_sampler(&mut self, didx: usize) -> Option<&mut RefCell<dyn Sampler<bool>>> {
-        Rc::get_mut(&mut self.digsam[didx])
+    pub fn get_num_decoders(&self) -> usize {
+        self.decoders.len()
+    }
+
+    pub fn get_decoder(&self, dec: usize) -> &dyn Decoder {
+        &*self.decoders[dec].1
+    }
+
+    /// Get decoders drawn under signal
+    pub fn get_signal_decoders(&self, sig: usize) -> Vec<usize> {
+        (0..self.decoders.len()).filter(|&dec| self.decoders[dec].0 == sig).collect()
+    }
+
+    pub fn get_dig_sampler(&self, didx: usize) -> Option<&RefCell<dyn Sampler<bool>>> {
+        self.digsam.get(didx).map(|smpl| &**smpl)
     }
 
-    pub fn get_ana_sampler(&mut self, aidx: usize) -> Option<&mut RefCell<dyn Sampler<f32>>> {
-        Rc::get_mut(&mut self.anasam[aidx])
+    pub fn get_ana_sampler(&self, aidx: usize) -> Option<&RefCell<dyn Sampler<f32>>> {
+        self.anasam.get(aidx).map(|smpl| &**smpl)
     }
 }
 
@@ -0,0 +1,111 @@
+use crate::{Result, TimeRel, TimeScale};
+use super::{Decoder, DecoderInput, Levels, Segment, SegmentCache};
+
+/// I2C decoder, marking start and stop conditions, addresses with the read/write bit, data
+/// bytes and acknowledge bits. Bits are read on rising SCL edges, each segment ends at the
+/// falling SCL edge after its last bit.
+pub struct I2c {
+    label: String,
+    scl: DecoderInput,
+    sda: DecoderInput,
+    /// Start of input data, decoded from so transfers started before the range are complete
+    origin: TimeRel,
+    timescale: TimeScale,
+    cache: SegmentCache,
+}
+
+impl I2c {
+    pub fn new(label: String, scl: DecoderInput, sda: DecoderInput, origin: TimeRel, timescale: TimeScale) -> Self {
+        I2c { label, scl, sda, origin, timescale, cache: SegmentCache::default() }
+    }
+
+    /// Decode segments from `from` up to `end`. After `origin`, `from` is the end of a stop
+    /// condition decoded before.
+    fn decode_range(&self, from: TimeRel, end: TimeRel) -> Result<Vec<Segment>> {
+        let read_range = [from, end];
+        let scl = Levels::read(&*self.scl, &read_range, self.timescale)?;
+        let sda = Levels::read(&*self.sda, &read_range, self.timescale)?;
+        // Changes of both lines in time order, SDA (true) after SCL at the same time
+        let mut changes : Vec<(TimeRel, bool, bool)> = scl.changes().iter().map(|&(t, v)| (t, false, v))
+            .chain(sda.changes().iter().map(|&(t, v)| (t, true, v))).collect();
+        changes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut segments = Vec::new();
+        let mut push = |span: [TimeRel; 2], value: Option<u64>, label: String, error: bool| {
+            segments.push(Segment { span, value, label, error });
+        };
+        let mut scl_level = scl.value_at(read_range[0]);
+        let mut in_frame = false;
+        // Start condition waiting for the first falling SCL edge
+        let mut pending_start : Option<(TimeRel, &str)> = None;
+        let mut seg_start = read_range[0];
+        let mut last_rise = read_range[0];
+        let mut bits : Vec<bool> = Vec::new();
+        let to_byte = |bits: &[bool]| bits.iter().fold(0, |acc, &b| (acc << 1) | b as u64);
+        let mut address = false;
+        for (t, is_sda, level) in changes {
+            if !is_sda {
+                scl_level = level;
+                if !in_frame { continue; }
+                if level {
+                    bits.push(sda.value_at(t));
+                    last_rise = t;
+                    continue;
+                }
+                if let Some((st, label)) = pending_start.take() {
+                    push([st, t], None, label.to_string(), false);
+                } else if bits.len() == 8 {
+                    let byte = to_byte(&bits);
+                    if address {
+                        let rw = if byte & 1 != 0 { "R" } else { "W" };
+                        push([seg_start, t], Some(byte >> 1), format!("Addr {:02X} {rw}", byte >> 1), false);
+                    } else {
+                        push([seg_start, t], Some(byte), format!("{byte:02X}"), false);
+                    }
+                } else if bits.len() == 9 {
+                    let label = if bits[8] { "NACK" } else { "ACK" };
+                    push([seg_start, t], Some(bits[8] as u64), label.to_string(), false);
+                    bits.clear();
+                    address = false;
+                } else {
+                    continue;
+                }
+                seg_start = t;
+            } else if scl_level {
+                if !level {
+                    // Start, or repeated start within a transfer
+                    pending_start = Some((t, if in_frame { "Sr" } else { "S" }));
+                    in_frame = true;
+                    address = true;
+                } else if in_frame {
+                    // The clock rises once before a stop, more bits are a truncated byte
+                    if bits.len() > 1 && pending_start.is_none() {
+                        let partial = to_byte(&bits[..bits.len() - 1]);
+                        push([seg_start, last_rise], None, format!("{partial:02X}?"), true);
+                    }
+                    push([last_rise, t], None, "P".to_string(), false);
+                    in_frame = false;
+                    pending_start = None;
+                }
+                bits.clear();
+            }
+        }
+        Ok(segments)
+    }
+}
+
+impl Decoder for I2c {
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn decode(&self, range: &[TimeRel; 2]) -> Result<Vec<Segment>> {
+        // Bytes within a transfer depend on its start, resume after a stop condition
+        self.cache.get(range, self.origin, |seg| (seg.label == "P").then_some(seg.span[1]),
+                       |from, end| self.decode_range(from, end))
+    }
+
+    fn invalidate(&self) {
+        self.cache.invalidate();
+    }
+}
@@ -0,0 +1,129 @@
+//! Protocol decoders, turning digital signals into rows of labelled segments
+mod i2c;
+mod spi;
+mod uart;
+pub use i2c::I2c;
+pub use spi::Spi;
+pub use uart::{Uart, UartConfig};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::{Result, Sampler, TimeRel, TimeScale};
+
+/// Labelled time span of a decoded row, e.g. a byte
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub span: [TimeRel; 2],
+    /// Decoded value, if the segment holds one
+    pub value: Option<u64>,
+    pub label: String,
+    /// Set for framing, parity or protocol errors
+    pub error: bool,
+}
+
+pub trait Decoder {
+    fn get_label(&self) -> String;
+
+    /// Decode segments overlapping `range`
+    fn decode(&self, range: &[TimeRel; 2]) -> Result<Vec<Segment>>;
+
+    /// Mark segments kept from earlier calls to `decode` as outdated after new input data,
+    /// those near the end are decoded again
+    fn invalidate(&self) {}
+}
+
+/// Segments decoded from the start of input data, kept so each view is not decoded from the
+/// start again. More segments are decoded as needed, resuming after the last complete one.
+#[derive(Default)]
+pub struct SegmentCache {
+    /// End of decoded time, the segments in time order, and whether input data has been
+    /// added since
+    decoded: RefCell<Option<(TimeRel, Vec<Segment>, bool)>>,
+}
+
+impl SegmentCache {
+    /// Segments overlapping `range`. When past the end of those kept, or after new data,
+    /// `decode` is called with the times to decode from and up to: `origin` at first, then
+    /// the time `resume` returns for the latest segment that has one. Segments from that
+    /// time on are decoded again.
+    pub fn get(&self, range: &[TimeRel; 2], origin: TimeRel, resume: impl Fn(&Segment) -> Option<TimeRel>,
+               decode: impl FnOnce(TimeRel, TimeRel) -> Result<Vec<Segment>>) -> Result<Vec<Segment>> {
+        let mut decoded = self.decoded.borrow_mut();
+        if !matches!(&*decoded, Some((end, _, false)) if *end >= range[1]) {
+            // Decode a view ahead, scrolling doesn't decode again each frame
+            let mut end = range[1] + (range[1] - range[0]);
+            let (from, mut segments) = match decoded.take() {
+                Some((prev_end, mut segments, _)) => {
+                    end = end.max(prev_end);
+                    let from = segments.iter().rev().find_map(&resume).unwrap_or(origin);
+                    segments.truncate(segments.partition_point(|seg| seg.span[0] < from));
+                    (from, segments)
+                }
+                None => (origin, Vec::new()),
+            };
+            segments.extend(decode(from, end)?);
+            *decoded = Some((end, segments, false));
+        }
+        let segments = &decoded.as_ref().unwrap().1;
+        let last = segments.partition_point(|seg| seg.span[0] <= range[1]);
+        Ok(segments[..last].iter().filter(|seg| seg.span[1] >= range[0]).cloned().collect())
+    }
+
+    /// Mark kept segments as outdated, see `get`
+    pub fn invalidate(&self) {
+        if let Some(decoded) = self.decoded.borrow_mut().as_mut() {
+            decoded.2 = true;
+        }
+    }
+}
+
+/// Levels of a digital signal over a time range
+pub struct Levels {
+    initial: bool,
+    changes: Vec<(TimeRel, bool)>,
+}
+
+impl Levels {
+    pub fn read(smpl: &RefCell<dyn Sampler<bool>>, range: &[TimeRel; 2], timescale: TimeScale) -> Result<Self> {
+        let smpl = smpl.borrow();
+        let initial = smpl.get_value_at(range[0], timescale);
+        let mut cur = initial;
+        let mut changes = Vec::new();
+        for (val, t) in smpl.iter_range(range)? {
+            if t > range[0] && t <= range[1] && val != cur {
+                changes.push((t, val));
+                cur = val;
+            }
+        }
+        Ok(Self { initial, changes })
+    }
+
+    pub fn value_at(&self, t: TimeRel) -> bool {
+        match self.changes.partition_point(|c| c.0 <= t) {
+            0 => self.initial,
+            idx => self.changes[idx - 1].1,
+        }
+    }
+
+    /// Level just before `t`, ignoring a change at `t`
+    pub fn value_before(&self, t: TimeRel) -> bool {
+        match self.changes.partition_point(|c| c.0 < t) {
+            0 => self.initial,
+            idx => self.changes[idx - 1].1,
+        }
+    }
+
+    /// Time of first change to `level` after `t`
+    pub fn next_edge(&self, t: TimeRel, level: bool) -> Option<TimeRel> {
+        let start = self.changes.partition_point(|c| c.0 <= t);
+        self.changes[start..].iter().find(|c| c.1 == level).map(|c| c.0)
+    }
+
+    /// Changes in time order
+    pub fn changes(&self) -> &[(TimeRel, bool)] {
+        &self.changes
+    }
+}
+
+/// Digital input signal of a decoder
+pub type DecoderInput = Rc<RefCell<dyn Sampler<bool>>>;
@@ -0,0 +1,114 @@
+use crate::{Result, TimeRel, TimeScale};
+use super::{Decoder, DecoderInput, Levels, Segment, SegmentCache};
+
+/// SPI decoder, a row of words (MSB first) for each assertion of the active low chip select
+pub struct Spi {
+    label: String,
+    sclk: DecoderInput,
+    mosi: Option<DecoderInput>,
+    miso: Option<DecoderInput>,
+    cs: DecoderInput,
+    /// Clock idle level
+    cpol: bool,
+    /// Data sampled on the second clock edge
+    cpha: bool,
+    word_bits: usize,
+    /// Start of input data, decoded from so transfers started before the range are complete
+    origin: TimeRel,
+    timescale: TimeScale,
+    cache: SegmentCache,
+}
+
+impl Spi {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(label: String, sclk: DecoderInput, mosi: Option<DecoderInput>, miso: Option<DecoderInput>, cs: DecoderInput,
+               mode: u32, word_bits: usize, origin: TimeRel, timescale: TimeScale) -> Self {
+        Spi {
+            label,
+            sclk,
+            mosi,
+            miso,
+            cs,
+            cpol: mode & 2 != 0,
+            cpha: mode & 1 != 0,
+            word_bits,
+            origin,
+            timescale,
+            cache: SegmentCache::default(),
+        }
+    }
+
+    fn word_label(&self, mosi: Option<u64>, miso: Option<u64>, bits: usize) -> String {
+        let digits = bits.div_ceil(4);
+        let words : Vec<String> = [mosi, miso].into_iter().flatten().map(|w| format!("{w:0digits$X}")).collect();
+        words.join("/")
+    }
+
+    /// Decode segments from `from` up to `end`. After `origin`, `from` is the start of a word
+    /// decoded before, within a transfer.
+    fn decode_range(&self, from: TimeRel, end: TimeRel) -> Result<Vec<Segment>> {
+        let read_range = [from, end];
+        let read = |input: &DecoderInput| Levels::read(input, &read_range, self.timescale);
+        let sclk = read(&self.sclk)?;
+        let cs = read(&self.cs)?;
+        let mosi = self.mosi.as_ref().map(read).transpose()?;
+        let miso = self.miso.as_ref().map(read).transpose()?;
+        // Mode 0 and 3 sample on rising edges, 1 and 2 on falling edges
+        let sample_level = self.cpol == self.cpha;
+
+        let mut segments = Vec::new();
+        let mut start = if from > self.origin || !cs.value_at(from) { Some(from) } else { cs.next_edge(from, false) };
+        while let Some(cs_start) = start {
+            let cs_end = cs.next_edge(cs_start, true);
+            let end = cs_end.unwrap_or(read_range[1]);
+            let mut word_start = cs_start;
+            let mut words = [mosi.as_ref().map(|_| 0u64), miso.as_ref().map(|_| 0u64)];
+            let mut nbits = 0;
+            let clocks = &sclk.changes()[sclk.changes().partition_point(|c| c.0 <= cs_start)..];
+            for &(t, _) in clocks.iter().take_while(|c| c.0 <= end).filter(|c| c.1 == sample_level) {
+                for (word, levels) in words.iter_mut().zip([&mosi, &miso]) {
+                    if let (Some(word), Some(levels)) = (word, levels) {
+                        *word = (*word << 1) | levels.value_before(t) as u64;
+                    }
+                }
+                nbits += 1;
+                if nbits == self.word_bits {
+                    segments.push(Segment {
+                        span: [word_start, t],
+                        value: words[0].or(words[1]),
+                        label: self.word_label(words[0], words[1], nbits),
+                        error: false,
+                    });
+                    word_start = t;
+                    words = words.map(|w| w.map(|_| 0));
+                    nbits = 0;
+                }
+            }
+            // Chip select released part way through a word
+            if nbits > 0 && cs_end.is_some() {
+                segments.push(Segment {
+                    span: [word_start, end],
+                    value: None,
+                    label: format!("{}?", self.word_label(words[0], words[1], nbits)),
+                    error: true,
+                });
+            }
+            start = cs_end.and_then(|t| cs.next_edge(t, false));
+        }
+        Ok(segments)
+    }
+}
+
+impl Decoder for Spi {
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn decode(&self, range: &[TimeRel; 2]) -> Result<Vec<Segment>> {
+        self.cache.get(range, self.origin, |seg| Some(seg.span[0]), |from, end| self.decode_range(from, end))
+    }
+
+    fn invalidate(&self) {
+        self.cache.invalidate();
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::{Error, ErrorKind};
+use crate::{Result, TimeRel, TimeScale};
+use super::{Decoder, DecoderInput, Levels, Segment, SegmentCache};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Serial line settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UartConfig {
+    pub baud: f64,
+    pub data_bits: u32,
+    pub parity: Parity,
+    pub stop_bits: f64,
+}
+
+impl UartConfig {
+    /// Parse baud rate and a frame format such as `8N1`, `7E2` or `8O1.5`
+    pub fn parse(baud: &str, format: &str) -> Result<Self> {
+        let invalid = |msg: String| -> Box<dyn std::error::Error> { Box::new(Error::new(ErrorKind::InvalidInput, msg)) };
+        let baud : f64 = baud.trim().parse().ok().filter(|&b: &f64| b > 0.).ok_or_else(|| invalid(format!("Invalid baud rate {baud}")))?;
+        let format = format.trim().to_ascii_uppercase();
+        let bad_format = || invalid(format!("Invalid UART format {format}, expected e.g. 8N1"));
+        let mut chars = format.chars();
+        let data_bits = chars.next().and_then(|c| c.to_digit(10)).filter(|n| (5..=9).contains(n)).ok_or_else(bad_format)?;
+        let parity = match chars.next() {
+            Some('N') => Parity::None,
+            Some('E') => Parity::Even,
+            Some('O') => Parity::Odd,
+            _ => return Err(bad_format()),
+        };
+        let stop_bits : f64 = chars.as_str().parse().ok().filter(|s| [1., 1.5, 2.].contains(s)).ok_or_else(bad_format)?;
+        Ok(Self { baud, data_bits, parity, stop_bits })
+    }
+}
+
+/// Asynchronous serial decoder for an idle high line, bytes are sent LSB first
+pub struct Uart {
+    label: String,
+    input: DecoderInput,
+    config: UartConfig,
+    /// Bit time (in global time units)
+    bit_time: TimeRel,
+    /// Start of input data, decoded from so each start bit is found after the previous frame
+    origin: TimeRel,
+    timescale: TimeScale,
+    cache: SegmentCache,
+}
+
+impl Uart {
+    pub fn new(label: String, input: DecoderInput, config: UartConfig, origin: TimeRel, timescale: TimeScale) -> Self {
+        Uart {
+            label,
+            input,
+            config,
+            bit_time: 1. / (config.baud * timescale.secs()),
+            origin,
+            timescale,
+            cache: SegmentCache::default(),
+        }
+    }
+
+    /// Number of bit times in a frame, from the start bit to the end of the stop bits
+    fn frame_bits(&self) -> f64 {
+        let parity = if self.config.parity == Parity::None { 0. } else { 1. };
+        1. + self.config.data_bits as f64 + parity + self.config.stop_bits
+    }
+
+    /// Decode frames from `from` up to `end`. After `origin`, `from` is the start bit of a
+    /// frame decoded before.
+    fn decode_range(&self, from: TimeRel, end: TimeRel) -> Result<Vec<Segment>> {
+        let cfg = &self.config;
+        let bit = self.bit_time;
+        let frame = self.frame_bits() * bit;
+        // Include the whole of a frame started before the end
+        let levels = Levels::read(&*self.input, &[from, end + frame], self.timescale)?;
+        let mut segments = Vec::new();
+        let mut next = if from > self.origin { Some(from) } else { levels.next_edge(from, false) };
+        while let Some(start) = next {
+            let sample = |nbit: f64| levels.value_at(start + (nbit + 0.5) * bit);
+            if start > end { break; }
+            let mut value : u64 = 0;
+            for n in 0..cfg.data_bits {
+                if sample(1. + n as f64) { value |= 1 << n; }
+            }
+            let mut stop_bit = 1. + cfg.data_bits as f64;
+            let parity_error = match cfg.parity {
+                Parity::None => false,
+                parity => {
+                    stop_bit += 1.;
+                    let ones = value.count_ones() + sample(stop_bit - 1.) as u32;
+                    (ones & 1 == 0) != (parity == Parity::Even)
+                }
+            };
+            let framing_error = !sample(stop_bit);
+            let mut label = format!("{value:02X}");
+            if (0x20..0x7f).contains(&value) {
+                label += &format!(" '{}'", value as u8 as char);
+            }
+            if framing_error { label += " FE"; }
+            if parity_error { label += " PE"; }
+            segments.push(Segment {
+                span: [start, start + frame],
+                value: Some(value),
+                label,
+                error: framing_error || parity_error,
+            });
+            // Look for the next start bit from the middle of the stop bit
+            next = levels.next_edge(start + (stop_bit + 0.5) * bit, false);
+        }
+        Ok(segments)
+    }
+}
+
+impl Decoder for Uart {
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn decode(&self, range: &[TimeRel; 2]) -> Result<Vec<Segment>> {
+        self.cache.get(range, self.origin, |seg| Some(seg.span[0]), |from, end| self.decode_range(from, end))
+    }
+
+    fn invalidate(&self) {
+        self.cache.invalidate();
+    }
+}
@@ -0,0 +1,106 @@
+//! Comparing the signals of two wave files
+use std::collections::HashMap;
+use wavefmt::FieldType;
+use crate::{export, DataStore, Result, TimeRel};
+
+/// Time intervals where a signal differs between the two files
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigDiff {
+    pub name: String,
+    /// Signal in each file
+    pub sigs: [usize; 2],
+    pub spans: Vec<[TimeRel; 2]>,
+}
+
+#[derive(Debug, Default)]
+pub struct WaveDiff {
+    /// Signals with mismatches
+    pub diffs: Vec<SigDiff>,
+    /// Names of signals found in only one of the files
+    pub unpaired: Vec<String>,
+}
+
+impl WaveDiff {
+    /// Return all mismatches as (signal name, span), ordered by start time
+    pub fn mismatches(&self) -> Vec<(&str, [TimeRel; 2])> {
+        let mut all : Vec<(&str, [TimeRel; 2])> = self.diffs.iter()
+            .flat_map(|d| d.spans.iter().map(|&span| (d.name.as_str(), span)))
+            .collect();
+        all.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]));
+        all
+    }
+
+    /// Return mismatch spans of each signal, for highlighting on the chart
+    pub fn signal_spans(&self) -> Vec<(usize, [TimeRel; 2])> {
+        self.diffs.iter()
+            .flat_map(|d| d.sigs.iter().flat_map(|&sig| d.spans.iter().map(move |&span| (sig, span))))
+            .collect()
+    }
+}
+
+/// Compare the signals of loaded files `files`, pairing them by hierarchical name. Analog
+/// values match when they are within `tolerance` of each other, others have to be equal.
+pub fn compare(datas: &DataStore, files: [usize; 2], range: &[TimeRel; 2], tolerance: f64) -> Result<WaveDiff> {
+    let names = |sigs: std::ops::Range<usize>| -> HashMap<String, usize> {
+        sigs.map(|sig| (datas.get_signal_field(sig).name, sig)).collect()
+    };
+    let golden = names(datas.get_file_signals(files[0]));
+    let other = names(datas.get_file_signals(files[1]));
+
+    let mut wdiff = WaveDiff::default();
+    for sig_a in datas.get_file_signals(files[0]) {
+        let fi = datas.get_signal_field(sig_a);
+        let Some(&sig_b) = other.get(&fi.name) else {
+            wdiff.unpaired.push(fi.name);
+            continue;
+        };
+        let analog = fi.ftype == FieldType::Analog || datas.get_signal_field(sig_b).ftype == FieldType::Analog;
+        let tol = if analog { tolerance } else { 0. };
+        let spans = diff_spans(read(datas, sig_a, range)?, read(datas, sig_b, range)?, range, tol);
+        if !spans.is_empty() {
+            wdiff.diffs.push(SigDiff { name: fi.name, sigs: [sig_a, sig_b], spans });
+        }
+    }
+    for sig_b in datas.get_file_signals(files[1]) {
+        let name = datas.get_signal_field(sig_b).name;
+        if !golden.contains_key(&name) {
+            wdiff.unpaired.push(name);
+        }
+    }
+    Ok(wdiff)
+}
+
+type Changes = (f64, Vec<(TimeRel, f64)>);
+
+fn read(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<Changes> {
+    export::read_changes(datas, sig, range)
+}
+
+/// Merge the value changes of two signals, return the spans where they differ
+fn diff_spans(a: Changes, b: Changes, range: &[TimeRel; 2], tolerance: f64) -> Vec<[TimeRel; 2]> {
+    let differ = |a: f64, b: f64| (a - b).abs() > tolerance;
+    let (mut val_a, changes_a) = a;
+    let (mut val_b, changes_b) = b;
+    let (mut ia, mut ib) = (0, 0);
+    let mut spans = Vec::new();
+    let mut start = differ(val_a, val_b).then_some(range[0]);
+    while ia < changes_a.len() || ib < changes_b.len() {
+        let ta = changes_a.get(ia).map_or(f64::INFINITY, |c| c.0);
+        let tb = changes_b.get(ib).map_or(f64::INFINITY, |c| c.0);
+        let t = ta.min(tb);
+        if ta == t { val_a = changes_a[ia].1; ia += 1; }
+        if tb == t { val_b = changes_b[ib].1; ib += 1; }
+        match (start, differ(val_a, val_b)) {
+            (None, true) => start = Some(t),
+            (Some(t0), false) => {
+                spans.push([t0, t]);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(t0) = start {
+        spans.push([t0, range[1]]);
+    }
+    spans
+}
@@ -0,0 +1,31 @@
+//! Drawing targets for charts
+mod scene;
+pub use scene::SceneSink;
+mod svg;
+pub use svg::SvgSink;
+#[cfg(test)]
+mod record;
+#[cfg(test)]
+pub use record::RecordSink;
+
+use vello::{
+    kurbo::{Affine, Shape},
+    peniko::{BlendMode, Brush, BrushRef, Fill, Stroke},
+};
+
+/// Target of chart drawing, taking the same calls as a vello `SceneBuilder` plus text runs
+pub trait DrawSink {
+    /// Fill shape
+    fn fill<'b>(&mut self, style: Fill, transform: Affine, brush: impl Into<BrushRef<'b>>, brush_transform: Option<Affine>, shape: &impl Shape);
+
+    /// Stroke shape outline
+    fn stroke<'b>(&mut self, style: &Stroke, transform: Affine, brush: impl Into<BrushRef<'b>>, brush_transform: Option<Affine>, shape: &impl Shape);
+
+    /// Push layer, drawing is clipped to `shape` until the matching `pop_layer`
+    fn push_layer(&mut self, blend: impl Into<BlendMode>, alpha: f32, transform: Affine, shape: &impl Shape);
+
+    fn pop_layer(&mut self);
+
+    /// Draw text run, starting at the baseline origin given by `transform`
+    fn text(&mut self, size: f32, brush: Option<&Brush>, transform: Affine, text: &str);
+}
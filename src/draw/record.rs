@@ -0,0 +1,80 @@
+use vello::{
+    kurbo::{Affine, BezPath, Point, Shape},
+    peniko::{BlendMode, Brush, BrushRef, Color, Fill, Stroke},
+};
+use super::DrawSink;
+
+/// Flattening tolerance for curves (in pixels)
+const TOLERANCE : f64 = 0.1;
+
+/// Recorded drawing command, paths and positions are in window coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCmd {
+    Fill { color: Option<Color>, path: BezPath },
+    Stroke { width: f32, color: Option<Color>, path: BezPath },
+    PushLayer { alpha: f32, clip: BezPath },
+    PopLayer,
+    Text { size: f32, color: Option<Color>, pos: Point, text: String },
+}
+
+/// Record drawing into a display list, so it can be checked without a GPU
+#[derive(Debug, Default)]
+pub struct RecordSink {
+    pub cmds: Vec<DrawCmd>,
+}
+
+impl RecordSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strokes drawn in `color`
+    pub fn strokes(&self, color: Color) -> impl Iterator<Item = &BezPath> {
+        self.cmds.iter().filter_map(move |cmd| match cmd {
+            DrawCmd::Stroke { color: Some(c), path, .. } if *c == color => Some(path),
+            _ => None,
+        })
+    }
+
+    /// Text runs with their baseline origin
+    pub fn texts(&self) -> impl Iterator<Item = (&str, Point)> {
+        self.cmds.iter().filter_map(|cmd| match cmd {
+            DrawCmd::Text { text, pos, .. } => Some((text.as_str(), *pos)),
+            _ => None,
+        })
+    }
+}
+
+impl DrawSink for RecordSink {
+    fn fill<'b>(&mut self, _style: Fill, transform: Affine, brush: impl Into<BrushRef<'b>>, _brush_transform: Option<Affine>, shape: &impl Shape) {
+        let path = transform * shape.to_path(TOLERANCE);
+        self.cmds.push(DrawCmd::Fill { color: solid(brush.into()), path });
+    }
+
+    fn stroke<'b>(&mut self, style: &Stroke, transform: Affine, brush: impl Into<BrushRef<'b>>, _brush_transform: Option<Affine>, shape: &impl Shape) {
+        let path = transform * shape.to_path(TOLERANCE);
+        self.cmds.push(DrawCmd::Stroke { width: style.width, color: solid(brush.into()), path });
+    }
+
+    fn push_layer(&mut self, _blend: impl Into<BlendMode>, alpha: f32, transform: Affine, shape: &impl Shape) {
+        let clip = transform * shape.to_path(TOLERANCE);
+        self.cmds.push(DrawCmd::PushLayer { alpha, clip });
+    }
+
+    fn pop_layer(&mut self) {
+        self.cmds.push(DrawCmd::PopLayer);
+    }
+
+    fn text(&mut self, size: f32, brush: Option<&Brush>, transform: Affine, text: &str) {
+        let color = brush.and_then(|b| solid(b.into()));
+        let pos = transform * Point::ORIGIN;
+        self.cmds.push(DrawCmd::Text { size, color, pos, text: text.to_string() });
+    }
+}
+
+fn solid(brush: BrushRef) -> Option<Color> {
+    match brush {
+        BrushRef::Solid(c) => Some(c),
+        _ => None,
+    }
+}
@@ -0,0 +1,41 @@
+use vello::{
+    kurbo::{Affine, Shape},
+    peniko::{BlendMode, Brush, BrushRef, Fill, Stroke},
+    SceneBuilder,
+};
+use crate::SimpleText;
+use super::DrawSink;
+
+/// Draw into a vello scene, for display on the GPU
+pub struct SceneSink<'a, 'b> {
+    sb: &'a mut SceneBuilder<'b>,
+    text: &'a mut SimpleText,
+}
+
+impl<'a, 'b> SceneSink<'a, 'b> {
+    pub fn new(sb: &'a mut SceneBuilder<'b>, text: &'a mut SimpleText) -> Self {
+        Self { sb, text }
+    }
+}
+
+impl DrawSink for SceneSink<'_, '_> {
+    fn fill<'b>(&mut self, style: Fill, transform: Affine, brush: impl Into<BrushRef<'b>>, brush_transform: Option<Affine>, shape: &impl Shape) {
+        self.sb.fill(style, transform, brush, brush_transform, shape);
+    }
+
+    fn stroke<'b>(&mut self, style: &Stroke, transform: Affine, brush: impl Into<BrushRef<'b>>, brush_transform: Option<Affine>, shape: &impl Shape) {
+        self.sb.stroke(style, transform, brush, brush_transform, shape);
+    }
+
+    fn push_layer(&mut self, blend: impl Into<BlendMode>, alpha: f32, transform: Affine, shape: &impl Shape) {
+        self.sb.push_layer(blend, alpha, transform, shape);
+    }
+
+    fn pop_layer(&mut self) {
+        self.sb.pop_layer();
+    }
+
+    fn text(&mut self, size: f32, brush: Option<&Brush>, transform: Affine, text: &str) {
+        self.text.add(self.sb, None, size, brush, transform, text);
+    }
+}
@@ -0,0 +1,100 @@
+use vello::{
+    kurbo::{Affine, Shape},
+    peniko::{BlendMode, Brush, BrushRef, Fill, Stroke},
+};
+use super::DrawSink;
+
+/// Flattening tolerance for curves (in pixels)
+const TOLERANCE : f64 = 0.1;
+
+/// Draw into an SVG document
+#[derive(Debug, Default)]
+pub struct SvgSink {
+    width: f64,
+    height: f64,
+    body: String,
+    clips: usize,
+}
+
+impl SvgSink {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// Complete the document, return its text
+    pub fn finish(self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"Roboto, sans-serif\">\n\
+             {body}</svg>\n",
+            w = self.width,
+            h = self.height,
+            body = self.body,
+        )
+    }
+
+    fn path(&mut self, transform: Affine, shape: &impl Shape, paint: &str) {
+        let d = shape.to_path(TOLERANCE).to_svg();
+        self.body.push_str(&format!("<path d=\"{d}\"{}{paint}/>\n", svg_transform(transform)));
+    }
+}
+
+impl DrawSink for SvgSink {
+    fn fill<'b>(&mut self, style: Fill, transform: Affine, brush: impl Into<BrushRef<'b>>, _brush_transform: Option<Affine>, shape: &impl Shape) {
+        let rule = match style {
+            Fill::NonZero => "nonzero",
+            Fill::EvenOdd => "evenodd",
+        };
+        let paint = format!("{} fill-rule=\"{rule}\"", svg_paint("fill", brush.into()));
+        self.path(transform, shape, &paint);
+    }
+
+    fn stroke<'b>(&mut self, style: &Stroke, transform: Affine, brush: impl Into<BrushRef<'b>>, _brush_transform: Option<Affine>, shape: &impl Shape) {
+        let paint = format!(" fill=\"none\"{} stroke-width=\"{}\"", svg_paint("stroke", brush.into()), style.width);
+        self.path(transform, shape, &paint);
+    }
+
+    fn push_layer(&mut self, _blend: impl Into<BlendMode>, alpha: f32, transform: Affine, shape: &impl Shape) {
+        let id = self.clips;
+        self.clips += 1;
+        let d = shape.to_path(TOLERANCE).to_svg();
+        self.body.push_str(&format!(
+            "<clipPath id=\"clip{id}\"><path d=\"{d}\"{}/></clipPath>\n<g clip-path=\"url(#clip{id})\" opacity=\"{alpha}\">\n",
+            svg_transform(transform),
+        ));
+    }
+
+    fn pop_layer(&mut self) {
+        self.body.push_str("</g>\n");
+    }
+
+    fn text(&mut self, size: f32, brush: Option<&Brush>, transform: Affine, text: &str) {
+        let fill = brush.map(|b| svg_paint("fill", b.into())).unwrap_or_default();
+        let text = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        self.body.push_str(&format!(
+            "<text{} font-size=\"{size}\"{fill} xml:space=\"preserve\">{text}</text>\n",
+            svg_transform(transform),
+        ));
+    }
+}
+
+fn svg_transform(transform: Affine) -> String {
+    if transform == Affine::IDENTITY {
+        return String::new();
+    }
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    format!(" transform=\"matrix({a} {b} {c} {d} {e} {f})\"")
+}
+
+/// Paint attributes (`fill` or `stroke`) for brush, non-solid brushes are drawn grey
+fn svg_paint(attr: &str, brush: BrushRef) -> String {
+    match brush {
+        BrushRef::Solid(c) if c.a == 255 => format!(" {attr}=\"rgb({},{},{})\"", c.r, c.g, c.b),
+        BrushRef::Solid(c) => format!(" {attr}=\"rgb({},{},{})\" {attr}-opacity=\"{:.3}\"", c.r, c.g, c.b, c.a as f64 / 255.),
+        _ => format!(" {attr}=\"rgb(128,128,128)\""),
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use crate::{DataStore, Result, TimeRel};
+use super::{fmt_value, read_changes, value_at};
+
+/// Rows written to a CSV export
+#[derive(Debug, Clone, Copy)]
+pub enum CsvMode {
+    /// A row for every time a signal changes
+    Transitions,
+    /// Sample at a fixed interval
+    Interval(TimeRel),
+    /// Sample on the rising edges of a clock signal
+    Clock(usize),
+}
+
+/// Most rows written sampling at an interval
+pub const MAX_ROWS : usize = 1000000;
+
+/// Write values of signals `sigs` within time `range` to a CSV file, with a column per
+/// signal after the time column
+pub fn export_csv(datas: &DataStore, sigs: &[usize], range: &[TimeRel; 2], mode: CsvMode, path: &Path) -> Result<()> {
+    if let CsvMode::Interval(step) = mode {
+        let rows = (range[1] - range[0]) / step;
+        if rows > MAX_ROWS as f64 {
+            let msg = format!("Sampling interval too short, over {MAX_ROWS} rows");
+            return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+        }
+    }
+    let timescale = datas.get_timescale();
+    let fields : Vec<_> = sigs.iter().map(|&sig| datas.get_signal_field(sig)).collect();
+    let mut w = BufWriter::new(File::create(path)?);
+    let mut header = vec![format!("time_{}", timescale.unit)];
+    header.extend(fields.iter().map(|fi| quote(&fi.name)));
+    writeln!(w, "{}", header.join(","))?;
+
+    let mut write_row = |t: TimeRel, values: &[f64]| -> Result<()> {
+        let mut row = vec![format!("{}", t * timescale.time)];
+        row.extend(fields.iter().zip(values).map(|(fi, &val)| fmt_value(fi.ftype, val)));
+        writeln!(w, "{}", row.join(","))?;
+        Ok(())
+    };
+
+    match mode {
+        CsvMode::Transitions => {
+            let mut values = Vec::with_capacity(sigs.len());
+            let mut changes : Vec<(TimeRel, usize, f64)> = Vec::new();
+            for (col, &sig) in sigs.iter().enumerate() {
+                let (val, sig_changes) = read_changes(datas, sig, range)?;
+                values.push(val);
+                changes.extend(sig_changes.into_iter().map(|(t, val)| (t, col, val)));
+            }
+            changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+            write_row(range[0], &values)?;
+            // One row for all changes at the same time
+            let mut changes = changes.into_iter().peekable();
+            while let Some((t, col, val)) = changes.next() {
+                values[col] = val;
+                while let Some((_, col, val)) = changes.next_if(|c| c.0 == t) {
+                    values[col] = val;
+                }
+                write_row(t, &values)?;
+            }
+        }
+        CsvMode::Interval(_) | CsvMode::Clock(_) => {
+            let times : Vec<TimeRel> = match mode {
+                CsvMode::Interval(step) => {
+                    let count = ((range[1] - range[0]) / step).floor() as usize;
+                    (0..=count).map(|n| range[0] + n as f64 * step).collect()
+                }
+                CsvMode::Clock(clock) => {
+                    let (_, clock_changes) = read_changes(datas, clock, range)?;
+                    clock_changes.into_iter().filter(|c| c.1 != 0.).map(|c| c.0).collect()
+                }
+                CsvMode::Transitions => unreachable!(),
+            };
+            for t in times {
+                let values : Vec<f64> = sigs.iter().map(|&sig| value_at(datas, sig, t)).collect();
+                write_row(t, &values)?;
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn quote(name: &str) -> String {
+    if name.contains([',', '"']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
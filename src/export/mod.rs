@@ -0,0 +1,101 @@
+//! Writing signals to wave files
+mod vcd;
+pub use vcd::export_vcd;
+mod svg;
+pub use svg::export_svg;
+mod wavedrom;
+pub use wavedrom::{export_wavedrom, find_clock};
+mod csv;
+pub use csv::{export_csv, CsvMode, MAX_ROWS};
+
+use wavefmt::FieldType;
+use crate::{DataStore, Result, Sampler, TimeRel, TimeScale};
+use crate::datastore::SigType;
+
+/// Number of samples taken over the export range from generated signals
+pub const GEN_SAMPLES : f64 = 10000.;
+
+/// Read value of signal `sig` at the start of `range`, and its value changes within it
+pub fn read_changes(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<(f64, Vec<(TimeRel, f64)>)> {
+    let timescale = datas.get_timescale();
+    match datas.get_signal_type_idx(sig) {
+        (SigType::Digital, idx) => {
+            let smpl = datas.get_dig_sampler(idx).unwrap().borrow();
+            changes_of(&*smpl, range, timescale, |val| if val { 1. } else { 0. })
+        }
+        (SigType::Analog, idx) => {
+            let mut smpl = datas.get_ana_sampler(idx).unwrap().borrow_mut();
+            smpl.set_iter_scale(range, &timescale, GEN_SAMPLES);
+            changes_of(&*smpl, range, timescale, |val| val as f64)
+        }
+    }
+}
+
+/// Read value of analog signal `sig` at the start of `range`, and every point of its trace
+/// within it as drawn, with held values stepping at each change
+pub fn read_trace(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<(f64, Vec<(TimeRel, f64)>)> {
+    let timescale = datas.get_timescale();
+    let idx = datas.get_signal_type_idx(sig).1;
+    let mut smpl = datas.get_ana_sampler(idx).unwrap().borrow_mut();
+    smpl.set_iter_scale(range, &timescale, GEN_SAMPLES);
+    trace_of(&*smpl, range, timescale)
+}
+
+/// Value of `smpl` at the start of `range` and its trace within it, see `read_trace`
+pub fn trace_of(smpl: &dyn Sampler<f32>, range: &[TimeRel; 2], timescale: TimeScale) -> Result<(f64, Vec<(TimeRel, f64)>)> {
+    let mut points = crate::sampler::trace_points(smpl, range, timescale)?;
+    // First point may be a sample before the range
+    let initial = resample(points[0], &points[1..], &[range[0]])[0];
+    points.retain(|p| p.0 > range[0]);
+    Ok((initial, points))
+}
+
+/// Values of a trace read by `read_trace` at `times` in order, interpolating between its
+/// points from `start`
+pub fn resample(start: (TimeRel, f64), trace: &[(TimeRel, f64)], times: &[TimeRel]) -> Vec<f64> {
+    let mut prev = start;
+    let mut points = trace.iter().copied().peekable();
+    times.iter().map(|&t| {
+        while let Some(&next) = points.peek().filter(|next| next.0 < t) {
+            prev = next;
+            points.next();
+        }
+        match points.peek() {
+            Some(&(t1, v1)) if t1 > prev.0 => prev.1 + (v1 - prev.1) * (t - prev.0) / (t1 - prev.0),
+            _ => prev.1,
+        }
+    }).collect()
+}
+
+/// Read value of signal `sig` at time `t`
+pub fn value_at(datas: &DataStore, sig: usize, t: TimeRel) -> f64 {
+    let timescale = datas.get_timescale();
+    match datas.get_signal_type_idx(sig) {
+        (SigType::Digital, idx) => {
+            let val = datas.get_dig_sampler(idx).unwrap().borrow().get_value_at(t, timescale);
+            if val { 1. } else { 0. }
+        }
+        (SigType::Analog, idx) => datas.get_ana_sampler(idx).unwrap().borrow().get_value_at(t, timescale) as f64,
+    }
+}
+
+/// Format a signal value, as an integer unless the signal is analog
+pub fn fmt_value(ftype: FieldType, val: f64) -> String {
+    match ftype {
+        FieldType::Analog => format!("{val}"),
+        _ => format!("{}", val as u64),
+    }
+}
+
+fn changes_of<T: Copy + PartialEq>(smpl: &dyn Sampler<T>, range: &[TimeRel; 2], timescale: TimeScale, to_f64: fn(T) -> f64) -> Result<(f64, Vec<(TimeRel, f64)>)> {
+    let initial = smpl.get_value_at(range[0], timescale);
+    let mut cur = initial;
+    let mut changes = Vec::new();
+    for (val, t) in smpl.iter_range(range)? {
+        if t > range[0] && t <= range[1] && val != cur {
+            changes.push((t, to_f64(val)));
+            cur = val;
+        }
+    }
+    Ok((to_f64(initial), changes))
+}
@@ -0,0 +1,12 @@
+use std::path::Path;
+use crate::{DataStore, Result};
+use crate::chart::Chart;
+use crate::draw::SvgSink;
+
+/// Draw chart view to an SVG file, as shown in a window of `width` by `height` pixels
+pub fn export_svg(chart: &Chart, datas: &DataStore, width: f64, height: f64, path: &Path) -> Result<()> {
+    let mut svg = SvgSink::new(width, height);
+    chart.draw_layer::<bool>(&mut svg, datas, width, height);
+    std::fs::write(path, svg.finish())?;
+    Ok(())
+}
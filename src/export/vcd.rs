@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use wavefmt::{FieldInfo, VcdWriter};
+use crate::{DataStore, Result, TimeRel};
+use super::read_changes;
+
+/// Write signals `sigs` over time `range` to a VCD file. The timescale is the finest one of
+/// the files the signals were read from, scopes follow the dotted signal names.
+pub fn export_vcd(datas: &DataStore, sigs: &[usize], range: &[TimeRel; 2], path: &Path) -> Result<()> {
+    let unit = datas.get_timescale().secs();
+    let timescale = sigs.iter().filter_map(|&sig| datas.get_signal_timescale(sig)).reduce(f64::min).unwrap_or(unit);
+    let fields : Vec<FieldInfo> = sigs.iter().map(|&sig| datas.get_signal_field(sig)).collect();
+    let mut vcd = VcdWriter::new(BufWriter::new(File::create(path)?));
+    let tick = vcd.write_header(timescale, &fields)?;
+    let ratio = wavefmt::time_ratio(unit, tick);
+    let to_ticks = |t: TimeRel| (t * ratio).round().max(0.) as u64;
+
+    let mut initial = Vec::with_capacity(sigs.len());
+    let mut changes : Vec<(TimeRel, usize, f64)> = Vec::new();
+    for (var, &sig) in sigs.iter().enumerate() {
+        let (val, sig_changes) = read_changes(datas, sig, range)?;
+        initial.push(val);
+        changes.extend(sig_changes.into_iter().map(|(t, val)| (t, var, val)));
+    }
+    // Stable sort keeps the order of changes within each signal
+    changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+    vcd.write_initial(to_ticks(range[0]), &initial)?;
+    for (t, var, val) in changes {
+        vcd.write_change(to_ticks(t), var, val)?;
+    }
+    vcd.finish(to_ticks(range[1]))?;
+    Ok(())
+}
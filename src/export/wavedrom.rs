@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind};
+use std::path::Path;
+use wavefmt::FieldType;
+use wavefmt::wavedrom::{write_wavejson, WaveLane};
+use crate::{DataStore, Result, TimeRel};
+use crate::datastore::SigType;
+use super::read_changes;
+
+/// Find clock signal by name, or guess one: the first digital signal named like a clock,
+/// otherwise the first digital signal
+pub fn find_clock(datas: &DataStore, name: Option<&str>) -> Option<usize> {
+    let digital : Vec<usize> = (0..datas.get_num_signals())
+        .filter(|&sig| datas.get_signal_type_idx(sig).0 == SigType::Digital)
+        .collect();
+    if let Some(name) = name {
+        return digital.into_iter().find(|&sig| datas.get_signal_field(sig).name == name);
+    }
+    let is_clock = |sig: &usize| {
+        let name = datas.get_signal_field(*sig).name.to_lowercase();
+        let leaf = name.rsplit('.').next().unwrap_or_default();
+        leaf.contains("clk") || leaf.contains("clock")
+    };
+    digital.iter().copied().find(is_clock).or(digital.first().copied())
+}
+
+/// Sample digital and bus signals `sigs` at the rising edges of signal `clock` within
+/// `range`, write them to a WaveJSON diagram with one cell per clock cycle
+pub fn export_wavedrom(datas: &DataStore, clock: usize, sigs: &[usize], range: &[TimeRel; 2], path: &Path) -> Result<()> {
+    let (_, clock_changes) = read_changes(datas, clock, range)?;
+    let edges : Vec<TimeRel> = clock_changes.iter().filter(|c| c.1 != 0.).map(|c| c.0).collect();
+    if edges.is_empty() {
+        let msg = "No clock edges in range";
+        return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+    }
+    let clock_name = datas.get_signal_field(clock).name;
+    let mut lanes = vec![WaveLane::new(&clock_name, format!("p{}", ".".repeat(edges.len() - 1)), Vec::new())];
+    for &sig in sigs.iter().filter(|&&sig| sig != clock) {
+        let field = datas.get_signal_field(sig);
+        let bus = match field.ftype {
+            FieldType::Digital => false,
+            FieldType::DigiBus(_) => true,
+            _ => continue,
+        };
+        let (mut val, changes) = read_changes(datas, sig, range)?;
+        let mut changes = changes.into_iter().peekable();
+        let mut wave = String::new();
+        let mut data = Vec::new();
+        let mut prev = None;
+        for &t in &edges {
+            while let Some((_, next)) = changes.next_if(|c| c.0 <= t) {
+                val = next;
+            }
+            if prev == Some(val) {
+                wave.push('.');
+            } else if bus {
+                wave.push('=');
+                data.push(format!("0x{:x}", val as u64));
+            } else {
+                wave.push(if val != 0. { '1' } else { '0' });
+            }
+            prev = Some(val);
+        }
+        lanes.push(WaveLane::new(&field.name, wave, data));
+    }
+    write_wavejson(&mut BufWriter::new(File::create(path)?), &lanes)?;
+    Ok(())
+}
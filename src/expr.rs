@@ -0,0 +1,344 @@
+//! Expression language for derived signals, e.g. `valid & ready`, `addr[7:4]` or
+//! `vout - vref`. Operators and their precedence follow C, with `[hi:lo]` and `[bit]`
+//! selecting bits. Names with other characters can be quoted: `"top.a$b"`.
+use std::io::{Error, ErrorKind};
+use wavefmt::FieldType;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnOp {
+    Not,
+    /// Bitwise inversion within a width (in bits)
+    Inv(u32),
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Or, And,
+    BitOr, BitXor, BitAnd,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    Shl, Shr,
+    Add, Sub,
+    Mul, Div, Rem,
+}
+
+/// Binary operators, lowest precedence first
+const BINOPS : [&[(&str, BinOp)]; 10] = [
+    &[("||", BinOp::Or)],
+    &[("&&", BinOp::And)],
+    &[("|", BinOp::BitOr)],
+    &[("^", BinOp::BitXor)],
+    &[("&", BinOp::BitAnd)],
+    &[("==", BinOp::Eq), ("!=", BinOp::Ne)],
+    &[("<=", BinOp::Le), (">=", BinOp::Ge), ("<", BinOp::Lt), (">", BinOp::Gt)],
+    &[("<<", BinOp::Shl), (">>", BinOp::Shr)],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Rem)],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Num(f64),
+    /// Input signal (index in `Expr::names`)
+    Sig(usize),
+    Unary(UnOp, Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    /// Bits `hi` down to `lo`
+    Slice(Box<Node>, u32, u32),
+}
+
+/// Kind of value an expression produces
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Int(u32),
+    Real,
+}
+
+impl Kind {
+    fn width(self) -> u32 {
+        match self {
+            Kind::Bool => 1,
+            Kind::Int(width) => width,
+            Kind::Real => 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    root: Node,
+    names: Vec<String>,
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0, names: Vec::new() };
+        let root = parser.binary(0)?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(Self { root, names: parser.names })
+    }
+
+    /// Names of input signals, in the order their values are passed to `eval`
+    pub fn get_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Set types of the input signals, return the type of the result
+    pub fn bind(&mut self, ftypes: &[FieldType]) -> FieldType {
+        match bind(&mut self.root, ftypes) {
+            Kind::Bool => FieldType::Digital,
+            Kind::Int(width) => FieldType::DigiBus(width as usize),
+            Kind::Real => FieldType::Analog,
+        }
+    }
+
+    /// Evaluate with the current values of the input signals, booleans are 0 or 1
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        eval(&self.root, values)
+    }
+}
+
+fn mask(width: u32) -> u64 {
+    if width >= 64 { u64::MAX } else { (1 << width) - 1 }
+}
+
+fn bind(node: &mut Node, ftypes: &[FieldType]) -> Kind {
+    match node {
+        Node::Num(val) => {
+            if val.fract() == 0. && *val >= 0. {
+                Kind::Int((64 - (*val as u64).leading_zeros()).max(1))
+            } else {
+                Kind::Real
+            }
+        }
+        Node::Sig(idx) => match ftypes.get(*idx) {
+            Some(FieldType::Digital) => Kind::Bool,
+            Some(FieldType::DigiBus(width)) => Kind::Int(*width as u32),
+            _ => Kind::Real,
+        },
+        Node::Unary(op, arg) => {
+            let kind = bind(arg, ftypes);
+            match op {
+                UnOp::Not => Kind::Bool,
+                UnOp::Inv(width) => {
+                    *width = kind.width();
+                    if kind == Kind::Real { Kind::Int(64) } else { kind }
+                }
+                UnOp::Neg => Kind::Real,
+            }
+        }
+        Node::Binary(op, lhs, rhs) => {
+            let (a, b) = (bind(lhs, ftypes), bind(rhs, ftypes));
+            let ints = a != Kind::Real && b != Kind::Real;
+            match op {
+                BinOp::Or | BinOp::And | BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => Kind::Bool,
+                BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd => {
+                    if a == Kind::Bool && b == Kind::Bool { Kind::Bool } else { Kind::Int(a.width().max(b.width())) }
+                }
+                BinOp::Shl => Kind::Int(64),
+                BinOp::Shr => Kind::Int(a.width()),
+                BinOp::Add if ints => Kind::Int((a.width().max(b.width()) + 1).min(64)),
+                BinOp::Mul if ints => Kind::Int((a.width() + b.width()).min(64)),
+                BinOp::Rem if ints => Kind::Int(b.width()),
+                _ => Kind::Real,
+            }
+        }
+        Node::Slice(arg, hi, lo) => {
+            bind(arg, ftypes);
+            if hi == lo { Kind::Bool } else { Kind::Int(*hi - *lo + 1) }
+        }
+    }
+}
+
+fn eval(node: &Node, values: &[f64]) -> f64 {
+    let bool_val = |b: bool| if b { 1. } else { 0. };
+    match node {
+        Node::Num(val) => *val,
+        Node::Sig(idx) => values[*idx],
+        Node::Unary(op, arg) => {
+            let val = eval(arg, values);
+            match op {
+                UnOp::Not => bool_val(val == 0.),
+                UnOp::Inv(width) => (!(val as u64) & mask(*width)) as f64,
+                UnOp::Neg => -val,
+            }
+        }
+        Node::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs, values), eval(rhs, values));
+            let (ia, ib) = (a as u64, b as u64);
+            match op {
+                BinOp::Or => bool_val(a != 0. || b != 0.),
+                BinOp::And => bool_val(a != 0. && b != 0.),
+                BinOp::BitOr => (ia | ib) as f64,
+                BinOp::BitXor => (ia ^ ib) as f64,
+                BinOp::BitAnd => (ia & ib) as f64,
+                BinOp::Eq => bool_val(a == b),
+                BinOp::Ne => bool_val(a != b),
+                BinOp::Lt => bool_val(a < b),
+                BinOp::Le => bool_val(a <= b),
+                BinOp::Gt => bool_val(a > b),
+                BinOp::Ge => bool_val(a >= b),
+                BinOp::Shl => ia.checked_shl(ib as u32).unwrap_or(0) as f64,
+                BinOp::Shr => ia.checked_shr(ib as u32).unwrap_or(0) as f64,
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Rem => a % b,
+            }
+        }
+        Node::Slice(arg, hi, lo) => {
+            let val = eval(arg, values) as u64;
+            (val.checked_shr(*lo).unwrap_or(0) & mask(hi - lo + 1)) as f64
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    names: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> Box<dyn std::error::Error> {
+        Box::new(Error::new(ErrorKind::InvalidInput, format!("Expression: {msg} at column {}", self.pos + 1)))
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self.text.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).copied()
+    }
+
+    /// Consume `token` if it is next
+    fn accept(&mut self, token: &str) -> bool {
+        self.peek();
+        if self.text[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse binary operators of precedence `level` and above
+    fn binary(&mut self, level: usize) -> Result<Node> {
+        if level == BINOPS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        'outer: loop {
+            self.peek();
+            for &(token, op) in BINOPS[level] {
+                // Don't take the first character of a longer operator, e.g. `&` of `&&`
+                let longer = BINOPS.iter().flat_map(|ops| ops.iter())
+                    .any(|&(t, _)| t.len() > token.len() && t.starts_with(token) && self.text[self.pos..].starts_with(t.as_bytes()));
+                if !longer && self.accept(token) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        let op = match self.peek() {
+            Some(b'!') if !self.text[self.pos..].starts_with(b"!=") => UnOp::Not,
+            Some(b'~') => UnOp::Inv(64),
+            Some(b'-') => UnOp::Neg,
+            _ => return self.postfix(),
+        };
+        self.pos += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn postfix(&mut self) -> Result<Node> {
+        let mut node = self.primary()?;
+        while self.accept("[") {
+            let hi = self.bit()?;
+            let lo = if self.accept(":") { self.bit()? } else { hi };
+            if lo > hi || !self.accept("]") {
+                return Err(self.error("expected [hi:lo]"));
+            }
+            node = Node::Slice(Box::new(node), hi, lo);
+        }
+        Ok(node)
+    }
+
+    fn bit(&mut self) -> Result<u32> {
+        match self.primary()? {
+            Node::Num(val) if val.fract() == 0. && (0. ..64.).contains(&val) => Ok(val as u32),
+            _ => Err(self.error("expected bit number")),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node> {
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                let node = self.binary(0)?;
+                if !self.accept(")") {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(node)
+            }
+            Some(b'"') => {
+                self.pos += 1;
+                let len = self.text[self.pos..].iter().position(|&c| c == b'"').ok_or_else(|| self.error("unterminated name"))?;
+                let name = String::from_utf8_lossy(&self.text[self.pos..self.pos + len]).into_owned();
+                self.pos += len + 1;
+                Ok(self.signal(name))
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' || c == b'$' => {
+                let start = self.pos;
+                while self.text.get(self.pos).is_some_and(|&c| c.is_ascii_alphanumeric() || b"_$.".contains(&c)) {
+                    self.pos += 1;
+                }
+                let name = String::from_utf8_lossy(&self.text[start..self.pos]).into_owned();
+                Ok(self.signal(name))
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn signal(&mut self, name: String) -> Node {
+        match self.names.iter().position(|n| *n == name) {
+            Some(idx) => Node::Sig(idx),
+            None => {
+                self.names.push(name);
+                Node::Sig(self.names.len() - 1)
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Node> {
+        let rest = &self.text[self.pos..];
+        for (prefix, radix) in [(b"0x", 16), (b"0b", 2)] {
+            if rest.starts_with(prefix) {
+                let len = rest[2..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == b'_').count();
+                let digits = String::from_utf8_lossy(&rest[2..2 + len]).replace('_', "");
+                self.pos += 2 + len;
+                return u64::from_str_radix(&digits, radix).map(|v| Node::Num(v as f64)).map_err(|_| self.error("invalid number"));
+            }
+        }
+        let mut len = 0;
+        while let Some(&c) = rest.get(len) {
+            let exponent_sign = (c == b'-' || c == b'+') && len > 0 && (rest[len - 1] == b'e' || rest[len - 1] == b'E');
+            if !(c.is_ascii_digit() || c == b'.' || c == b'e' || c == b'E' || exponent_sign) { break; }
+            len += 1;
+        }
+        let num = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len;
+        num.parse().map(Node::Num).map_err(|_| self.error("invalid number"))
+    }
+}
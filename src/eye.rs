@@ -0,0 +1,219 @@
+//! Eye diagrams, folding an analog signal on its unit interval into a density histogram
+use std::io::{Error, ErrorKind};
+use vello::{
+    kurbo::{Affine, Rect},
+    peniko::{Brush, Color, Fill},
+};
+use crate::{parse_time, DataStore, DrawSink, Result, TimeRel, TimeScale, RULE_HEIGHT, SCROLL_WIDTH};
+use crate::datastore::SigType;
+use crate::measure::PANEL_BG_COL;
+
+/// Bins across the unit interval and the value range of a drawn eye
+pub const BINS : [usize; 2] = [128, 64];
+
+/// How the signal is folded into unit intervals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Folding {
+    /// Fixed bit period, in global units
+    Period(TimeRel),
+    /// Recovered from the rising edges of a clock signal, centred on them
+    Clock(usize),
+}
+
+impl Folding {
+    /// Parse a bit period such as `400ps`, else the name of a clock signal
+    pub fn parse(datas: &DataStore, text: &str) -> Result<Self> {
+        if let Some(ui) = parse_time(text, &datas.get_timescale()).filter(|&ui| ui > 0.) {
+            return Ok(Folding::Period(ui));
+        }
+        match (0..datas.get_num_signals()).find(|&sig| datas.get_signal_field(sig).name == text.trim()) {
+            Some(sig) => Ok(Folding::Clock(sig)),
+            None => {
+                let msg = format!("Expected a bit period or clock signal, got {text}");
+                Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)))
+            }
+        }
+    }
+}
+
+/// Density of an analog signal over a unit interval, with transitions at either side
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eye {
+    /// Unit interval, in global units (the average clock period when recovered)
+    pub ui: f64,
+    pub v_min: f64,
+    pub v_max: f64,
+    /// Level the eye opening is measured across, midway between min and max
+    pub threshold: f64,
+    /// Number of bins across the unit interval and the value range
+    pub bins: [usize; 2],
+    /// Count of trace points in each bin, by row from `v_min` and then column
+    pub counts: Vec<u32>,
+}
+
+impl Eye {
+    /// Accumulate the trace from `initial` through `changes`, interpolated between them,
+    /// at phases within the unit interval (0 to 1) given by `phase`
+    pub fn from_changes(initial: (TimeRel, f64), changes: &[(TimeRel, f64)], ui: f64, bins: [usize; 2], phase: impl Fn(TimeRel) -> Option<f64>) -> Self {
+        let bins = [bins[0].max(1), bins[1].max(1)];
+        let values = changes.iter().map(|c| c.1).chain([initial.1]);
+        let v_min = values.clone().fold(f64::INFINITY, f64::min);
+        let v_max = values.fold(f64::NEG_INFINITY, f64::max);
+        let span = if v_max > v_min { v_max - v_min } else { 1. };
+        let mut eye = Eye { ui, v_min, v_max, threshold: (v_min + v_max) / 2., bins, counts: vec![0; bins[0] * bins[1]] };
+        let mut prev = initial;
+        for &(t, val) in changes {
+            // Half a bin between points in either direction
+            let steps = ((t - prev.0) / ui * bins[0] as f64 * 2.)
+                .max((val - prev.1).abs() / span * bins[1] as f64 * 2.)
+                .ceil().max(1.) as usize;
+            for step in 0..steps {
+                let frac = step as f64 / steps as f64;
+                let pt = prev.0 + frac * (t - prev.0);
+                let Some(ph) = phase(pt) else { continue };
+                let col = ((ph * bins[0] as f64) as usize).min(bins[0] - 1);
+                let row = (((prev.1 + frac * (val - prev.1) - v_min) / span * bins[1] as f64) as usize).min(bins[1] - 1);
+                eye.counts[row * bins[0] + col] += 1;
+            }
+            prev = (t, val);
+        }
+        eye
+    }
+
+    pub fn count(&self, col: usize, row: usize) -> u32 {
+        self.counts[row * self.bins[0] + col]
+    }
+
+    /// Vertical opening at the centre of the unit interval, zero when closed
+    pub fn height(&self) -> f64 {
+        let col = self.bins[0] / 2;
+        let Some(row) = self.threshold_row().filter(|&row| self.count(col, row) == 0) else { return 0. };
+        let top = (row..self.bins[1]).take_while(|&r| self.count(col, r) == 0).count();
+        let bottom = (0..row).rev().take_while(|&r| self.count(col, r) == 0).count();
+        (top + bottom) as f64 * (self.v_max - self.v_min) / self.bins[1] as f64
+    }
+
+    /// Horizontal opening across the threshold, in global units, zero when closed
+    pub fn width(&self) -> f64 {
+        let col = self.bins[0] / 2;
+        let Some(row) = self.threshold_row().filter(|&row| self.count(col, row) == 0) else { return 0. };
+        let right = (col..self.bins[0]).take_while(|&c| self.count(c, row) == 0).count();
+        let left = (0..col).rev().take_while(|&c| self.count(c, row) == 0).count();
+        (right + left) as f64 * self.ui / self.bins[0] as f64
+    }
+
+    fn threshold_row(&self) -> Option<usize> {
+        (self.v_max > self.v_min).then(|| (self.bins[1] / 2).min(self.bins[1] - 1))
+    }
+}
+
+/// Fold analog signal `sig` within `range` into an eye. Without a folding, the bit period
+/// is the shortest time between crossings of the level midway between min and max.
+pub fn eye(datas: &DataStore, sig: usize, range: &[TimeRel; 2], folding: Option<Folding>, bins: [usize; 2]) -> Result<Eye> {
+    if datas.get_signal_type_idx(sig).0 != SigType::Analog {
+        let msg = format!("Signal is not analog: {}", datas.get_signal_field(sig).name);
+        return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+    }
+    let (initial, changes) = crate::export::read_trace(datas, sig, range)?;
+    match folding {
+        Some(Folding::Clock(clk)) => {
+            let edges = crate::jitter::edge_times(datas, clk, range, None)?;
+            if edges.len() < 2 {
+                let msg = format!("Clock needs at least two rising edges, found {}", edges.len());
+                return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+            }
+            let ui = (edges[edges.len() - 1] - edges[0]) / (edges.len() - 1) as f64;
+            // Each clock period is one unit interval, with the clock edge in the middle
+            let phase = |t: TimeRel| {
+                let idx = edges.partition_point(|&e| e <= t).checked_sub(1)?;
+                let next = edges.get(idx + 1)?;
+                Some(((t - edges[idx]) / (next - edges[idx]) + 0.5).rem_euclid(1.))
+            };
+            Ok(Eye::from_changes((range[0], initial), &changes, ui, bins, phase))
+        }
+        folding => {
+            let crossings = crossings(initial, &changes, range[0]);
+            let ui = match folding {
+                Some(Folding::Period(ui)) => ui,
+                _ => crossings.windows(2).map(|w| w[1] - w[0]).filter(|&d| d > 0.).reduce(f64::min).ok_or_else(|| {
+                    Box::new(Error::new(ErrorKind::InvalidData, "Too few transitions to estimate the bit period"))
+                })?,
+            };
+            // Transitions at the start and end of the unit interval
+            let origin = crossings.first().copied().unwrap_or(range[0]);
+            let phase = |t: TimeRel| Some(((t - origin) / ui).rem_euclid(1.));
+            Ok(Eye::from_changes((range[0], initial), &changes, ui, bins, phase))
+        }
+    }
+}
+
+/// Times the trace crosses the level midway between its min and max, in either direction
+fn crossings(initial: f64, changes: &[(TimeRel, f64)], start: TimeRel) -> Vec<TimeRel> {
+    let values = changes.iter().map(|c| c.1).chain([initial]);
+    let level = (values.clone().fold(f64::INFINITY, f64::min) + values.fold(f64::NEG_INFINITY, f64::max)) / 2.;
+    let mut found = Vec::new();
+    let mut prev = (start, initial);
+    for &(t, val) in changes {
+        if (prev.1 < level) != (val < level) {
+            found.push(prev.0 + (level - prev.1) / (val - prev.1) * (t - prev.0));
+        }
+        prev = (t, val);
+    }
+    found
+}
+
+/// Colour of a bin from blue through yellow to red as its density rises from 0 to 1
+fn heat(density: f64) -> Color {
+    let d = density.clamp(0., 1.);
+    let (r, g, b) = if d < 0.5 {
+        (2. * d, 2. * d, 1. - 2. * d)
+    } else {
+        (1., 2. - 2. * d, 0.)
+    };
+    Color::rgb8((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8)
+}
+
+/// Draw titled eye heat map with readout lines over the waveform window
+pub fn draw(sb: &mut impl DrawSink, title: &str, eye: Option<&Eye>, lines: &[String], timescale: TimeScale, width: f64, height: f64) {
+    let label_height = (RULE_HEIGHT - 4.) as f32;
+    let line_height = RULE_HEIGHT - 4.;
+    let panel = Rect::new(width * 0.15, RULE_HEIGHT + 8., width - SCROLL_WIDTH - 8., height - RULE_HEIGHT - 8.);
+    sb.fill(Fill::NonZero, Affine::IDENTITY, &PANEL_BG_COL, None, &panel);
+    let text = std::iter::once(title).chain(lines.iter().map(|l| l.as_str()));
+    for (num, line) in text.enumerate() {
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(if num == 0 { Color::YELLOW } else { Color::WHITE })),
+            Affine::translate((panel.x0 + 4., panel.y0 + 4. + line_height * (num + 1) as f64 - 2.)),
+            line,
+        );
+    }
+    let Some(eye) = eye else { return };
+    // Plot below the text, with value labels to its left
+    let label_width = 0.6 * label_height as f64 * 10.;
+    let plot = Rect::new(panel.x0 + label_width, panel.y0 + line_height * (lines.len() + 2) as f64, panel.x1 - 4., panel.y1 - line_height - 8.);
+    if plot.width() <= 0. || plot.height() <= 0. { return; }
+    let (cell_w, cell_h) = (plot.width() / eye.bins[0] as f64, plot.height() / eye.bins[1] as f64);
+    let most = eye.counts.iter().copied().max().unwrap_or(0).max(1) as f64;
+    for row in 0..eye.bins[1] {
+        for col in 0..eye.bins[0] {
+            let count = eye.count(col, row);
+            if count == 0 { continue; }
+            let (x, y) = (plot.x0 + col as f64 * cell_w, plot.y1 - (row + 1) as f64 * cell_h);
+            sb.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                &Brush::Solid(heat((count as f64).ln_1p() / most.ln_1p())),
+                None,
+                &Rect::new(x, y, x + cell_w, y + cell_h),
+            );
+        }
+    }
+    let white = Brush::Solid(Color::WHITE);
+    sb.text(label_height, Some(&white), Affine::translate((panel.x0 + 4., plot.y0 + line_height)), &format!("{:.4}", eye.v_max));
+    sb.text(label_height, Some(&white), Affine::translate((panel.x0 + 4., plot.y1)), &format!("{:.4}", eye.v_min));
+    let ui = crate::fmt_time_unit(TimeScale { time: eye.ui, unit: timescale.unit });
+    sb.text(label_height, Some(&white), Affine::translate((plot.x0, plot.y1 + line_height)), "0");
+    let ui_width = 0.6 * label_height as f64 * ui.len() as f64;
+    sb.text(label_height, Some(&white), Affine::translate((plot.x1 - ui_width, plot.y1 + line_height)), &ui);
+}
@@ -0,0 +1,207 @@
+//! Clock jitter analysis from the rising edges of a signal, and the period histogram
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use vello::{
+    kurbo::{Affine, Rect},
+    peniko::{Brush, Color, Fill},
+};
+use crate::{DataStore, DrawSink, Result, TimeRel, TimeScale, RULE_HEIGHT, SCROLL_WIDTH};
+use crate::datastore::SigType;
+use crate::measure::PANEL_BG_COL;
+
+const BAR_COL : Brush = Brush::Solid(Color::rgb8(80, 160, 255));
+const HIST_WIDTH : f64 = 320.;
+const HIST_HEIGHT : f64 = 120.;
+
+/// Number of bins in the period histogram drawn
+pub const BINS : usize = 32;
+
+/// Period sequence and jitter of a clock. Times are in global units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jitter {
+    /// Rising edge times
+    pub edges: Vec<TimeRel>,
+    /// Time between consecutive edges
+    pub periods: Vec<f64>,
+    /// Change of period from one cycle to the next
+    pub c2c: Vec<f64>,
+    /// Time interval error of each edge against the ideal clock
+    pub tie: Vec<f64>,
+    /// Period of the ideal clock, a least squares fit to the edges
+    pub ideal_period: f64,
+}
+
+/// Counts of values in equal width bins from `start`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub start: f64,
+    pub bin_width: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Jitter {
+    /// Analyse edge times in order, needing at least two edges
+    pub fn from_edges(edges: Vec<TimeRel>) -> Result<Self> {
+        if edges.len() < 2 {
+            let msg = format!("Jitter needs at least two rising edges, found {}", edges.len());
+            return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+        }
+        let periods : Vec<f64> = edges.windows(2).map(|w| w[1] - w[0]).collect();
+        let c2c : Vec<f64> = periods.windows(2).map(|w| w[1] - w[0]).collect();
+        // Fit edge = offset + n * period
+        let n = edges.len() as f64;
+        let mean_idx = (n - 1.) / 2.;
+        let mean_t = edges.iter().sum::<f64>() / n;
+        let (mut cov, mut var) = (0., 0.);
+        for (idx, &t) in edges.iter().enumerate() {
+            cov += (idx as f64 - mean_idx) * (t - mean_t);
+            var += (idx as f64 - mean_idx).powi(2);
+        }
+        let ideal_period = cov / var;
+        let offset = mean_t - ideal_period * mean_idx;
+        let tie = edges.iter().enumerate().map(|(idx, &t)| t - (offset + ideal_period * idx as f64)).collect();
+        Ok(Jitter { edges, periods, c2c, tie, ideal_period })
+    }
+
+    /// Names and values of the summary statistics, in display order
+    pub fn items(&self) -> Vec<(&'static str, f64)> {
+        let mean = self.periods.iter().sum::<f64>() / self.periods.len() as f64;
+        let std_dev = (self.periods.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / self.periods.len() as f64).sqrt();
+        vec![
+            ("period mean", mean),
+            ("period std", std_dev),
+            ("period pk-pk", pk_pk(&self.periods)),
+            ("ideal period", self.ideal_period),
+            ("c2c rms", rms(&self.c2c)),
+            ("c2c pk-pk", pk_pk(&self.c2c)),
+            ("tie rms", rms(&self.tie)),
+            ("tie pk-pk", pk_pk(&self.tie)),
+        ]
+    }
+
+    /// Histogram of the periods over `bins` bins spanning their range
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        let bins = bins.max(1);
+        let min = self.periods.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.periods.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        // A clock without jitter fills a single bin
+        let bin_width = if max > min { (max - min) / bins as f64 } else { 1. };
+        let mut counts = vec![0; bins];
+        for &p in &self.periods {
+            counts[(((p - min) / bin_width) as usize).min(bins - 1)] += 1;
+        }
+        Histogram { start: min, bin_width, counts }
+    }
+
+    /// Write a row per edge to a CSV file: time, period to the next edge, cycle-to-cycle
+    /// change and time interval error, in units of `timescale`
+    pub fn write_csv(&self, timescale: TimeScale, path: &Path) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        let unit = timescale.unit;
+        writeln!(w, "edge,time_{unit},period_{unit},c2c_{unit},tie_{unit}")?;
+        let cell = |val: Option<&f64>| val.map_or(String::new(), |val| format!("{}", val * timescale.time));
+        for (idx, t) in self.edges.iter().enumerate() {
+            writeln!(w, "{idx},{},{},{},{}",
+                cell(Some(t)), cell(self.periods.get(idx)), cell(idx.checked_sub(1).and_then(|i| self.c2c.get(i))), cell(self.tie.get(idx)))?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+fn rms(vals: &[f64]) -> f64 {
+    if vals.is_empty() { return 0.; }
+    (vals.iter().map(|v| v * v).sum::<f64>() / vals.len() as f64).sqrt()
+}
+
+fn pk_pk(vals: &[f64]) -> f64 {
+    let min = vals.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = vals.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if vals.is_empty() { 0. } else { max - min }
+}
+
+/// Rising edge times of signal `sig` within `range`. Analog signals cross `threshold`,
+/// on the trace between samples as drawn, or midway between their min and max without one.
+pub fn edge_times(datas: &DataStore, sig: usize, range: &[TimeRel; 2], threshold: Option<f64>) -> Result<Vec<TimeRel>> {
+    match datas.get_signal_type_idx(sig).0 {
+        SigType::Digital => {
+            let (_, changes) = crate::export::read_changes(datas, sig, range)?;
+            Ok(changes.into_iter().filter(|c| c.1 != 0.).map(|c| c.0).collect())
+        }
+        SigType::Analog => {
+            let threshold = match threshold {
+                Some(threshold) => threshold,
+                None => {
+                    let stats = crate::measure::analog(datas, sig, range)?;
+                    (stats.min + stats.max) / 2.
+                }
+            };
+            // Held values step between two points at the same time, crossing at the change
+            let (initial, trace) = crate::export::read_trace(datas, sig, range)?;
+            let mut edges = Vec::new();
+            let mut prev = (range[0], initial);
+            for (t, val) in trace {
+                if prev.1 < threshold && val >= threshold {
+                    edges.push(prev.0 + (threshold - prev.1) / (val - prev.1) * (t - prev.0));
+                }
+                prev = (t, val);
+            }
+            Ok(edges)
+        }
+    }
+}
+
+/// Analyse the jitter of signal `sig` within `range`, see `edge_times`
+pub fn analyse(datas: &DataStore, sig: usize, range: &[TimeRel; 2], threshold: Option<f64>) -> Result<Jitter> {
+    Jitter::from_edges(edge_times(datas, sig, range, threshold)?)
+}
+
+/// Draw titled histogram of periods with summary lines at the bottom right of the
+/// waveform window, an empty plot without a histogram
+pub fn draw_histogram(sb: &mut impl DrawSink, title: &str, hist: Option<&Histogram>, lines: &[String], timescale: TimeScale, width: f64, height: f64) {
+    let label_height = (RULE_HEIGHT - 4.) as f32;
+    let line_height = RULE_HEIGHT - 4.;
+    let text_height = line_height * (lines.len() + 1) as f64;
+    let panel_height = text_height + HIST_HEIGHT + line_height + 16.;
+    let panel_width = HIST_WIDTH + 8.;
+    let offset = Affine::translate((width - SCROLL_WIDTH - panel_width - 8., height - RULE_HEIGHT - panel_height - 8.));
+    sb.fill(
+        Fill::NonZero,
+        offset,
+        &PANEL_BG_COL,
+        None,
+        &Rect::new(0., 0., panel_width, panel_height),
+    );
+    let text = std::iter::once(title).chain(lines.iter().map(|l| l.as_str()));
+    for (num, line) in text.enumerate() {
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(if num == 0 { Color::YELLOW } else { Color::WHITE })),
+            offset * Affine::translate((4., 4. + line_height * (num + 1) as f64 - 2.)),
+            line,
+        );
+    }
+    let Some(hist) = hist.filter(|h| !h.counts.is_empty()) else { return };
+    let bars = offset * Affine::translate((4., text_height + 8.));
+    let most = hist.counts.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = HIST_WIDTH / hist.counts.len() as f64;
+    for (num, &count) in hist.counts.iter().enumerate() {
+        let bar_height = HIST_HEIGHT * count as f64 / most as f64;
+        sb.fill(
+            Fill::NonZero,
+            bars,
+            &BAR_COL,
+            None,
+            &Rect::new(num as f64 * bar_width + 1., HIST_HEIGHT - bar_height, (num + 1) as f64 * bar_width - 1., HIST_HEIGHT),
+        );
+    }
+    // Period at either end of the axis
+    let time = |t: f64| crate::fmt_time_unit(TimeScale { time: t, unit: timescale.unit });
+    let end = hist.start + hist.bin_width * hist.counts.len() as f64;
+    let axis = bars * Affine::translate((0., HIST_HEIGHT + line_height));
+    sb.text(label_height, Some(&Brush::Solid(Color::WHITE)), axis, &time(hist.start));
+    let end_label = time(end);
+    let end_width = 0.6 * label_height as f64 * end_label.len() as f64;
+    sb.text(label_height, Some(&Brush::Solid(Color::WHITE)), axis * Affine::translate((HIST_WIDTH - end_width, 0.)), &end_label);
+}
@@ -1,7 +1,7 @@
 #![feature(return_position_impl_trait_in_trait)]
 
 use instant::{Duration, Instant};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use clap::{/*CommandFactory,*/ Parser};
 use vello::peniko::Color;
 use vello::util::RenderSurface;
@@ -20,16 +20,39 @@ use winit::{
 #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
 mod times;
 use times::*;
+pub use times::{TimeRel, TimeScale, TimeUnit};
 mod hot_reload;
 mod stats;
 mod chart;
 mod face;
 mod sampler;
-use sampler::{AnaSigGen, DigiSig, DigiSigGen, Sampler, SineGen};
+use sampler::{AnaSig, AnaSigGen, Derived, DerivedInput, DigiSig, DigiSigGen, PatternGen, RingBuf, RingSig, Sampler, SineGen, Threshold};
+mod expr;
+use expr::Expr;
+mod decode;
+use decode::Decoder;
 mod datastore;
-use datastore::{DataStore, FileType};
+pub use datastore::{DataStore, FileType};
 mod simple_text;
 use simple_text::SimpleText;
+mod draw;
+use draw::{DrawSink, SceneSink};
+mod prompt;
+mod session;
+mod search;
+pub mod measure;
+mod jitter;
+mod eye;
+mod spectrum;
+mod xy;
+use search::Search;
+use prompt::{Prompt, PromptKind};
+mod stream;
+mod export;
+mod diff;
+mod cli;
+#[cfg(test)]
+mod tests;
 
 const GUI_SCALE      : f64 = 1.5;
 const RULE_HEIGHT    : f64 = GUI_SCALE * 16.;
@@ -37,15 +60,79 @@ const SCROLL_WIDTH   : f64 = GUI_SCALE * 16.;
 const HEIGHT_DIGITAL : f64 = GUI_SCALE * 16.;
 const HEIGHT_ANALOG  : f64 = GUI_SCALE * 32.;
 
+/// Global timescale (may affect minimum waveform display resoltion)
+const TIMESCALE : TimeScale = TimeScale { time: 1., unit: TimeUnit::Ps };
+
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[derive(Parser, Debug)]
-#[command(about, long_about = None, bin_name="cargo run -p wavygravy")]
+#[command(about, long_about = None, bin_name="cargo run -p wavygravy", args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+
+    /// Wave files to display (VCD, CSV or WaveJSON), `-` reads from stdin
     paths: Vec<PathBuf>,
 
+    #[command(flatten)]
+    wavedrom: cli::WaveDromCell,
+
     #[arg(short = 't')]
     test: bool,
+
+    /// Follow waves as they grow (tail mode), scrolling to the newest data
+    #[arg(short = 'f', long)]
+    follow: bool,
+
+    /// Listen for live sample streams on a TCP address or `unix:<path>` socket
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<String>,
+
+    /// Number of samples kept for each live streamed signal
+    #[arg(long, default_value_t = 1000000, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    ring_size: usize,
+
+    /// Add a signal derived from an expression, e.g. `busy = valid & !ready` (repeatable)
+    #[arg(long = "derive", value_name = "[NAME=]EXPR")]
+    derived: Vec<String>,
+
+    /// Add a digital signal comparing an analog one with high and low (hysteresis)
+    /// thresholds, e.g. `rx=top.vrx:1.6:1.2` (repeatable)
+    #[arg(long = "threshold", value_name = "[NAME=]SIGNAL:HIGH[:LOW]")]
+    thresholds: Vec<String>,
+
+    /// Decode a protocol from digital signals, drawn under the signals, e.g.
+    /// `uart:top.tx:115200:8N1`, `spi:sclk:mosi:miso:cs[:MODE[:BITS]]` (`-` for an unused
+    /// data line) or `i2c:scl:sda` (repeatable)
+    #[arg(long = "decode", value_name = "[NAME=]PROTOCOL:SIGNALS...")]
+    decoders: Vec<String>,
+
+    /// Compare the first two wave files, highlighting where signals of the same name differ
+    #[arg(long)]
+    diff: bool,
+
+    /// Largest difference between compared analog values treated as a match
+    #[arg(long, default_value_t = 0.)]
+    tolerance: f64,
+
+    /// Session file keeping named markers and signal groups, loaded at start and saved as
+    /// they change
+    #[arg(long)]
+    session: Option<PathBuf>,
+
+    /// Unit interval of the eye diagram, a bit period such as `400ps` or a clock signal to
+    /// recover it from, estimated from the shortest bit by default
+    #[arg(long, value_name = "PERIOD|CLOCK")]
+    eye: Option<String>,
+}
+
+/// Save markers and groups to the session file, if there is one
+fn save_session(path: &Option<PathBuf>, chart: &chart::Chart, datas: &DataStore) {
+    if let Some(path) = path {
+        if let Err(e) = session::save(path, chart, datas) {
+            eprintln!("Unable to save session {}: {e}", path.display());
+        }
+    }
 }
 
 struct RenderState {
@@ -61,14 +148,19 @@ fn run(
     render_cx: RenderContext,
     #[cfg(target_arch = "wasm32")] render_state: RenderState,
 ) {
-    // Drawing timescale (may affect minimum waveform display resoltion)
-    let /*mut*/ timescale = TimeScale { time: 1., unit: TimeUnit::Ps };
+    // Drawing timescale
+    let /*mut*/ timescale = TIMESCALE;
 
     println!("args {:?}", args);
+    let follow = args.follow || args.listen.is_some() || args.paths.iter().any(|p| p.as_os_str() == "-");
     let mut datas : DataStore = if args.test {
         DataStore::new_test(timescale)
     } else {
         let mut datas = DataStore::new(timescale);
+        if let Err(e) = args.wavedrom.apply(&mut datas) {
+            eprintln!("{e}");
+            return;
+        }
         for path in args.paths {
             let res = datas.load_wave(path.clone(), FileType::TryAny);
             if let Err(e) = res {
@@ -77,8 +169,39 @@ fn run(
                 return;
             }
         }
+        if let Some(addr) = &args.listen {
+            if let Err(e) = datas.listen(addr, args.ring_size) {
+                eprintln!("Unable to listen on {addr}: {e}");
+                return;
+            }
+        }
+        for def in &args.derived {
+            if let Err(e) = datas.add_derived(def) {
+                eprintln!("Unable to add derived signal {def}: {e}");
+                return;
+            }
+        }
+        for def in &args.thresholds {
+            if let Err(e) = datas.add_threshold(def) {
+                eprintln!("Unable to add threshold signal {def}: {e}");
+                return;
+            }
+        }
+        for def in &args.decoders {
+            if let Err(e) = datas.add_decoder(def) {
+                eprintln!("Unable to add decoder {def}: {e}");
+                return;
+            }
+        }
         datas
     };
+    let eye_folding = match args.eye.as_deref().map(|text| eye::Folding::parse(&datas, text)).transpose() {
+        Ok(folding) => folding,
+        Err(e) => {
+            eprintln!("Unable to set eye unit interval: {e}");
+            return;
+        }
+    };
 
     use winit::{event::*, event_loop::ControlFlow};
     let mut renderers: Vec<Option<Renderer>> = vec![];
@@ -122,7 +245,46 @@ fn run(
     chart.set_range( &[tstart, tstart+0.1*(tend-tstart)], &timescale);
     chart.set_max_range( &[tstart, tend], &TimeScale { time: 1., unit: TimeUnit::Ps });
     //chart.set_cursor(56789000. + 8000000.);
+    chart.follow = follow;
+    let session_path = args.session.clone();
+    if let Some(path) = &session_path {
+        if let Err(e) = session::load(path, &mut chart, &datas) {
+            eprintln!("Unable to load session {}: {e}", path.display());
+        }
+    }
+    if args.diff {
+        if datas.get_num_files() < 2 {
+            eprintln!("Comparing waves needs two wave files");
+            return;
+        }
+        match diff::compare(&datas, [0, 1], &[tstart, tend], args.tolerance) {
+            Ok(wdiff) => {
+                println!("{} mismatches in {} signals", wdiff.mismatches().len(), wdiff.diffs.len());
+                chart.mismatches = wdiff.signal_spans();
+            }
+            Err(e) => eprintln!("Unable to compare waves: {e}"),
+        }
+    }
     let mut chart_shown = true;
+    let mut prompt : Option<Prompt> = None;
+    let mut search : Option<Search> = None;
+    let mut measure_shown = false;
+    let mut jitter_shown = false;
+    // Jitter panel lines and histogram, kept until the signal or view changes or data is added
+    let mut jitter_view : Option<(usize, [TimeRel; 2], std::result::Result<(Vec<String>, jitter::Histogram), String>)> = None;
+    let mut spectrum_shown = false;
+    let mut spectrum_window = spectrum::Window::Hann;
+    // Spectrum shown, kept until the signal, view or window changes or data is added
+    let mut spectrum_view : Option<(usize, [TimeRel; 2], spectrum::Window, std::result::Result<spectrum::Spectrum, String>)> = None;
+    // Eye diagram shown, kept until the signal or view changes or data is added
+    let mut eye_shown = false;
+    let mut eye_view : Option<(usize, [TimeRel; 2], std::result::Result<eye::Eye, String>)> = None;
+    // X signal of the XY plot when shown, against the selected signal
+    let mut xy_x : Option<usize> = None;
+    // Trajectory plotted, kept until the signals or view change or data is added, so moving
+    // the cursor only looks up its point
+    let mut xy_view : Option<(usize, usize, [TimeRel; 2], std::result::Result<xy::Trajectory, String>)> = None;
+    let mut xy_persistence = false;
     
     let mut face = face::Face::new(5, 1000., 1000.);
     let mut face_shown = false;
@@ -139,9 +301,11 @@ fn run(
     // navigation_fingers are fingers which are used in the navigation 'zone' at the bottom
     // of the screen. This ensures that one press on the screen doesn't have multiple actions
     let mut mouse_down = false;
+    let mut modifiers = ModifiersState::default();
     let mut prior_position: Option<Vec2> = None;
     let mut profile_stored = None;
     let mut profile_taken = Instant::now();
+    let mut follow_polled = Instant::now();
     // _event_loop is used on non-wasm platforms to create new windows
     event_loop.run(move |event, _event_loop, control_flow| match event {
         Event::WindowEvent {
@@ -155,7 +319,12 @@ fn run(
             match event {
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                 WindowEvent::KeyboardInput { input, .. } => {
-                    if input.state == ElementState::Pressed {
+                    if input.state == ElementState::Pressed && prompt.is_some() {
+                        // Typed text arrives as ReceivedCharacter
+                        if input.virtual_keycode == Some(VirtualKeyCode::Escape) {
+                            prompt = None;
+                        }
+                    } else if input.state == ElementState::Pressed {
                         match input.virtual_keycode {
 /*
                             Some(VirtualKeyCode::Left) => scene_ix = scene_ix.saturating_sub(1),
@@ -176,9 +345,19 @@ fn run(
                                 transform = Affine::IDENTITY;
                             }
 */
+                            Some(VirtualKeyCode::X) if modifiers.shift() => {
+                                // The selected signal becomes X, plotted against later selections
+                                xy_x = if xy_x.is_some() { None } else { chart.selected };
+                                if xy_x.is_none() && chart.selected.is_none() {
+                                    eprintln!("Select the X signal for an XY plot");
+                                }
+                            }
                             Some(VirtualKeyCode::X) => {
                                 chart_shown = !chart_shown;
                             }
+                            Some(VirtualKeyCode::O) => {
+                                xy_persistence = !xy_persistence;
+                            }
                             Some(VirtualKeyCode::S) => {
                                 stats_shown = !stats_shown;
                             }
@@ -194,6 +373,53 @@ fn run(
                             Some(VirtualKeyCode::C) => {
                                 stats.clear_min_and_max();
                             }
+                            Some(VirtualKeyCode::T) => {
+                                chart.follow = !chart.follow;
+                            }
+                            Some(VirtualKeyCode::E) => {
+                                let path = Path::new("export.vcd");
+                                let sigs = chart.export_signals(datas.get_num_signals());
+                                match export::export_vcd(&datas, &sigs, &chart.measure_range(), path) {
+                                    Ok(()) => println!("Wrote VCD to path {path:?}"),
+                                    Err(e) => eprintln!("Failed to write VCD {e}"),
+                                }
+                            }
+                            Some(VirtualKeyCode::W) => {
+                                let path = Path::new("export.csv");
+                                let sigs = chart.export_signals(datas.get_num_signals());
+                                match export::export_csv(&datas, &sigs, &chart.measure_range(), export::CsvMode::Transitions, path) {
+                                    Ok(()) => println!("Wrote CSV to path {path:?}"),
+                                    Err(e) => eprintln!("Failed to write CSV {e}"),
+                                }
+                            }
+                            Some(VirtualKeyCode::G) if modifiers.shift() => {
+                                if let Some(sig) = chart.selected {
+                                    let name = chart.group_of(sig).unwrap_or("").to_string();
+                                    prompt = Some(Prompt::new(PromptKind::Group, 'g').with_text(&name));
+                                }
+                            }
+                            Some(VirtualKeyCode::G) => {
+                                let path = Path::new("export.svg");
+                                let width = render_state.surface.config.width;
+                                let height = render_state.surface.config.height;
+                                match export::export_svg(&chart, &datas, width as f64, height as f64, path) {
+                                    Ok(()) => println!("Wrote SVG to path {path:?}"),
+                                    Err(e) => eprintln!("Failed to write SVG {e}"),
+                                }
+                            }
+                            Some(VirtualKeyCode::Q) => {
+                                // Clock cycles between the cursors, or over the view
+                                let path = Path::new("export.json");
+                                let sigs = chart.export_signals(datas.get_num_signals());
+                                let res = match export::find_clock(&datas, None) {
+                                    Some(clock) => export::export_wavedrom(&datas, clock, &sigs, &chart.measure_range(), path),
+                                    None => Err("No clock signal".into()),
+                                };
+                                match res {
+                                    Ok(()) => println!("Wrote WaveDrom to path {path:?}"),
+                                    Err(e) => eprintln!("Failed to write WaveDrom {e}"),
+                                }
+                            }
                             Some(VirtualKeyCode::P) => {
                                 if let Some(renderer) = &renderers[render_state.surface.dev_id] {
                                     if let Some(profile_result) = &renderer
@@ -228,6 +454,95 @@ fn run(
                                     },
                                 );
                             }
+                            Some(VirtualKeyCode::D) => {
+                                prompt = Some(Prompt::new(PromptKind::Derive, 'd'));
+                            }
+                            Some(VirtualKeyCode::M) => {
+                                if chart.add_marker_at_cursor().is_some() {
+                                    save_session(&session_path, &chart, &datas);
+                                }
+                            }
+                            Some(VirtualKeyCode::R) => {
+                                let width = render_state.surface.config.width as f64;
+                                if let Some(idx) = chart.current_marker(width) {
+                                    prompt = Some(Prompt::new(PromptKind::RenameMarker(idx), 'r').with_text(&chart.markers[idx].name));
+                                }
+                            }
+                            Some(VirtualKeyCode::Delete) => {
+                                let width = render_state.surface.config.width as f64;
+                                if let Some(idx) = chart.current_marker(width) {
+                                    chart.delete_marker(idx);
+                                    save_session(&session_path, &chart, &datas);
+                                }
+                            }
+                            Some(VirtualKeyCode::RBracket) => {
+                                chart.goto_marker(true);
+                            }
+                            Some(VirtualKeyCode::LBracket) => {
+                                chart.goto_marker(false);
+                            }
+                            Some(VirtualKeyCode::Up) => {
+                                chart.select_next(datas.get_num_signals(), false);
+                            }
+                            Some(VirtualKeyCode::Down) => {
+                                chart.select_next(datas.get_num_signals(), true);
+                            }
+                            Some(VirtualKeyCode::Right) => {
+                                chart.goto_edge(&datas, true);
+                            }
+                            Some(VirtualKeyCode::Left) => {
+                                chart.goto_edge(&datas, false);
+                            }
+                            Some(VirtualKeyCode::Tab) => {
+                                chart.edge_filter = chart.edge_filter.next();
+                            }
+                            Some(VirtualKeyCode::Slash) => {
+                                prompt = Some(Prompt::new(PromptKind::Search, '/'));
+                            }
+                            Some(VirtualKeyCode::N) => {
+                                // Shift-N searches backwards
+                                let curs = chart.cursor.unwrap_or(chart.time_range[0]);
+                                if let Some(t) = search.as_ref().and_then(|s| s.next(curs, !modifiers.shift())) {
+                                    chart.goto_time(t);
+                                }
+                            }
+                            Some(VirtualKeyCode::A) => {
+                                measure_shown = !measure_shown;
+                            }
+                            Some(VirtualKeyCode::J) if modifiers.shift() => {
+                                // Shift-J exports the jitter of the selected signal over the view
+                                let path = Path::new("jitter.csv");
+                                let res : Result<()> = match chart.selected {
+                                    Some(sig) => jitter::analyse(&datas, sig, &chart.time_range, None)
+                                        .and_then(|jit| jit.write_csv(datas.get_timescale(), path)),
+                                    None => Err(Box::new(std::io::Error::other("No signal selected"))),
+                                };
+                                match res {
+                                    Ok(()) => println!("Wrote jitter CSV to path {path:?}"),
+                                    Err(e) => eprintln!("Failed to write jitter CSV {e}"),
+                                }
+                            }
+                            Some(VirtualKeyCode::J) => {
+                                jitter_shown = !jitter_shown;
+                            }
+                            Some(VirtualKeyCode::U) if modifiers.shift() => {
+                                spectrum_window = spectrum_window.next();
+                            }
+                            Some(VirtualKeyCode::U) => {
+                                spectrum_shown = !spectrum_shown;
+                            }
+                            Some(VirtualKeyCode::Y) => {
+                                eye_shown = !eye_shown;
+                            }
+                            Some(VirtualKeyCode::Z) => {
+                                chart.zoom_to_cursors();
+                            }
+                            Some(VirtualKeyCode::Period) => {
+                                chart.goto_mismatch(true);
+                            }
+                            Some(VirtualKeyCode::Comma) => {
+                                chart.goto_mismatch(false);
+                            }
                             Some(VirtualKeyCode::Plus) | Some(VirtualKeyCode::Equals) => {
                                 chart.do_zoom(0.9);
                                 render_state.window.request_redraw();
@@ -282,17 +597,68 @@ fn run(
                     }
                 }
                 */
+                WindowEvent::ReceivedCharacter(c) => {
+                    if let Some(entry) = &mut prompt {
+                        if entry.handle_char(*c) {
+                            let entry = prompt.take().unwrap();
+                            match entry.kind {
+                                PromptKind::Derive => {
+                                    if let Err(e) = datas.add_derived(&entry.text) {
+                                        eprintln!("Unable to add derived signal {}: {e}", entry.text);
+                                    }
+                                }
+                                PromptKind::Search => {
+                                    match Search::new(&datas, &entry.text, &chart.max_range) {
+                                        Ok(found) => {
+                                            println!("{} matches of {}", found.matches().len(), found.text);
+                                            let curs = chart.cursor.unwrap_or(chart.time_range[0]);
+                                            if let Some(t) = found.next(curs, true) {
+                                                chart.goto_time(t);
+                                            }
+                                            search = Some(found);
+                                        }
+                                        Err(e) => eprintln!("Unable to search for {}: {e}", entry.text),
+                                    }
+                                }
+                                PromptKind::RenameMarker(idx) => {
+                                    if let Some(marker) = chart.markers.get_mut(idx) {
+                                        marker.name = entry.text.trim().to_string();
+                                        save_session(&session_path, &chart, &datas);
+                                    }
+                                }
+                                PromptKind::Group => {
+                                    if let Some(sig) = chart.selected {
+                                        chart.group_signal(sig, &entry.text);
+                                        save_session(&session_path, &chart, &datas);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
                 WindowEvent::Resized(size) => {
                     render_cx.resize_surface(&mut render_state.surface, size.width, size.height);
                     render_state.window.request_redraw();
                 }
+                WindowEvent::ModifiersChanged(state) => {
+                    modifiers = *state;
+                }
                 WindowEvent::MouseInput { state, button, .. } => {
-                    if button == &MouseButton::Left {
+                    // Right or shift-click places the second cursor
+                    if button == &MouseButton::Left || button == &MouseButton::Right {
                         mouse_down = state == &ElementState::Pressed;
                         if let Some(_prior) = prior_position {
                             let width = render_state.surface.config.width;
                             let height = render_state.surface.config.height;
-                            chart.handle_mousedown(&prior_position, width as f64, height as f64);
+                            let secondary = button == &MouseButton::Right || modifiers.shift();
+                            chart.handle_mousedown(&prior_position, width as f64, height as f64, secondary);
+                            if let (Some(prior), ElementState::Pressed) = (&prior_position, state) {
+                                if chart.handle_group_click(&datas, prior, width as f64) {
+                                    save_session(&session_path, &chart, &datas);
+                                } else {
+                                    chart.handle_select_click(&datas, prior, width as f64);
+                                }
+                            }
                         }
                     }
                 }
@@ -354,6 +720,21 @@ fn run(
                     * transform;
             }
 */
+            if follow && follow_polled.elapsed() > Duration::from_millis(100) {
+                follow_polled = Instant::now();
+                match datas.poll() {
+                    Ok(true) => {
+                        jitter_view = None;
+                        spectrum_view = None;
+                        xy_view = None;
+                        eye_view = None;
+                        let (tstart, tend) = datas.get_range();
+                        chart.extend_max_range(&[tstart, tend]);
+                    }
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Unable to follow waves: {e}"),
+                }
+            }
             if let Some(render_state) = &mut render_state {
                 render_state.window.request_redraw();
             }
@@ -378,9 +759,151 @@ fn run(
             //builder.append(&fragment, None); //Some(transform));
             if chart_shown {
                 chart.draw_layer::<bool>(
-                    &mut builder,
-                    &mut simple_text,
-                    &mut datas,
+                    &mut SceneSink::new(&mut builder, &mut simple_text),
+                    &datas,
+                    width as f64,
+                    height as f64,
+                );
+            }
+            if chart_shown && measure_shown {
+                // Analog measurements of the selected signal between the cursors, or over the
+                // view. Digital timing is over the view, with the edges between the cursors.
+                let (title, items) = match chart.selected {
+                    Some(sig) if datas.get_signal_type_idx(sig).0 == datastore::SigType::Digital => {
+                        let range = chart.time_range;
+                        let title = format!("{} {} - {}", datas.get_signal_field(sig).name, fmt_time(range[0]), fmt_time(range[1]));
+                        let timescale = datas.get_timescale();
+                        let between = chart.measure_range();
+                        let measured = measure::digital(&datas, sig, &range).and_then(|stats| {
+                            let mut items = stats.display_items(timescale);
+                            if between != range {
+                                items.push(("edges between cursors".to_string(), measure::count_edges(&datas, sig, &between)?.to_string()));
+                            }
+                            Ok(items)
+                        });
+                        match measured {
+                            Ok(items) => (title, items),
+                            Err(e) => (title, vec![("error".to_string(), e.to_string())]),
+                        }
+                    }
+                    Some(sig) => {
+                        let range = chart.measure_range();
+                        let title = format!("{} {} - {}", datas.get_signal_field(sig).name, fmt_time(range[0]), fmt_time(range[1]));
+                        match measure::analog(&datas, sig, &range) {
+                            Ok(stats) => (title, stats.items().iter().map(|(name, val)| (name.to_string(), measure::fmt_measure(*val))).collect()),
+                            Err(e) => (title, vec![("error".to_string(), e.to_string())]),
+                        }
+                    }
+                    None => ("Select a signal to measure".to_string(), Vec::new()),
+                };
+                measure::draw_panel(&mut SceneSink::new(&mut builder, &mut simple_text), &title, &items, width as f64);
+            }
+            if chart_shown && jitter_shown {
+                // Period histogram of the selected clock over the view
+                let timescale = datas.get_timescale();
+                let (title, hist, lines) = match chart.selected {
+                    Some(sig) => {
+                        if !matches!(&jitter_view, Some((s, range, _)) if *s == sig && *range == chart.time_range) {
+                            let res = jitter::analyse(&datas, sig, &chart.time_range, None).map(|jit| {
+                                let mut lines = vec![format!("edges: {}", jit.edges.len())];
+                                lines.extend(jit.items().iter().map(|(name, val)| format!("{name}: {} {}", measure::fmt_measure(*val), timescale.unit)));
+                                (lines, jit.histogram(jitter::BINS))
+                            }).map_err(|e| e.to_string());
+                            jitter_view = Some((sig, chart.time_range, res));
+                        }
+                        let title = format!("{} jitter", datas.get_signal_field(sig).name);
+                        match jitter_view.as_ref().map(|view| &view.2) {
+                            Some(Ok((lines, hist))) => (title, Some(hist), lines.clone()),
+                            Some(Err(e)) => (title, None, vec![e.clone()]),
+                            None => (title, None, Vec::new()),
+                        }
+                    }
+                    None => ("Select a clock for jitter".to_string(), None, Vec::new()),
+                };
+                jitter::draw_histogram(&mut SceneSink::new(&mut builder, &mut simple_text), &title, hist, &lines, timescale, width as f64, height as f64);
+            }
+            if chart_shown && spectrum_shown {
+                // Spectrum of the selected analog signal over the view, following it as it pans
+                let (title, spec, lines) = match chart.selected {
+                    Some(sig) => {
+                        if !matches!(&spectrum_view, Some((s, range, window, _)) if *s == sig && *range == chart.time_range && *window == spectrum_window) {
+                            let res = spectrum::spectrum(&datas, sig, &chart.time_range, spectrum_window).map_err(|e| e.to_string());
+                            spectrum_view = Some((sig, chart.time_range, spectrum_window, res));
+                        }
+                        let title = format!("{} spectrum ({spectrum_window})", datas.get_signal_field(sig).name);
+                        match spectrum_view.as_ref().map(|view| &view.3) {
+                            Some(Ok(spec)) => {
+                                let lines = match spec.peak() {
+                                    Some((hz, db)) => vec![format!("peak: {} at {:.1} dB", fmt_freq(hz), db)],
+                                    None => Vec::new(),
+                                };
+                                (title, Some(spec), lines)
+                            }
+                            Some(Err(e)) => (title, None, vec![e.clone()]),
+                            None => (title, None, Vec::new()),
+                        }
+                    }
+                    None => ("Select an analog signal for its spectrum".to_string(), None, Vec::new()),
+                };
+                spectrum::draw(&mut SceneSink::new(&mut builder, &mut simple_text), &title, spec, &lines, width as f64, height as f64);
+            }
+            if let (true, Some(x_sig)) = (chart_shown, xy_x) {
+                // Selected signal against the X signal over the view
+                let x_name = datas.get_signal_field(x_sig).name;
+                let (title, traj, lines) = match chart.selected {
+                    Some(y_sig) => {
+                        if !matches!(&xy_view, Some((x, y, range, _)) if *x == x_sig && *y == y_sig && *range == chart.time_range) {
+                            let res = xy::trajectory(&datas, x_sig, y_sig, &chart.time_range).map_err(|e| e.to_string());
+                            xy_view = Some((x_sig, y_sig, chart.time_range, res));
+                        }
+                        let title = format!("{} vs {x_name}", datas.get_signal_field(y_sig).name);
+                        match xy_view.as_ref().map(|view| &view.3) {
+                            Some(Ok(traj)) => {
+                                let lines = vec![
+                                    format!("x: {} to {}", measure::fmt_measure(traj.x_range[0]), measure::fmt_measure(traj.x_range[1])),
+                                    format!("y: {} to {}", measure::fmt_measure(traj.y_range[0]), measure::fmt_measure(traj.y_range[1])),
+                                ];
+                                (title, Some(traj), lines)
+                            }
+                            Some(Err(e)) => (title, None, vec![e.clone()]),
+                            None => (title, None, Vec::new()),
+                        }
+                    }
+                    None => (format!("Select a signal to plot against {x_name}"), None, Vec::new()),
+                };
+                xy::draw(&mut SceneSink::new(&mut builder, &mut simple_text), &title, traj, &lines, chart.cursor, xy_persistence, width as f64, height as f64);
+            }
+            if chart_shown && eye_shown {
+                // Eye diagram of the selected analog signal over the view
+                let timescale = datas.get_timescale();
+                let (title, folded, lines) = match chart.selected {
+                    Some(sig) => {
+                        if !matches!(&eye_view, Some((s, range, _)) if *s == sig && *range == chart.time_range) {
+                            let res = eye::eye(&datas, sig, &chart.time_range, eye_folding, eye::BINS).map_err(|e| e.to_string());
+                            eye_view = Some((sig, chart.time_range, res));
+                        }
+                        let title = format!("{} eye", datas.get_signal_field(sig).name);
+                        match eye_view.as_ref().map(|view| &view.2) {
+                            Some(Ok(folded)) => {
+                                let time = |t: f64| fmt_time_unit(TimeScale { time: t, unit: timescale.unit });
+                                let lines = vec![
+                                    format!("UI: {}", time(folded.ui)),
+                                    format!("height: {}", measure::fmt_measure(folded.height())),
+                                    format!("width: {}", time(folded.width())),
+                                ];
+                                (title, Some(folded), lines)
+                            }
+                            Some(Err(e)) => (title, None, vec![e.clone()]),
+                            None => (title, None, Vec::new()),
+                        }
+                    }
+                    None => ("Select an analog signal for its eye".to_string(), None, Vec::new()),
+                };
+                eye::draw(&mut SceneSink::new(&mut builder, &mut simple_text), &title, folded, &lines, timescale, width as f64, height as f64);
+            }
+            if let Some(entry) = &prompt {
+                entry.draw_layer(
+                    &mut SceneSink::new(&mut builder, &mut simple_text),
                     width as f64,
                     height as f64,
                 );
@@ -589,6 +1112,9 @@ pub fn main() -> Result<()> {
     #[cfg(not(target_arch = "wasm32"))]
     env_logger::init();
     let args = Args::parse();
+    if let Some(cmd) = args.command {
+        return cli::run(cmd);
+    }
     let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
     #[allow(unused_mut)]
     let mut render_cx = RenderContext::new().unwrap();
@@ -0,0 +1,230 @@
+//! Measurements over a time window, and the panel showing them. Library users measure
+//! signals of a `DataStore` with `analog`, `digital` and `count_edges`.
+use vello::{
+    kurbo::{Affine, Rect},
+    peniko::{Brush, Color, Fill},
+};
+use crate::{DataStore, DrawSink, Result, Sampler, TimeRel, TimeScale, RULE_HEIGHT, SCROLL_WIDTH};
+use crate::datastore::SigType;
+
+pub(crate) const PANEL_BG_COL : Brush = Brush::Solid(Color::rgba8(30, 30, 60, 220));
+
+/// Statistics of an analog signal, following its trace as drawn: straight lines between
+/// samples, or held values until each change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalogStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub rms: f64,
+    pub pk_pk: f64,
+    /// Integral over time, in value seconds
+    pub integral: f64,
+}
+
+impl AnalogStats {
+    /// Measure over `range` from the value at its start and the points of its trace within
+    /// it, joined by straight lines (held values step between two points at the same time)
+    pub fn from_trace(initial: f64, trace: &[(TimeRel, f64)], range: &[TimeRel; 2], timescale: TimeScale) -> Self {
+        let (mut min, mut max) = (initial, initial);
+        let (mut sum, mut sum_sq) = (0., 0.);
+        // Integrate the line from (t0, v0) to (t1, v1)
+        let mut line = |(t0, v0): (TimeRel, f64), (t1, v1): (TimeRel, f64)| {
+            sum += 0.5 * (v0 + v1) * (t1 - t0);
+            sum_sq += (v0 * v0 + v0 * v1 + v1 * v1) / 3. * (t1 - t0);
+        };
+        let mut prev = (range[0], initial);
+        for &(t, val) in trace.iter().filter(|p| p.0 > range[0] && p.0 <= range[1]) {
+            // A step takes no time, a value stepped to at the end of the range isn't reached
+            if t > prev.0 {
+                line(prev, (t, val));
+                min = min.min(prev.1.min(val));
+                max = max.max(prev.1.max(val));
+            }
+            prev = (t, val);
+        }
+        line(prev, (range[1], prev.1));
+        let duration = range[1] - range[0];
+        let (mean, rms) = if duration > 0. { (sum / duration, (sum_sq / duration).sqrt()) } else { (initial, initial.abs()) };
+        AnalogStats {
+            min,
+            max,
+            mean,
+            rms,
+            pk_pk: max - min,
+            integral: sum * timescale.secs(),
+        }
+    }
+
+    /// Measure a sampler over `range`, at the resolution set by `set_iter_scale`
+    pub fn measure(smpl: &dyn Sampler<f32>, range: &[TimeRel; 2], timescale: TimeScale) -> Result<Self> {
+        let (initial, trace) = crate::export::trace_of(smpl, range, timescale)?;
+        Ok(Self::from_trace(initial, &trace, range, timescale))
+    }
+
+    /// Names and values, in display order
+    pub fn items(&self) -> [(&'static str, f64); 6] {
+        [
+            ("min", self.min),
+            ("max", self.max),
+            ("mean", self.mean),
+            ("rms", self.rms),
+            ("pk-pk", self.pk_pk),
+            ("integral", self.integral),
+        ]
+    }
+}
+
+/// Measure analog signal `sig` over `range`
+pub fn analog(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<AnalogStats> {
+    let (SigType::Analog, idx) = datas.get_signal_type_idx(sig) else {
+        let msg = format!("Signal is not analog: {}", datas.get_signal_field(sig).name);
+        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)));
+    };
+    let timescale = datas.get_timescale();
+    let mut smpl = datas.get_ana_sampler(idx).unwrap().borrow_mut();
+    smpl.set_iter_scale(range, &timescale, crate::export::GEN_SAMPLES);
+    AnalogStats::measure(&*smpl, range, timescale)
+}
+
+/// Timing of a digital signal, from complete periods (rising edge to rising edge) and
+/// pulses. Times are in global units, and are None without a complete period or pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DigitalStats {
+    /// Number of rising and falling edges
+    pub edges: usize,
+    pub period_avg: Option<f64>,
+    pub period_min: Option<f64>,
+    pub period_max: Option<f64>,
+    /// Frequency from the average period (in Hz)
+    pub frequency: Option<f64>,
+    /// Fraction of complete periods spent high
+    pub duty: Option<f64>,
+    pub high_min: Option<f64>,
+    pub high_max: Option<f64>,
+    pub low_min: Option<f64>,
+    pub low_max: Option<f64>,
+}
+
+impl DigitalStats {
+    /// Measure from the level changes within a range
+    pub fn from_changes(changes: &[(TimeRel, bool)], timescale: TimeScale) -> Self {
+        fn min_max(vals: &[f64]) -> (Option<f64>, Option<f64>) {
+            (vals.iter().copied().reduce(f64::min), vals.iter().copied().reduce(f64::max))
+        }
+        let rises : Vec<TimeRel> = changes.iter().filter(|c| c.1).map(|c| c.0).collect();
+        let periods : Vec<f64> = rises.windows(2).map(|w| w[1] - w[0]).collect();
+        let mut high = Vec::new();
+        let mut low = Vec::new();
+        for pair in changes.windows(2) {
+            let width = pair[1].0 - pair[0].0;
+            if pair[0].1 { high.push(width) } else { low.push(width) }
+        }
+        let (period_min, period_max) = min_max(&periods);
+        let (high_min, high_max) = min_max(&high);
+        let (low_min, low_max) = min_max(&low);
+        let period_avg = (!periods.is_empty()).then(|| (rises[rises.len() - 1] - rises[0]) / periods.len() as f64);
+        // High time of the pulses within complete periods
+        let duty = period_avg.map(|_| {
+            let (first, last) = (rises[0], rises[rises.len() - 1]);
+            let high_time : f64 = changes.windows(2)
+                .filter(|pair| pair[0].1 && pair[0].0 >= first && pair[1].0 <= last)
+                .map(|pair| pair[1].0 - pair[0].0)
+                .sum();
+            high_time / (last - first)
+        });
+        DigitalStats {
+            edges: changes.len(),
+            period_avg,
+            period_min,
+            period_max,
+            frequency: period_avg.map(|p| 1. / (p * timescale.secs())),
+            duty,
+            high_min,
+            high_max,
+            low_min,
+            low_max,
+        }
+    }
+
+    /// Names and values, in display order. Frequency is in Hz and duty a fraction.
+    pub fn items(&self) -> [(&'static str, Option<f64>); 10] {
+        [
+            ("edges", Some(self.edges as f64)),
+            ("period", self.period_avg),
+            ("period min", self.period_min),
+            ("period max", self.period_max),
+            ("frequency", self.frequency),
+            ("duty", self.duty),
+            ("high min", self.high_min),
+            ("high max", self.high_max),
+            ("low min", self.low_min),
+            ("low max", self.low_max),
+        ]
+    }
+
+    /// Names and values formatted for display, times in units of `timescale`
+    pub fn display_items(&self, timescale: TimeScale) -> Vec<(String, String)> {
+        self.items().iter().map(|&(name, val)| {
+            let text = match (name, val) {
+                (_, None) => "-".to_string(),
+                ("edges", Some(n)) => format!("{n}"),
+                ("frequency", Some(hz)) => crate::fmt_freq(hz),
+                ("duty", Some(d)) => format!("{:.1}%", 100. * d),
+                (_, Some(t)) => crate::fmt_time_unit(TimeScale { time: t, unit: timescale.unit }),
+            };
+            (name.to_string(), text)
+        }).collect()
+    }
+}
+
+/// Measure timing of digital signal `sig` over `range`
+pub fn digital(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<DigitalStats> {
+    if datas.get_signal_type_idx(sig).0 != SigType::Digital {
+        let msg = format!("Signal is not digital: {}", datas.get_signal_field(sig).name);
+        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)));
+    }
+    let (_, changes) = crate::export::read_changes(datas, sig, range)?;
+    let changes : Vec<(TimeRel, bool)> = changes.into_iter().map(|(t, val)| (t, val != 0.)).collect();
+    Ok(DigitalStats::from_changes(&changes, datas.get_timescale()))
+}
+
+/// Count edges of digital signal `sig` within `range`
+pub fn count_edges(datas: &DataStore, sig: usize, range: &[TimeRel; 2]) -> Result<usize> {
+    Ok(digital(datas, sig, range)?.edges)
+}
+
+/// Format measured value, small values in exponent form
+pub fn fmt_measure(val: f64) -> String {
+    if val != 0. && val.abs() < 1e-3 {
+        format!("{val:.4e}")
+    } else {
+        format!("{val:.4}")
+    }
+}
+
+/// Draw titled panel of measurements at the top right of the waveform window
+pub fn draw_panel(sb: &mut impl DrawSink, title: &str, items: &[(String, String)], width: f64) {
+    let label_height = (RULE_HEIGHT - 4.) as f32;
+    let line_height = RULE_HEIGHT - 4.;
+    let chars = items.iter().map(|(name, value)| name.len() + value.len() + 2).chain([title.len()]).max().unwrap_or(0);
+    let panel_width = 0.6 * label_height as f64 * chars as f64 + 8.;
+    let panel_height = line_height * (items.len() + 1) as f64 + 8.;
+    let offset = Affine::translate((width - SCROLL_WIDTH - panel_width - 8., RULE_HEIGHT + 8.));
+    sb.fill(
+        Fill::NonZero,
+        offset,
+        &PANEL_BG_COL,
+        None,
+        &Rect::new(0., 0., panel_width, panel_height),
+    );
+    let lines = std::iter::once(title.to_string()).chain(items.iter().map(|(name, value)| format!("{name}: {value}")));
+    for (num, line) in lines.enumerate() {
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(if num == 0 { Color::YELLOW } else { Color::WHITE })),
+            offset * Affine::translate((4., 4. + line_height * (num + 1) as f64 - 2.)),
+            &line,
+        );
+    }
+}
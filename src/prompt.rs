@@ -0,0 +1,89 @@
+//! Single line text entry shown over the bottom ruler
+use vello::{
+    kurbo::{Affine, Rect},
+    peniko::{Brush, Color, Fill},
+};
+use crate::{DrawSink, RULE_HEIGHT};
+
+const PROMPT_BG_COL : Brush = Brush::Solid(Color::rgba8(40, 40, 80, 255));
+
+/// What the entered text is used for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptKind {
+    /// Expression of a derived signal
+    Derive,
+    /// New name of the marker with this index
+    RenameMarker(usize),
+    /// Condition searched for
+    Search,
+    /// Group the selected signal is moved into, none when empty
+    Group,
+}
+
+impl PromptKind {
+    fn label(self) -> &'static str {
+        match self {
+            PromptKind::Derive => "Derive:",
+            PromptKind::RenameMarker(_) => "Marker name:",
+            PromptKind::Search => "Search:",
+            PromptKind::Group => "Group:",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub text: String,
+    /// Character typed by the key that opened the prompt, not entered as text
+    opened_by: Option<char>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, opened_by: char) -> Self {
+        Self {
+            kind,
+            text: String::new(),
+            opened_by: Some(opened_by),
+        }
+    }
+
+    /// Start with `text` entered, e.g. a name being changed
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+
+    /// Handle a typed character, return true when entry is complete (Enter)
+    pub fn handle_char(&mut self, c: char) -> bool {
+        if self.opened_by.take().is_some_and(|o| o.eq_ignore_ascii_case(&c)) {
+            return false;
+        }
+        match c {
+            '\r' | '\n' => return true,
+            '\u{8}' | '\u{7f}' => { self.text.pop(); }
+            c if !c.is_control() => self.text.push(c),
+            _ => {}
+        }
+        false
+    }
+
+    /// Draw prompt and text entered so far over the bottom ruler
+    pub fn draw_layer(&self, sb: &mut impl DrawSink, width: f64, height: f64) {
+        let offset = Affine::translate((0., height - RULE_HEIGHT));
+        sb.fill(
+            Fill::NonZero,
+            offset,
+            &PROMPT_BG_COL,
+            None,
+            &Rect::new(0., 0., width, RULE_HEIGHT),
+        );
+        let label = format!("{} {}_", self.kind.label(), self.text);
+        sb.text(
+            (RULE_HEIGHT - 4.) as f32,
+            Some(&Brush::Solid(Color::WHITE)),
+            offset * Affine::translate((4., RULE_HEIGHT - 5.)),
+            &label,
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind};
+use crate::{Result, Sampler, TimeRel, TimeScale};
+use wavefmt::{FieldInfo, IndexedWave, WaveFile};
+
+pub struct AnaSig<T: WaveFile> {
+    wave: Rc<RefCell<T>>,
+    sig: usize,
+    step: bool,
+}
+
+pub trait AnaSigIo {
+    /// Start reading changes of `sig` after `range[0]`, other signals are unaffected
+    fn prepare_iter(&mut self, sig: usize, range: &[f64; 2]);
+
+    fn read_record_with_time(&mut self, buf: &mut Vec<u8>, sig: usize) -> Result<(f32, TimeRel)>;
+
+    fn value_at(&self, sig: usize, t: TimeRel) -> f32;
+
+    /// Last sample at or before `t`, and first sample after it
+    fn samples_around(&self, sig: usize, t: TimeRel) -> [Option<(f32, TimeRel)>; 2];
+
+    /// Return (min, max) value of signal
+    fn get_limits(&self, sig: usize) -> (f32, f32);
+}
+
+impl<T: WaveFile> AnaSig<T> {
+    /// Create analog signal, `step` holds each value until the next change (buses, VCD reals)
+    /// rather than drawing straight lines between samples
+    pub fn new(wave: Rc<RefCell<T>>, idx: usize, step: bool) -> Self {
+        AnaSig {
+            wave,
+            sig: idx,
+            step,
+        }
+    }
+}
+
+impl<T: WaveFile + AnaSigIo> Sampler<f32> for AnaSig<T> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_ANALOG }
+
+    /// Return signal y scale (largest absolute value, so zero stays on the centre line)
+    fn get_yscale(&self) -> f64 {
+        let (min, max) = self.wave.borrow().get_limits(self.sig);
+        let height = min.abs().max(max.abs()) as f64;
+        if height > 0. { height } else { 1. }
+    }
+
+    fn get_label(&self) -> String {
+        let fi_bind = self.wave.borrow();
+        let fi : &FieldInfo = fi_bind.get_field_info(self.sig);
+        fi.name.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (f32, TimeRel)> + '_>> {
+        let mut wv_bind = self.wave.borrow_mut();
+        wv_bind.prepare_iter(self.sig, range);
+        let curval = wv_bind.value_at(self.sig, range[0]);
+        Ok(Box::new(AnaSigIter {
+            smpl: self,
+            range: *range,
+            recbuf: Vec::new(),
+            curval,
+            held: None,
+            done: false,
+        }))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> f32 {
+        self.wave.borrow().value_at(self.sig, t)
+    }
+
+    fn get_point_before(&self, t: TimeRel, s: TimeScale) -> (f32, TimeRel) {
+        match self.wave.borrow().samples_around(self.sig, t) {
+            [Some(sample), _] if !self.step => sample,
+            _ => (self.get_value_at(t, s), t),
+        }
+    }
+
+    fn get_point_after(&self, t: TimeRel, s: TimeScale) -> (f32, TimeRel) {
+        match self.wave.borrow().samples_around(self.sig, t) {
+            [_, Some(sample)] if !self.step => sample,
+            _ => (self.get_value_at(t, s), t),
+        }
+    }
+}
+
+pub struct AnaSigIter<'r, W: WaveFile + AnaSigIo> {
+    smpl: &'r AnaSig<W>,
+    range: [TimeRel; 2],
+    recbuf: Vec<u8>,
+    curval: f32,
+    held: Option<(f32, TimeRel)>,
+    done: bool,
+}
+
+impl<W: WaveFile + AnaSigIo> Iterator for AnaSigIter<'_, W> {
+    type Item = (f32, TimeRel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.held.take() {
+            return Some(item);
+        }
+        if self.done { return None; }
+        let mut wv_bind = self.smpl.wave.borrow_mut();
+        match wv_bind.read_record_with_time(&mut self.recbuf, self.smpl.sig) {
+            Ok((val, time)) if time <= self.range[1] => {
+                let prev = std::mem::replace(&mut self.curval, val);
+                if self.smpl.step {
+                    // Hold previous value up to the change
+                    self.held = Some((val, time));
+                    Some((prev, time))
+                } else {
+                    Some((val, time))
+                }
+            }
+            _ => {
+                // Extend last value to end of range
+                self.done = true;
+                Some((self.curval, self.range[1]))
+            }
+        }
+    }
+}
+
+impl<W: IndexedWave> AnaSigIo for W {
+    fn prepare_iter(&mut self, sig: usize, range: &[f64; 2]) {
+        self.get_index_mut().prepare_field_iter(sig, range);
+    }
+
+    fn read_record_with_time(&mut self, _buf: &mut Vec<u8>, sig: usize) -> Result<(f32, TimeRel)> {
+        let (time, val) = self.get_index_mut().next_change(sig)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "No more changes"))?;
+        Ok((val as f32, time))
+    }
+
+    fn value_at(&self, sig: usize, t: TimeRel) -> f32 {
+        self.get_index().value_at(sig, t) as f32
+    }
+
+    fn samples_around(&self, sig: usize, t: TimeRel) -> [Option<(f32, TimeRel)>; 2] {
+        let index = self.get_index();
+        let changes = index.get_changes(sig);
+        let idx = index.first_after(sig, t);
+        let sample = |idx: usize| changes.get(idx).map(|&(ct, val)| (val as f32, ct));
+        [idx.checked_sub(1).and_then(sample), sample(idx)]
+    }
+
+    fn get_limits(&self, sig: usize) -> (f32, f32) {
+        let (min, max) = self.get_index().get_limits(sig);
+        (min as f32, max as f32)
+    }
+}
@@ -0,0 +1,163 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use crate::{Expr, Result, Sampler, TimeRel, TimeScale};
+
+/// Signal an expression reads its value from
+#[derive(Clone)]
+pub enum DerivedInput {
+    Digital(Rc<RefCell<dyn Sampler<bool>>>),
+    Analog(Rc<RefCell<dyn Sampler<f32>>>),
+}
+
+impl DerivedInput {
+    fn value_at(&self, t: TimeRel, s: TimeScale) -> f64 {
+        match self {
+            DerivedInput::Digital(smpl) => if smpl.borrow().get_value_at(t, s) { 1. } else { 0. },
+            DerivedInput::Analog(smpl) => smpl.borrow().get_value_at(t, s) as f64,
+        }
+    }
+
+    /// Return value changes after `range[0]` up to `range[1]`
+    fn changes(&self, range: &[f64; 2], s: TimeScale) -> Result<Vec<(TimeRel, f64)>> {
+        let mut cur = self.value_at(range[0], s);
+        let mut changes = Vec::new();
+        let mut push = |val: f64, t: TimeRel| {
+            if t > range[0] && t <= range[1] && val != cur {
+                changes.push((t, val));
+                cur = val;
+            }
+        };
+        match self {
+            DerivedInput::Digital(smpl) => {
+                for (val, t) in smpl.borrow().iter_range(range)? {
+                    push(if val { 1. } else { 0. }, t);
+                }
+            }
+            DerivedInput::Analog(smpl) => {
+                for (val, t) in smpl.borrow().iter_range(range)? {
+                    push(val as f64, t);
+                }
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Signal evaluating an expression over other signals whenever one of them changes
+pub struct Derived<T> {
+    label: String,
+    expr: Expr,
+    inputs: Vec<DerivedInput>,
+    timescale: TimeScale,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Derived<T> {
+    /// Create signal from an expression and its inputs, in the order of `Expr::get_names`
+    pub fn new(label: String, expr: Expr, inputs: Vec<DerivedInput>, timescale: TimeScale) -> Self {
+        Derived {
+            label,
+            expr,
+            inputs,
+            timescale,
+            phantom: PhantomData,
+        }
+    }
+
+    fn value_at(&self, t: TimeRel) -> f64 {
+        let values : Vec<f64> = self.inputs.iter().map(|inp| inp.value_at(t, self.timescale)).collect();
+        self.expr.eval(&values)
+    }
+
+    /// Merge input changes in time order, return the changes of the expression value
+    fn iter_changes(&self, range: &[f64; 2]) -> Result<Vec<(f64, TimeRel)>> {
+        let mut values : Vec<f64> = self.inputs.iter().map(|inp| inp.value_at(range[0], self.timescale)).collect();
+        // Inputs are read one at a time, as signals of the same wave share its iteration state
+        let mut changes : Vec<(TimeRel, usize, f64)> = Vec::new();
+        for (idx, inp) in self.inputs.iter().enumerate() {
+            changes.extend(inp.changes(range, self.timescale)?.into_iter().map(|(t, val)| (t, idx, val)));
+        }
+        changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut cur = self.expr.eval(&values);
+        let mut out = Vec::new();
+        let mut changes = changes.into_iter().peekable();
+        while let Some((t, idx, val)) = changes.next() {
+            values[idx] = val;
+            while let Some((_, idx, val)) = changes.next_if(|c| c.0 == t) {
+                values[idx] = val;
+            }
+            let val = self.expr.eval(&values);
+            if val != cur {
+                out.push((val, t));
+                cur = val;
+            }
+        }
+        Ok(out)
+    }
+
+    fn set_inputs_scale(&mut self, range: &[f64; 2], timescale: &TimeScale, scale_width: f64) {
+        for inp in self.inputs.iter() {
+            if let DerivedInput::Analog(smpl) = inp {
+                smpl.borrow_mut().set_iter_scale(range, timescale, scale_width);
+            }
+        }
+    }
+}
+
+impl Sampler<bool> for Derived<bool> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_DIGITAL }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (bool, TimeRel)> + '_>> {
+        let changes = self.iter_changes(range)?;
+        Ok(Box::new(changes.into_iter().map(|(val, t)| (val != 0., t))))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> bool {
+        self.value_at(t) != 0.
+    }
+
+    fn set_iter_scale(&mut self, range: &[f64; 2], timescale: &TimeScale, scale_width: f64) {
+        self.set_inputs_scale(range, timescale, scale_width);
+    }
+}
+
+impl Sampler<f32> for Derived<f32> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_ANALOG }
+
+    /// Return signal y scale (peak-to-peak height), from the largest input scale
+    fn get_yscale(&self) -> f64 {
+        self.inputs.iter().map(|inp| match inp {
+            DerivedInput::Digital(_) => 1.,
+            DerivedInput::Analog(smpl) => smpl.borrow().get_yscale(),
+        }).fold(1., f64::max)
+    }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (f32, TimeRel)> + '_>> {
+        // Step between values, extending the last one to the end of range
+        let mut cur = self.value_at(range[0]) as f32;
+        let end = range[1];
+        let steps : Vec<(f32, TimeRel)> = self.iter_changes(range)?.into_iter().flat_map(|(val, t)| {
+            let prev = std::mem::replace(&mut cur, val as f32);
+            [(prev, t), (val as f32, t)]
+        }).collect();
+        Ok(Box::new(steps.into_iter().chain(std::iter::once((cur, end)))))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> f32 {
+        self.value_at(t) as f32
+    }
+
+    fn set_iter_scale(&mut self, range: &[f64; 2], timescale: &TimeScale, scale_width: f64) {
+        self.set_inputs_scale(range, timescale, scale_width);
+    }
+}
@@ -2,7 +2,8 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use std::marker::PhantomData;
 use crate::{Result, Sampler, TimeRel, /*TimeUnit,*/ TimeScale};
-use wavefmt::{FieldInfo, WaveFile};
+use std::io::{Error, ErrorKind};
+use wavefmt::{FieldInfo, IndexedWave, WaveFile};
 
 pub struct DigiSig<T: WaveFile> {
     wave: Rc<RefCell<T>>,
@@ -10,8 +11,13 @@ pub struct DigiSig<T: WaveFile> {
 }
 
 pub trait DigiSigIo {
+    /// Start reading changes of `sig` after `range[0]`, other signals are unaffected
+    fn prepare_iter(&mut self, sig: usize, range: &[f64; 2]);
+
     //fn seek_record(&mut self, idx: usize) -> Result<()>;
     fn read_record_with_time(&mut self, buf: &mut Vec<u8>, sig: usize) -> Result<(bool, TimeRel)>;
+
+    fn value_at(&self, sig: usize, t: TimeRel) -> bool;
 }
 
 impl<T: WaveFile> DigiSig<T> {
@@ -36,7 +42,7 @@ impl<T: WaveFile + DigiSigIo> Sampler<bool> for DigiSig<T> {
     fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (bool, TimeRel)> + '_>> {
         let mut wv_bind = self.wave.borrow_mut();
         //println!("iter_range [{:.02},{:.02}]", range[0], range[1]);
-        let _sample_bounds : [usize; 2] = wv_bind.prepare_iter_range(range)?;
+        wv_bind.prepare_iter(self.sig, range);
         //println!("iter_range sample_bounds [{:.02},{:.02}]", sample_bounds[0], sample_bounds[1]);
         let recbuf =
             if let Some(recsize) = wv_bind.get_record_size() {
@@ -58,8 +64,7 @@ impl<T: WaveFile + DigiSigIo> Sampler<bool> for DigiSig<T> {
     }
 
     fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> bool {
-        //let fi : &FieldInfo = self.wave.borrow().get_sig_info(self.sig);
-        false
+        self.wave.borrow().value_at(self.sig, t)
     }
 }
 
@@ -101,3 +106,19 @@ impl<W: WaveFile + DigiSigIo> Iterator for DigiSigIter<'_, bool, W> {
         }
     }
 }
+
+impl<W: IndexedWave> DigiSigIo for W {
+    fn prepare_iter(&mut self, sig: usize, range: &[f64; 2]) {
+        self.get_index_mut().prepare_field_iter(sig, range);
+    }
+
+    fn read_record_with_time(&mut self, _buf: &mut Vec<u8>, sig: usize) -> Result<(bool, TimeRel)> {
+        let (time, val) = self.get_index_mut().next_change(sig)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "No more changes"))?;
+        Ok((val != 0., time))
+    }
+
+    fn value_at(&self, sig: usize, t: TimeRel) -> bool {
+        self.get_index().value_at(sig, t) != 0.
+    }
+}
@@ -1,11 +1,21 @@
 mod digisig;
 pub use digisig::DigiSig;
+mod anasig;
+pub use anasig::AnaSig;
+mod ringsig;
+pub use ringsig::{RingBuf, RingSig};
 mod digisiggen;
 pub use digisiggen::DigiSigGen;
 mod sinegen;
 pub use sinegen::SineGen;
 mod anasiggen;
 pub use anasiggen::AnaSigGen;
+mod patterngen;
+pub use patterngen::PatternGen;
+mod derived;
+pub use derived::{Derived, DerivedInput};
+mod threshold;
+pub use threshold::Threshold;
 
 use super::{Result, TimeRel, TimeScale};
 
@@ -23,7 +33,44 @@ pub trait Sampler<T> {
 
     fn get_value_at(&self, t: TimeRel, s: TimeScale) -> T;
 
+    /// Last point of the trace at or before `t`, lines drawn into a range starting at `t`
+    /// begin there. The value at `t`, unless the signal interpolates between samples.
+    fn get_point_before(&self, t: TimeRel, s: TimeScale) -> (T, TimeRel) {
+        (self.get_value_at(t, s), t)
+    }
+
+    /// First point of the trace after `t`, see `get_point_before`
+    fn get_point_after(&self, t: TimeRel, s: TimeScale) -> (T, TimeRel) {
+        (self.get_value_at(t, s), t)
+    }
+
     /// Set iteration scale, used to generate filtered summary waveform
     fn set_iter_scale(&mut self, _range: &[f64; 2], _timescale: &TimeScale, _scale_width: f64) { }
 }
 
+
+/// Value of an analog trace at `t`, on the line between its points around `t`
+pub fn trace_value_at(smpl: &dyn Sampler<f32>, t: TimeRel, s: TimeScale) -> f64 {
+    let (v0, t0) = smpl.get_point_before(t, s);
+    let (v1, t1) = smpl.get_point_after(t, s);
+    let (v0, v1) = (v0 as f64, v1 as f64);
+    if t1 > t0 { v0 + (v1 - v0) * (t - t0) / (t1 - t0) } else { v0 }
+}
+
+/// Points of an analog trace over `range` as drawn, from its last point at or before
+/// `range[0]` up to its value at `range[1]`. Held values step between two points at the
+/// same time.
+pub fn trace_points(smpl: &dyn Sampler<f32>, range: &[TimeRel; 2], s: TimeScale) -> Result<Vec<(TimeRel, f64)>> {
+    let (v0, t0) = smpl.get_point_before(range[0], s);
+    let mut points = vec![(t0, v0 as f64)];
+    points.extend(smpl.iter_range(range)?
+        .filter(|&(_, t)| t > range[0] && t <= range[1])
+        .map(|(val, t)| (t, val as f64)));
+    // Iteration holds the last value to the end of the range
+    let end = trace_value_at(smpl, range[1], s);
+    match points.last_mut() {
+        Some(last) if last.0 == range[1] => last.1 = end,
+        _ => points.push((range[1], end)),
+    }
+    Ok(points)
+}
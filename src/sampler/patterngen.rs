@@ -0,0 +1,90 @@
+use std::marker::PhantomData;
+use crate::{Result, Sampler, TimeRel, TimeScale};
+
+/// Generated signal following a fixed list of value changes, e.g. from a timing diagram
+#[derive(Debug)]
+pub struct PatternGen<T> {
+    label: String,
+    changes: Vec<(TimeRel, f32)>,
+    end: TimeRel,
+    peak: f32,
+    phantom: PhantomData<T>,
+}
+
+impl<T> PatternGen<T> {
+    /// Create signal from value changes in time order, holding the last value until `end`
+    pub fn new(label: String, changes: Vec<(TimeRel, f32)>, end: TimeRel) -> Self {
+        let peak = changes.iter().fold(0f32, |peak, c| peak.max(c.1.abs()));
+        PatternGen {
+            label,
+            changes,
+            end,
+            peak,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return time range covered by pattern
+    pub fn get_range(&self) -> (TimeRel, TimeRel) {
+        (self.changes.first().map(|c| c.0).unwrap_or(self.end), self.end)
+    }
+
+    fn value_at(&self, t: TimeRel) -> f32 {
+        match self.changes.partition_point(|c| c.0 <= t) {
+            0 => self.changes.first().map(|c| c.1).unwrap_or(0.),
+            idx => self.changes[idx - 1].1,
+        }
+    }
+
+    fn iter_changes(&self, range: &[f64; 2]) -> impl Iterator<Item = (f32, TimeRel)> + '_ {
+        let start = self.changes.partition_point(|c| c.0 <= range[0]);
+        let range = *range;
+        self.changes[start..].iter()
+            .take_while(move |c| c.0 <= range[1])
+            .map(|&(t, val)| (val, t))
+    }
+}
+
+impl Sampler<bool> for PatternGen<bool> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_DIGITAL }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (bool, TimeRel)> + '_>> {
+        Ok(Box::new(self.iter_changes(range).map(|(val, t)| (val != 0., t))))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> bool {
+        self.value_at(t) != 0.
+    }
+}
+
+impl Sampler<f32> for PatternGen<f32> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_ANALOG }
+
+    /// Return signal y scale (peak-to-peak height)
+    fn get_yscale(&self) -> f64 {
+        if self.peak > 0. { self.peak as f64 } else { 1. }
+    }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (f32, TimeRel)> + '_>> {
+        // Step between values, extending the last one to the end of range
+        let mut cur = self.value_at(range[0]);
+        let end = range[1];
+        let steps = self.iter_changes(range).flat_map(move |(val, t)| {
+            let prev = std::mem::replace(&mut cur, val);
+            [(prev, t), (val, t)]
+        });
+        Ok(Box::new(steps.chain(std::iter::once((self.value_at(end), end)))))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> f32 {
+        self.value_at(t)
+    }
+}
@@ -0,0 +1,185 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use crate::{Result, Sampler, TimeRel, TimeScale};
+
+/// Fixed capacity sample buffer for a live signal, the oldest samples are dropped when full
+#[derive(Debug)]
+pub struct RingBuf {
+    data: VecDeque<(TimeRel, f32)>,
+    capacity: usize,
+    dedup: bool,
+    /// Number of samples stored so far
+    count: u64,
+    /// Candidates for the largest absolute value buffered, as (sample number, absolute
+    /// value) with decreasing values. The front is the peak.
+    peaks: VecDeque<(u64, f32)>,
+}
+
+impl RingBuf {
+    /// Create buffer holding up to `capacity` samples, `dedup` stores only value changes
+    pub fn new(capacity: usize, dedup: bool) -> Self {
+        assert!(capacity > 0, "Ring buffer capacity must be positive");
+        RingBuf {
+            data: VecDeque::new(),
+            capacity,
+            dedup,
+            count: 0,
+            peaks: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, t: TimeRel, val: f32) {
+        if self.dedup && self.data.back().map(|s| s.1) == Some(val) {
+            return;
+        }
+        if self.data.len() == self.capacity {
+            self.data.pop_front();
+            let dropped = self.count - self.capacity as u64;
+            if self.peaks.front().is_some_and(|p| p.0 == dropped) {
+                self.peaks.pop_front();
+            }
+        }
+        self.data.push_back((t, val));
+        let abs = val.abs();
+        if !abs.is_nan() {
+            while self.peaks.back().is_some_and(|p| p.1 <= abs) {
+                self.peaks.pop_back();
+            }
+            self.peaks.push_back((self.count, abs));
+        }
+        self.count += 1;
+    }
+
+    /// Return largest absolute value buffered
+    fn peak(&self) -> f32 {
+        self.peaks.front().map_or(0., |p| p.1)
+    }
+
+    /// Return time range of buffered samples
+    pub fn get_range(&self) -> Option<(TimeRel, TimeRel)> {
+        Some((self.data.front()?.0, self.data.back()?.0))
+    }
+
+    /// Index of first sample after time `t`
+    fn first_after(&self, t: TimeRel) -> usize {
+        self.data.partition_point(|s| s.0 <= t)
+    }
+
+    fn value_at(&self, t: TimeRel) -> f32 {
+        match self.first_after(t) {
+            0 => self.data.front().map(|s| s.1).unwrap_or(0.),
+            idx => self.data[idx - 1].1,
+        }
+    }
+}
+
+/// Live signal sampler, reading from a `RingBuf` filled by a stream
+pub struct RingSig<T> {
+    buf: Rc<RefCell<RingBuf>>,
+    label: String,
+    phantom: PhantomData<T>,
+}
+
+impl<T> RingSig<T> {
+    pub fn new(buf: Rc<RefCell<RingBuf>>, label: String) -> Self {
+        RingSig {
+            buf,
+            label,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Sampler<bool> for RingSig<bool> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_DIGITAL }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (bool, TimeRel)> + '_>> {
+        let pos = self.buf.borrow().first_after(range[0]);
+        Ok(Box::new(RingSigIter {
+            smpl: self,
+            pos,
+            range: *range,
+            done: true,
+        }))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> bool {
+        self.buf.borrow().value_at(t) != 0.
+    }
+}
+
+impl Sampler<f32> for RingSig<f32> {
+    fn get_height(&self) -> f64 { crate::HEIGHT_ANALOG }
+
+    /// Return signal y scale (largest absolute value buffered)
+    fn get_yscale(&self) -> f64 {
+        let peak = self.buf.borrow().peak() as f64;
+        if peak > 0. { peak } else { 1. }
+    }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (f32, TimeRel)> + '_>> {
+        let pos = self.buf.borrow().first_after(range[0]);
+        Ok(Box::new(RingSigIter {
+            smpl: self,
+            pos,
+            range: *range,
+            done: false,
+        }))
+    }
+
+    fn get_value_at(&self, t: TimeRel, _s: TimeScale) -> f32 {
+        self.buf.borrow().value_at(t)
+    }
+}
+
+pub struct RingSigIter<'r, T> {
+    smpl: &'r RingSig<T>,
+    pos: usize,
+    range: [TimeRel; 2],
+    /// Set when there is no need to extend the last value to the end of range
+    done: bool,
+}
+
+impl<T> RingSigIter<'_, T> {
+    fn next_sample(&mut self) -> Option<(f32, TimeRel)> {
+        let buf = self.smpl.buf.borrow();
+        match buf.data.get(self.pos) {
+            Some(&(t, val)) if t <= self.range[1] => {
+                self.pos += 1;
+                Some((val, t))
+            }
+            _ if !self.done => {
+                // Extend last value to end of range
+                self.done = true;
+                Some((buf.value_at(self.range[1]), self.range[1]))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for RingSigIter<'_, bool> {
+    type Item = (bool, TimeRel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_sample().map(|(val, t)| (val != 0., t))
+    }
+}
+
+impl Iterator for RingSigIter<'_, f32> {
+    type Item = (f32, TimeRel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_sample()
+    }
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::{Result, Sampler, TimeRel, TimeScale};
+
+/// Digital signal from comparing an analog signal with thresholds: it goes high when the
+/// input rises to `high`, and low when it falls to `low`. Edges are placed where straight
+/// lines between input samples cross the thresholds.
+pub struct Threshold {
+    label: String,
+    input: Rc<RefCell<dyn Sampler<f32>>>,
+    high: f32,
+    low: f32,
+    /// Start of input data, read from when the level at a time is between the thresholds
+    origin: TimeRel,
+    timescale: TimeScale,
+}
+
+impl Threshold {
+    pub fn new(label: String, input: Rc<RefCell<dyn Sampler<f32>>>, high: f32, low: f32, origin: TimeRel, timescale: TimeScale) -> Self {
+        Threshold {
+            label,
+            input,
+            high: high.max(low),
+            low: low.min(high),
+            origin,
+            timescale,
+        }
+    }
+
+    /// Return level of input value, None when between the thresholds
+    fn level(&self, val: f32) -> Option<bool> {
+        if val >= self.high {
+            Some(true)
+        } else if val <= self.low {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Return edges after `range[0]` up to `range[1]`
+    fn edges(&self, range: &[f64; 2]) -> Result<Vec<(bool, TimeRel)>> {
+        let input = self.input.borrow();
+        // From the last input point before the range, a crossing may fall between them
+        let points = super::trace_points(&*input, range, self.timescale)?;
+        let mut prev = points[0];
+        let mut state = self.get_value_at(prev.0, self.timescale);
+        let mut edges = Vec::new();
+        for &(t, val) in &points[1..] {
+            let (t0, v0) = prev;
+            let threshold = (if state { self.low } else { self.high }) as f64;
+            let crossed = if state { val <= threshold } else { val >= threshold };
+            if crossed {
+                // Interpolate crossing time between points
+                let frac = if val != v0 { ((threshold - v0) / (val - v0)).clamp(0., 1.) } else { 1. };
+                state = !state;
+                let edge = t0 + frac * (t - t0);
+                if edge > range[0] {
+                    edges.push((state, edge));
+                }
+            }
+            prev = (t, val);
+        }
+        Ok(edges)
+    }
+
+    /// Return level at `t` from the last input value outside the thresholds, searching back
+    /// from `t` in growing windows
+    fn value_before(&self, t: TimeRel) -> Result<bool> {
+        let input = self.input.borrow();
+        let mut end = t;
+        let mut span = (t - self.origin) / 1024.;
+        while end > self.origin {
+            let start = (end - span).max(self.origin);
+            let mut state = None;
+            for (val, _) in input.iter_range(&[start, end])? {
+                state = self.level(val).or(state);
+            }
+            if let Some(state) = state {
+                return Ok(state);
+            }
+            end = start;
+            span *= 4.;
+        }
+        // Input never left the band between thresholds, use the nearer one
+        let first = input.get_value_at(self.origin.min(t), self.timescale);
+        Ok(self.level(first).unwrap_or(first >= 0.5 * (self.high + self.low)))
+    }
+}
+
+impl Sampler<bool> for Threshold {
+    fn get_height(&self) -> f64 { crate::HEIGHT_DIGITAL }
+
+    fn get_label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn iter_range(&self, range: &[f64; 2]) -> Result<Box<dyn Iterator<Item = (bool, TimeRel)> + '_>> {
+        Ok(Box::new(self.edges(range)?.into_iter()))
+    }
+
+    fn get_value_at(&self, t: TimeRel, s: TimeScale) -> bool {
+        let val = super::trace_value_at(&*self.input.borrow(), t, s);
+        match self.level(val as f32) {
+            Some(level) => level,
+            None => self.value_before(t).unwrap_or(false),
+        }
+    }
+
+    fn set_iter_scale(&mut self, range: &[f64; 2], timescale: &TimeScale, scale_width: f64) {
+        self.input.borrow_mut().set_iter_scale(range, timescale, scale_width);
+    }
+}
@@ -0,0 +1,40 @@
+//! Searching for the times a condition over signals becomes true
+use crate::{DataStore, Derived, Result, Sampler, TimeRel};
+
+pub struct Search {
+    pub text: String,
+    /// Times the condition becomes true, in order
+    matches: Vec<TimeRel>,
+}
+
+impl Search {
+    /// Find where a condition such as `state == 5 && valid` becomes true within `range`,
+    /// including the start of range when it already holds there
+    pub fn new(datas: &DataStore, text: &str, range: &[TimeRel; 2]) -> Result<Self> {
+        let cond : Derived<bool> = datas.new_condition(text)?;
+        let mut cur = cond.get_value_at(range[0], datas.get_timescale());
+        let mut matches = if cur { vec![range[0]] } else { Vec::new() };
+        for (val, t) in cond.iter_range(range)? {
+            if val && !cur {
+                matches.push(t);
+            }
+            cur = val;
+        }
+        Ok(Search { text: text.trim().to_string(), matches })
+    }
+
+    pub fn matches(&self) -> &[TimeRel] {
+        &self.matches
+    }
+
+    /// First match after `t`, or last before it
+    pub fn next(&self, t: TimeRel, forward: bool) -> Option<TimeRel> {
+        if forward {
+            let idx = self.matches.partition_point(|&m| m <= t);
+            self.matches.get(idx).copied()
+        } else {
+            let idx = self.matches.partition_point(|&m| m < t);
+            idx.checked_sub(1).map(|idx| self.matches[idx])
+        }
+    }
+}
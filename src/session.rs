@@ -0,0 +1,88 @@
+//! Session files, keeping named markers and signal groups between runs. A session is a
+//! text file with one item per line, `marker TIME COLOUR NAME` with the time in global
+//! units (ps), or `group COLLAPSED NAME` with COLLAPSED 0 or 1 followed by a `member SIGNAL`
+//! line for each signal in the group. Names run to the end of the line, signal names can
+//! be expressions with spaces and commas.
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use crate::{DataStore, Result};
+use crate::chart::{Chart, Group, Marker};
+
+const HEADER : &str = "# wavygravy session";
+
+/// Load markers and groups from a session file into `chart`, a missing file is an empty
+/// session. Group members not among the signals of `datas` are dropped.
+pub fn load(path: &Path, chart: &mut Chart, datas: &DataStore) -> Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let mut markers = Vec::new();
+    let mut groups : Vec<Group> = Vec::new();
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(args) = line.strip_prefix("group ") {
+            let mut fields = args.trim().splitn(2, ' ');
+            let collapsed = match fields.next() {
+                Some("0") => false,
+                Some("1") => true,
+                _ => {
+                    let msg = format!("{}:{}: expected group COLLAPSED NAME", path.display(), num + 1);
+                    return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+                }
+            };
+            let name = fields.next().unwrap_or("").trim().to_string();
+            groups.push(Group { name, signals: Vec::new(), collapsed });
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("member ") {
+            let Some(group) = groups.last_mut() else {
+                let msg = format!("{}:{}: member without a group", path.display(), num + 1);
+                return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+            };
+            let sig = (0..datas.get_num_signals()).find(|&sig| datas.get_signal_field(sig).name == name.trim());
+            if let Some(sig) = sig.filter(|sig| !group.signals.contains(sig)) {
+                group.signals.push(sig);
+            }
+            continue;
+        }
+        // Comments and items from newer versions are skipped
+        let Some(args) = line.strip_prefix("marker ") else { continue };
+        let mut fields = args.trim().splitn(3, ' ');
+        let time = fields.next().and_then(|t| t.parse::<f64>().ok());
+        let colour = fields.next().and_then(|c| c.parse::<usize>().ok());
+        let (Some(time), Some(colour)) = (time, colour) else {
+            let msg = format!("{}:{}: expected marker TIME COLOUR NAME", path.display(), num + 1);
+            return Err(Box::new(Error::new(ErrorKind::InvalidData, msg)));
+        };
+        let name = fields.next().unwrap_or("").trim().to_string();
+        markers.push(Marker { time, name, colour });
+    }
+    markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+    chart.markers = markers;
+    // A signal is in one group at most
+    for idx in 1..groups.len() {
+        let (earlier, rest) = groups.split_at_mut(idx);
+        rest[0].signals.retain(|sig| !earlier.iter().any(|g| g.signals.contains(sig)));
+    }
+    groups.retain(|g| !g.signals.is_empty());
+    chart.groups = groups;
+    Ok(())
+}
+
+/// Save markers and groups of `chart` to a session file
+pub fn save(path: &Path, chart: &Chart, datas: &DataStore) -> Result<()> {
+    let mut text = format!("{HEADER}\n");
+    for marker in &chart.markers {
+        text += &format!("marker {} {} {}\n", marker.time, marker.colour, marker.name);
+    }
+    for group in &chart.groups {
+        text += &format!("group {} {}\n", u8::from(group.collapsed), group.name);
+        for &sig in &group.signals {
+            text += &format!("member {}\n", datas.get_signal_field(sig).name);
+        }
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+}
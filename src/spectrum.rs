@@ -0,0 +1,235 @@
+//! Frequency spectrum of an analog signal, resampled uniformly over a time window
+use std::io::{Error, ErrorKind};
+use vello::{
+    kurbo::{Affine, BezPath, Circle, Rect},
+    peniko::{Brush, Color, Fill, Stroke},
+};
+use crate::{DataStore, DrawSink, Result, TimeRel, RULE_HEIGHT, SCROLL_WIDTH};
+use crate::datastore::SigType;
+use crate::measure::PANEL_BG_COL;
+
+/// Number of uniform samples taken over the window, a power of two
+pub const POINTS : usize = 4096;
+/// Range of magnitudes plotted below the peak
+const DB_RANGE : f64 = 120.;
+const TRACE_COL : Brush = Brush::Solid(Color::rgb8(120, 255, 120));
+
+/// Window function applied to the samples before the FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Hann,
+    Blackman,
+    /// Flat-top, for accurate amplitudes of tones between bins
+    FlatTop,
+}
+
+impl Window {
+    pub fn next(self) -> Self {
+        match self {
+            Window::Hann => Window::Blackman,
+            Window::Blackman => Window::FlatTop,
+            Window::FlatTop => Window::Hann,
+        }
+    }
+
+    /// Weight of sample `n` of `len`
+    pub fn weight(self, n: usize, len: usize) -> f64 {
+        let coeffs : &[f64] = match self {
+            Window::Hann => &[0.5, 0.5],
+            Window::Blackman => &[0.42, 0.5, 0.08],
+            Window::FlatTop => &[0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368],
+        };
+        let x = 2. * std::f64::consts::PI * n as f64 / len as f64;
+        coeffs.iter().enumerate()
+            .map(|(k, &a)| if k & 1 == 0 { a } else { -a } * (k as f64 * x).cos())
+            .sum()
+    }
+}
+
+impl std::str::FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "hann" => Ok(Window::Hann),
+            "blackman" => Ok(Window::Blackman),
+            "flattop" => Ok(Window::FlatTop),
+            _ => Err(format!("Expected hann, blackman or flattop, got {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Window::Hann => "hann",
+            Window::Blackman => "blackman",
+            Window::FlatTop => "flattop",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Magnitudes from DC up to half the sample rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    /// Frequency step between bins (in Hz)
+    pub bin_hz: f64,
+    /// Amplitude of each bin in dB, a sine of amplitude 1 being 0 dB
+    pub db: Vec<f64>,
+    pub window: Window,
+}
+
+impl Spectrum {
+    /// Transform `samples` taken `step_secs` apart, their count a power of two
+    pub fn from_samples(samples: &[f64], step_secs: f64, window: Window) -> Self {
+        let len = samples.len();
+        let weights : Vec<f64> = (0..len).map(|n| window.weight(n, len)).collect();
+        let mut re : Vec<f64> = samples.iter().zip(&weights).map(|(s, w)| s * w).collect();
+        let mut im = vec![0.; len];
+        fft(&mut re, &mut im);
+        // Scale by the window's gain, doubling the one-sided bins
+        let gain : f64 = weights.iter().sum();
+        let db = (0..=len / 2).map(|k| {
+            let scale = if k == 0 || k == len / 2 { 1. } else { 2. };
+            let amp = scale * re[k].hypot(im[k]) / gain;
+            20. * amp.max(1e-10).log10()
+        }).collect();
+        Spectrum { bin_hz: 1. / (step_secs * len as f64), db, window }
+    }
+
+    /// Frequency (in Hz) and magnitude of the largest bin above DC
+    pub fn peak(&self) -> Option<(f64, f64)> {
+        self.db.iter().enumerate().skip(1)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(k, &db)| (k as f64 * self.bin_hz, db))
+    }
+}
+
+/// In-place radix-2 FFT, the length a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let len = re.len();
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut size = 2;
+    while size <= len {
+        let angle = -2. * std::f64::consts::PI / size as f64;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (w_im, w_re) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + size / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size <<= 1;
+    }
+}
+
+/// Spectrum of analog signal `sig`, resampled at `POINTS` uniform times over `range`
+/// interpolating between the points of its trace. A trace with more points than that is
+/// averaged over each sample interval instead, so detail between samples doesn't alias.
+pub fn spectrum(datas: &DataStore, sig: usize, range: &[TimeRel; 2], window: Window) -> Result<Spectrum> {
+    if datas.get_signal_type_idx(sig).0 != SigType::Analog {
+        let msg = format!("Signal is not analog: {}", datas.get_signal_field(sig).name);
+        return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+    }
+    if range[1] <= range[0] {
+        return Err(Box::new(Error::new(ErrorKind::InvalidInput, "Empty time window")));
+    }
+    let (initial, trace) = crate::export::read_trace(datas, sig, range)?;
+    let step = (range[1] - range[0]) / POINTS as f64;
+    let samples = if trace.len() > POINTS {
+        bin_means((range[0], initial), &trace, range, POINTS)
+    } else {
+        let times : Vec<TimeRel> = (0..POINTS).map(|n| range[0] + step * n as f64).collect();
+        crate::export::resample((range[0], initial), &trace, &times)
+    };
+    Ok(Spectrum::from_samples(&samples, step * datas.get_timescale().secs(), window))
+}
+
+/// Mean of a trace read by `read_trace` over each of `bins` equal intervals of `range`,
+/// straight between its points from `start`
+fn bin_means(start: (TimeRel, f64), trace: &[(TimeRel, f64)], range: &[TimeRel; 2], bins: usize) -> Vec<f64> {
+    let width = (range[1] - range[0]) / bins as f64;
+    let edge = |bin: usize| if bin + 1 < bins { range[0] + width * (bin + 1) as f64 } else { f64::INFINITY };
+    let mut sums = vec![0.; bins];
+    let mut prev = start;
+    for &next in trace.iter().filter(|p| p.0 <= range[1]) {
+        let value = |t: TimeRel| prev.1 + (next.1 - prev.1) * (t - prev.0) / (next.0 - prev.0);
+        // Split the line at bin edges
+        let mut t = prev.0;
+        let mut bin = (((t - range[0]) / width) as usize).min(bins - 1);
+        while t < next.0 {
+            while edge(bin) <= t { bin += 1; }
+            let end = edge(bin).min(next.0);
+            sums[bin] += 0.5 * (value(t) + value(end)) * (end - t);
+            t = end;
+        }
+        prev = next;
+    }
+    sums.into_iter().map(|sum| sum / width).collect()
+}
+
+/// Draw titled spectrum plot with readout lines over the lower half of the waveform window
+pub fn draw(sb: &mut impl DrawSink, title: &str, spec: Option<&Spectrum>, lines: &[String], width: f64, height: f64) {
+    let label_height = (RULE_HEIGHT - 4.) as f32;
+    let line_height = RULE_HEIGHT - 4.;
+    let panel = Rect::new(8., height / 2., width - SCROLL_WIDTH - 8., height - RULE_HEIGHT - 8.);
+    sb.fill(Fill::NonZero, Affine::IDENTITY, &PANEL_BG_COL, None, &panel);
+    let text = std::iter::once(title).chain(lines.iter().map(|l| l.as_str()));
+    for (num, line) in text.enumerate() {
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(if num == 0 { Color::YELLOW } else { Color::WHITE })),
+            Affine::translate((panel.x0 + 4., panel.y0 + 4. + line_height * (num + 1) as f64 - 2.)),
+            line,
+        );
+    }
+    let Some(spec) = spec.filter(|s| s.db.len() > 1) else { return };
+    // Plot below the text down to DB_RANGE under the peak, with level labels to its left
+    let char_width = 0.6 * label_height as f64;
+    let plot = Rect::new(panel.x0 + char_width * 10., panel.y0 + line_height * (lines.len() + 2) as f64, panel.x1 - 4., panel.y1 - line_height - 8.);
+    if plot.width() <= 0. || plot.height() <= 0. { return; }
+    let top = spec.db.iter().copied().fold(f64::NEG_INFINITY, f64::max).ceil();
+    let x_of = |k: usize| plot.x0 + plot.width() * k as f64 / (spec.db.len() - 1) as f64;
+    let y_of = |db: f64| plot.y0 + plot.height() * ((top - db) / DB_RANGE).clamp(0., 1.);
+    let mut path = BezPath::new();
+    for (k, &db) in spec.db.iter().enumerate() {
+        if k == 0 {
+            path.move_to((x_of(k), y_of(db)));
+        } else {
+            path.line_to((x_of(k), y_of(db)));
+        }
+    }
+    sb.stroke(&Stroke::new(1.0), Affine::IDENTITY, &TRACE_COL, None, &path);
+    if let Some((hz, db)) = spec.peak() {
+        let k = (hz / spec.bin_hz).round() as usize;
+        sb.stroke(&Stroke::new(1.0), Affine::IDENTITY, &Brush::Solid(Color::YELLOW), None, &Circle::new((x_of(k), y_of(db)), 4.));
+    }
+    let nyquist = crate::fmt_freq(spec.bin_hz * (spec.db.len() - 1) as f64);
+    let labels = [
+        (panel.x0 + 4., plot.y0 + line_height, format!("{top} dB")),
+        (panel.x0 + 4., plot.y1, format!("{} dB", top - DB_RANGE)),
+        (plot.x0, plot.y1 + line_height, "0 Hz".to_string()),
+        (plot.x1 - char_width * nyquist.len() as f64, plot.y1 + line_height, nyquist),
+    ];
+    for (x, y, label) in labels {
+        sb.text(label_height, Some(&Brush::Solid(Color::WHITE)), Affine::translate((x, y)), &label);
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::{self, BufReader, Read};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use wavefmt::stream::{read_msg, StreamMsg};
+use crate::Result;
+
+/// Stream message, tagged with the number of the connection it came from
+pub type ConnMsg = (usize, StreamMsg);
+
+/// Listen for live sample streams (see `wavefmt::stream` for the protocol) on a TCP address,
+/// e.g. `127.0.0.1:5555`, or a Unix socket path prefixed with `unix:`. Each connection is
+/// read on its own thread, decoded messages are passed back through the returned channel.
+pub fn listen(addr: &str) -> Result<Receiver<ConnMsg>> {
+    let (tx, rx) = channel();
+    #[cfg(unix)]
+    if let Some(path) = addr.strip_prefix("unix:") {
+        // Remove stale socket left by a previous run
+        if std::fs::metadata(path).map(|m| m.file_type().is_socket()).unwrap_or(false) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        thread::spawn(move || accept(listener.incoming(), tx));
+        return Ok(rx);
+    }
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || accept(listener.incoming(), tx));
+    Ok(rx)
+}
+
+fn accept<S: Read + Send + 'static>(incoming: impl Iterator<Item = io::Result<S>>, tx: Sender<ConnMsg>) {
+    for (conn, stream) in incoming.enumerate() {
+        match stream {
+            Ok(stream) => {
+                let tx = tx.clone();
+                thread::spawn(move || read_conn(conn, stream, tx));
+            }
+            Err(e) => eprintln!("Stream connection failed: {e}"),
+        }
+    }
+}
+
+fn read_conn(conn: usize, stream: impl Read, tx: Sender<ConnMsg>) {
+    let mut rd = BufReader::new(stream);
+    loop {
+        match read_msg(&mut rd) {
+            Ok(Some(msg)) => {
+                if tx.send((conn, msg)).is_err() { break; }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Stream connection {conn}: {e}");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,844 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+use clap::Parser;
+use vello::kurbo::{BezPath, PathEl, Point, Shape};
+use vello::peniko::Color;
+use crate::*;
+use crate::chart::{Chart, EdgeFilter, Row};
+use crate::draw::RecordSink;
+use crate::sampler::{RingBuf, RingSig};
+
+const WIDTH : f64 = 1044.;
+const HEIGHT : f64 = 200.;
+/// Waveform area of a `WIDTH` wide chart with default columns
+const SIG_XOFFS : f64 = (WIDTH - SCROLL_WIDTH) * 0.25;
+const SIG_WIDTH : f64 = (WIDTH - SCROLL_WIDTH) - SIG_XOFFS;
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wavygravy_{}_{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Chart of a clock rising at 5ns and 20ns, falling at 10ns, showing 0-30ns
+fn record(name: &str, cursor: Option<TimeRel>) -> RecordSink {
+    record_with(name, |chart| chart.cursor = cursor)
+}
+
+fn record_with(name: &str, setup: impl FnOnce(&mut Chart)) -> RecordSink {
+    let path = temp_file(name, "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $upscope $end\n$enddefinitions $end\n#0\n0!\n#5\n1!\n#10\n0!\n#20\n1!\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let mut chart = Chart::new();
+    chart.set_range(&[0., 30000.], &TIMESCALE);
+    chart.set_max_range(&[0., 30000.], &TIMESCALE);
+    setup(&mut chart);
+    let mut rec = RecordSink::new();
+    chart.draw_layer::<bool>(&mut rec, &datas, WIDTH, HEIGHT);
+    rec
+}
+
+fn xpos(t: TimeRel) -> f64 {
+    SIG_XOFFS + SIG_WIDTH * t / 30000.
+}
+
+/// X positions of vertical lines
+fn vlines<'a>(paths: impl Iterator<Item = &'a BezPath>) -> Vec<f64> {
+    paths.filter_map(|path| match path.elements() {
+        [PathEl::MoveTo(p0), PathEl::LineTo(p1)] if p0.x == p1.x => Some(p0.x),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn ruler_labels() {
+    let rec = record("ruler.vcd", None);
+    let labels : Vec<(&str, Point)> = rec.texts().filter(|(text, _)| text.ends_with(",000")).collect();
+    assert_eq!(labels[0], ("10,000", Point::new(xpos(10000.).round() + 1., RULE_HEIGHT - 5.)));
+    assert_eq!(labels[1], ("20,000", Point::new(xpos(20000.).round() + 1., RULE_HEIGHT - 5.)));
+    // Same labels on the bottom (global) ruler
+    assert_eq!(labels[3].1.y, HEIGHT - 5.);
+}
+
+#[test]
+fn digital_edges() {
+    let rec = record("edges.vcd", None);
+    let rising = vlines(rec.strokes(Color::rgba8(100, 100, 200, 255)));
+    let falling = vlines(rec.strokes(Color::rgba8(100, 0, 200, 255)));
+    assert_eq!(rising, [xpos(5000.), xpos(20000.)]);
+    assert_eq!(falling, [xpos(10000.)]);
+    let labels : Vec<&str> = rec.texts().map(|(text, _)| text).collect();
+    assert!(labels.contains(&"top.clk"));
+}
+
+#[test]
+fn cursor_placement() {
+    let rec = record("cursor.vcd", Some(15000.));
+    // Main cursor is the only yellow line spanning the waveform window
+    let cursor = vlines(rec.strokes(Color::YELLOW).filter(|p| p.bounding_box().y1 == HEIGHT - RULE_HEIGHT));
+    assert_eq!(cursor, [xpos(15000.)]);
+    let global = vlines(rec.strokes(Color::RED));
+    assert_eq!(global, [xpos(15000.)]);
+    assert!(rec.texts().any(|(text, pos)| text == "15,000 ps" && pos.x == 0.));
+    // Value column shows the clock level at the cursor
+    assert!(rec.texts().any(|(text, pos)| text == "0" && pos.x == 0.2 * WIDTH));
+}
+
+#[test]
+fn second_cursor() {
+    let rec = record_with("cursor2.vcd", |chart| {
+        chart.cursor = Some(5000.);
+        chart.cursor2 = Some(25000.);
+    });
+    let cursor2 = vlines(rec.strokes(Color::rgba8(0, 220, 220, 255)).filter(|p| p.bounding_box().y1 == HEIGHT - RULE_HEIGHT));
+    assert_eq!(cursor2, [xpos(25000.)]);
+    assert!(rec.texts().any(|(text, pos)| text == "5,000 ps  \u{394} 20,000 ps  50 MHz" && pos.x == 0.));
+    // Values at both cursors
+    assert!(rec.texts().any(|(text, pos)| text == "1 | 1" && pos.x == 0.2 * WIDTH));
+
+    let mut chart = Chart::new();
+    chart.set_max_range(&[0., 30000.], &TIMESCALE);
+    chart.set_range(&[0., 30000.], &TIMESCALE);
+    chart.cursor = Some(20000.);
+    chart.cursor2 = Some(12000.);
+    chart.zoom_to_cursors();
+    assert_eq!(chart.time_range, [12000., 20000.]);
+    assert_eq!(fmt_freq(1.25e8), "125 MHz");
+}
+
+#[test]
+fn edge_navigation() {
+    let path = temp_file("edges_nav.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $upscope $end\n$enddefinitions $end\n#0\n0!\n#5\n1!\n#10\n0!\n#20\n1!\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let mut chart = Chart::new();
+    chart.set_max_range(&[0., 30000.], &TIMESCALE);
+    chart.set_range(&[0., 3000.], &TIMESCALE);
+    assert!(!chart.goto_edge(&datas, true));
+    chart.select_next(datas.get_num_signals(), true);
+    assert_eq!(chart.selected, Some(0));
+    assert_eq!(chart.signal_at(&datas, RULE_HEIGHT + 1.), Some(0));
+    assert_eq!(chart.signal_at(&datas, 1.), None);
+
+    let mut steps = Vec::new();
+    while chart.goto_edge(&datas, true) {
+        steps.push(chart.cursor.unwrap());
+    }
+    assert_eq!(steps, [5000., 10000., 20000.]);
+    // View pans to keep the cursor visible
+    assert!(chart.time_range[0] <= 20000. && chart.time_range[1] >= 20000.);
+    assert_eq!(chart.time_range[1] - chart.time_range[0], 3000.);
+
+    chart.edge_filter = EdgeFilter::Falling;
+    chart.goto_edge(&datas, false);
+    assert_eq!(chart.cursor, Some(10000.));
+    chart.edge_filter = chart.edge_filter.next();
+    assert_eq!(chart.edge_filter, EdgeFilter::Any);
+    chart.edge_filter = EdgeFilter::Rising;
+    assert!(chart.goto_edge(&datas, false));
+    assert_eq!(chart.cursor, Some(5000.));
+
+    // Filter shown on the selected signal's label
+    let rec = record_with("edges_label.vcd", |c| {
+        c.selected = Some(0);
+        c.edge_filter = EdgeFilter::Falling;
+    });
+    assert!(rec.texts().any(|(text, _)| text == "top.clk [falling]"));
+    let rec = record_with("edges_any.vcd", |c| c.selected = Some(0));
+    assert!(rec.texts().any(|(text, _)| text == "top.clk"));
+}
+
+#[test]
+fn markers() {
+    let mut chart = Chart::new();
+    chart.set_max_range(&[0., 30000.], &TIMESCALE);
+    chart.set_range(&[0., 30000.], &TIMESCALE);
+    chart.cursor = Some(20000.);
+    chart.add_marker_at_cursor();
+    chart.cursor = Some(8000.);
+    chart.add_marker_at_cursor();
+    let names : Vec<(&str, TimeRel)> = chart.markers.iter().map(|m| (m.name.as_str(), m.time)).collect();
+    assert_eq!(names, [("M2", 8000.), ("M1", 20000.)]);
+    chart.cursor = Some(0.);
+    chart.goto_marker(true);
+    chart.goto_marker(true);
+    assert_eq!(chart.cursor, Some(20000.));
+    chart.goto_marker(false);
+    assert_eq!(chart.cursor, Some(8000.));
+    let idx = chart.current_marker(WIDTH).unwrap();
+    chart.markers[idx].name = "irq fired".to_string();
+    // Only a marker within a few pixels of the cursor
+    chart.cursor = Some(8100.);
+    assert_eq!(chart.current_marker(WIDTH), Some(idx));
+    chart.cursor = Some(9000.);
+    assert_eq!(chart.current_marker(WIDTH), None);
+
+    let path = temp_file("session.txt", "");
+    let datas = DataStore::new(TIMESCALE);
+    session::save(&path, &chart, &datas).unwrap();
+    let mut loaded = Chart::new();
+    session::load(&path, &mut loaded, &datas).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.markers, chart.markers);
+    loaded.delete_marker(0);
+    assert_eq!(loaded.markers.len(), 1);
+    // Colours keep cycling
+    for n in 0..7 {
+        loaded.add_marker(1000. * n as f64, "");
+    }
+    let colours : Vec<usize> = loaded.markers.iter().map(|m| m.colour).collect();
+    assert_eq!(colours[..7], [1, 2, 3, 4, 5, 0, 1]);
+
+    // Labels in the top ruler, ticks on the bottom ruler
+    let rec = record_with("markers.vcd", |c| c.markers = chart.markers.clone());
+    assert!(rec.texts().any(|(text, pos)| text == "irq fired" && pos.x == xpos(8000.) + 2.));
+    let lines = vlines(rec.strokes(Color::rgba8(255, 80, 80, 255)));
+    assert_eq!(lines, [xpos(20000.), xpos(20000.)]);
+}
+
+#[test]
+fn signal_groups() {
+    let path = temp_file("groups.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! awvalid $end\n\
+        $var wire 1 \" awready $end\n$var wire 1 # wvalid $end\n$upscope $end\n$enddefinitions $end\n#0\n0! 0\" 0#\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let mut chart = Chart::new();
+    chart.group_signal(2, "AXI write channel");
+    chart.group_signal(0, "AXI write channel");
+    assert_eq!(chart.rows(3), [Row::Group(0), Row::Signal(0), Row::Signal(2), Row::Signal(1)]);
+    assert_eq!(chart.group_of(2), Some("AXI write channel"));
+    assert_eq!(chart.rows_height(&datas), 4. * HEIGHT_DIGITAL);
+
+    // Clicking the header collapses the group to it
+    let header = Vec2::new(1., RULE_HEIGHT + 1.);
+    assert_eq!(chart.row_at(&datas, header.y), Some(Row::Group(0)));
+    assert!(chart.handle_group_click(&datas, &header, WIDTH));
+    assert_eq!(chart.rows(3), [Row::Group(0), Row::Signal(1)]);
+    assert_eq!(chart.signal_at(&datas, RULE_HEIGHT + HEIGHT_DIGITAL + 1.), Some(1));
+    chart.select_next(3, true);
+    chart.select_next(3, true);
+    assert_eq!(chart.selected, Some(1));
+
+    // Derived signals are named by their expression, with spaces
+    let both = datas.add_derived("top.awvalid && !top.wvalid").unwrap();
+    chart.group_signal(both, "AXI write channel");
+    let path = temp_file("groups_session.txt", "");
+    session::save(&path, &chart, &datas).unwrap();
+    let mut loaded = Chart::new();
+    session::load(&path, &mut loaded, &datas).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.groups, chart.groups);
+    assert_eq!(loaded.groups[0].signals.len(), 3);
+
+    // Ungrouping the last member removes the group
+    loaded.group_signal(0, "");
+    loaded.group_signal(2, "");
+    loaded.group_signal(both, "");
+    assert!(loaded.groups.is_empty());
+    assert_eq!(loaded.rows(3), [Row::Signal(0), Row::Signal(1), Row::Signal(2)]);
+
+    // Collapsed group draws a header in place of its members
+    let rec = record_with("groups_draw.vcd", |c| {
+        c.group_signal(0, "clocks");
+        c.groups[0].collapsed = true;
+    });
+    assert!(rec.texts().any(|(text, pos)| text == "[+] clocks (1)" && pos.y == RULE_HEIGHT + HEIGHT_DIGITAL - 2.));
+    assert!(!rec.texts().any(|(text, _)| text.contains("clk")));
+}
+
+#[test]
+fn derived_signals() {
+    let path = temp_file("derived.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! valid $end\n\
+        $var wire 1 \" ready $end\n$var wire 8 # addr [7:0] $end\n$var real 64 % v $end\n$upscope $end\n\
+        $enddefinitions $end\n#0\n0! 1\" b0 # r0.5 %\n#5\n1!\n#10\n0\" b10100101 #\n#15\n1\" r2 %\n#20\n0!\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let fire = datas.add_derived("fire = top.valid & top.ready").unwrap();
+    let hi = datas.add_derived("top.addr[7:4]").unwrap();
+    let dv = datas.add_derived("top.v - 0.5").unwrap();
+    let both = datas.add_derived("fire && top.addr == 0xa5").unwrap();
+    assert_eq!(datas.get_signal_field(fire), wavefmt::FieldInfo { name: "fire".to_string(), ftype: wavefmt::FieldType::Digital });
+    assert_eq!(datas.get_signal_field(hi).ftype, wavefmt::FieldType::DigiBus(4));
+    assert_eq!(datas.get_signal_field(dv).ftype, wavefmt::FieldType::Analog);
+
+    let range = [0., 30000.];
+    assert_eq!(export::read_changes(&datas, fire, &range).unwrap(), (0., vec![(5000., 1.), (10000., 0.), (15000., 1.), (20000., 0.)]));
+    assert_eq!(export::read_changes(&datas, hi, &range).unwrap(), (0., vec![(10000., 10.)]));
+    assert_eq!(export::read_changes(&datas, dv, &range).unwrap(), (0., vec![(15000., 1.5)]));
+    assert_eq!(export::read_changes(&datas, both, &range).unwrap(), (0., vec![(15000., 1.), (20000., 0.)]));
+    assert!(datas.add_derived("top.valid & nope").is_err());
+    assert!(datas.add_derived("top.valid &").is_err());
+}
+
+#[test]
+fn threshold_edges() {
+    // Rises through 1.5 at 7.5ns, dips to 1.4 (staying high) and falls through 1.0 at 23ns
+    let path = temp_file("threshold.csv", "time_ns,v\n0,0.0\n10,2\n20,1.4\n22,1.2\n24,0.8\n30,0\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let sig = datas.add_threshold("v:1.5:1.0").unwrap();
+    assert_eq!(datas.get_signal_field(sig).name, "v_logic");
+    let (initial, edges) = export::read_changes(&datas, sig, &[0., 30000.]).unwrap();
+    assert_eq!(initial, 0.);
+    assert_eq!(edges.len(), 2);
+    assert!((edges[0].0 - 7500.).abs() < 0.01 && edges[0].1 == 1.);
+    assert!((edges[1].0 - 23000.).abs() < 0.01 && edges[1].1 == 0.);
+    // Between the thresholds, the level is held from before
+    assert_eq!(export::value_at(&datas, sig, 21000.), 1.);
+    assert_eq!(export::read_changes(&datas, sig, &[15000., 30000.]).unwrap().0, 1.);
+    // Window starting between samples, the line from the sample before it still crosses
+    let (_, edges) = export::read_changes(&datas, sig, &[5000., 30000.]).unwrap();
+    assert!((edges[0].0 - 7500.).abs() < 0.01 && edges.len() == 2);
+    let (initial, edges) = export::read_changes(&datas, sig, &[8000., 30000.]).unwrap();
+    assert_eq!(initial, 1.);
+    assert!((edges[0].0 - 23000.).abs() < 0.01 && edges.len() == 1);
+    assert!(datas.add_threshold("v").is_err());
+    assert!(datas.add_threshold("nope:1").is_err());
+}
+
+#[test]
+fn uart_decode() {
+    // 1 Mbaud 8N1: 'A' from 2us, then 0x68 from 13us with its stop bit held low
+    let mut bits = vec![1, 1];
+    for (byte, stop) in [(0x41u32, 1), (0x68, 0)] {
+        bits.push(0);
+        bits.extend((0..8).map(|n| (byte >> n) & 1));
+        bits.extend([stop, 1]);
+    }
+    bits.extend([1, 1]);
+    let csv : String = bits.iter().enumerate().map(|(n, bit)| format!("{},{bit}\n", n * 1000)).collect();
+    let path = temp_file("uart.csv", &format!("time_ns,tx\n{csv}"));
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+
+    let dec = datas.add_decoder("uart:tx:1e6:8N1").unwrap();
+    assert_eq!(datas.get_signal_decoders(0), vec![dec]);
+    let segments = datas.get_decoder(dec).decode(&[0., 26e6]).unwrap();
+    let events : Vec<(TimeRel, Option<u64>, bool)> = segments.iter().map(|seg| (seg.span[0], seg.value, seg.error)).collect();
+    assert_eq!(events, vec![(2e6, Some(0x41), false), (13e6, Some(0x68), true)]);
+    assert_eq!(segments[0].label, "41 'A'");
+    assert_eq!(segments[1].label, "68 'h' FE");
+    // A byte in progress at the start or end of the range is still decoded
+    assert_eq!(datas.get_decoder(dec).decode(&[5e6, 26e6]).unwrap().len(), 2);
+    assert_eq!(datas.get_decoder(dec).decode(&[0., 15e6]).unwrap().len(), 2);
+    // Falling edges within the data bits of 'A' aren't taken as start bits
+    let segments = datas.get_decoder(dec).decode(&[14e6, 26e6]).unwrap();
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].span[0], 13e6);
+
+    // Decoded again when more data arrives
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    let bits = [0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1];
+    let csv : String = bits.iter().enumerate().map(|(n, bit)| format!("{},{bit}\n", 26000 + n * 1000)).collect();
+    file.write_all(csv.as_bytes()).unwrap();
+    assert!(datas.poll().unwrap());
+    let segments = datas.get_decoder(dec).decode(&[0., 40e6]).unwrap();
+    assert_eq!(segments.last().map(|seg| (seg.span[0], seg.value)), Some((26e6, Some(0x42))));
+    std::fs::remove_file(path).unwrap();
+    assert!(datas.add_decoder("uart:tx:1e6:8X1").is_err());
+    assert!(datas.add_decoder("uart:nope:9600").is_err());
+}
+
+#[test]
+fn spi_i2c_decode() {
+    // SPI mode 0: 9F out and A5 in, then 3 bits before chip select is released
+    let (mosi, miso) = (0x9fu32 << 3 | 0b101, 0xa5u32 << 3);
+    let mut rows = vec!["0,0,0,0,1".to_string(), "10,0,0,0,0".to_string()];
+    for n in 0..11 {
+        let (o, i) = ((mosi >> (10 - n)) & 1, (miso >> (10 - n)) & 1);
+        rows.push(format!("{},0,{o},{i},0", 20 + 20 * n));
+        rows.push(format!("{},1,{o},{i},0", 30 + 20 * n));
+    }
+    rows.push("240,0,0,0,0\n250,0,0,0,1\n300,0,0,0,1".to_string());
+    let path = temp_file("spi.csv", &format!("time_ns,sclk,mosi,miso,cs\n{}\n", rows.join("\n")));
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let dec = datas.add_decoder("spi:sclk:mosi:miso:cs:0:8").unwrap();
+    assert_eq!(datas.get_signal_decoders(3), vec![dec]);
+    let segments = datas.get_decoder(dec).decode(&[0., 300000.]).unwrap();
+    let labels : Vec<(&str, bool)> = segments.iter().map(|seg| (seg.label.as_str(), seg.error)).collect();
+    assert_eq!(labels, vec![("9F/A5", false), ("5/0?", true)]);
+    assert_eq!(segments[0].span, [10000., 170000.]);
+    assert_eq!(segments[0].value, Some(0x9f));
+    // Viewing part of a transfer
+    assert_eq!(datas.get_decoder(dec).decode(&[100000., 300000.]).unwrap(), segments);
+    assert_eq!(datas.get_decoder(dec).decode(&[180000., 200000.]).unwrap(), segments[1..]);
+    // Decoding more of a transfer resumes from the last word, as decoded all at once
+    let nibbles = datas.add_decoder("spi:sclk:mosi:miso:cs:0:4").unwrap();
+    let resumed = datas.add_decoder("spi:sclk:mosi:miso:cs:0:4").unwrap();
+    assert_eq!(datas.get_decoder(resumed).decode(&[0., 60000.]).unwrap().len(), 1);
+    let segments = datas.get_decoder(nibbles).decode(&[0., 300000.]).unwrap();
+    let labels : Vec<&str> = segments.iter().map(|seg| seg.label.as_str()).collect();
+    assert_eq!(labels, vec!["9/A", "F/5", "5/0?"]);
+    assert_eq!(datas.get_decoder(resumed).decode(&[0., 300000.]).unwrap(), segments);
+    assert!(datas.add_decoder("spi:sclk:-:-:cs").is_err());
+
+    // I2C write of 3C to address 50, not acknowledged
+    let mut states = vec![(1, 1), (1, 0)];
+    for byte in [0xa0u32 << 1, 0x3c << 1 | 1] {
+        for n in (0..9).rev() {
+            let bit = (byte >> n) & 1;
+            states.extend([(0, bit), (1, bit)]);
+        }
+    }
+    states.extend([(0, 0), (1, 0), (1, 1)]);
+    let csv : String = states.iter().enumerate().map(|(n, (scl, sda))| format!("{},{scl},{sda}\n", n * 5)).collect();
+    let path = temp_file("i2c.csv", &format!("time_ns,scl,sda\n{csv}"));
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let dec = datas.add_decoder("i2c:scl:sda").unwrap();
+    let segments = datas.get_decoder(dec).decode(&[0., 1e6]).unwrap();
+    let labels : Vec<&str> = segments.iter().map(|seg| seg.label.as_str()).collect();
+    assert_eq!(labels, vec!["S", "Addr 50 W", "ACK", "3C", "NACK", "P"]);
+    assert!(segments.iter().all(|seg| !seg.error));
+    assert_eq!(segments[1].value, Some(0x50));
+}
+
+#[test]
+fn condition_search() {
+    let path = temp_file("search.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! valid $end\n\
+        $var wire 1 \" ready $end\n$var wire 8 # state [7:0] $end\n$upscope $end\n$enddefinitions $end\n\
+        #0\n0! 1\" b0 #\n#5\n1!\n#10\n0\" b101 #\n#15\n1\"\n#20\n0!\n#25\n1!\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let range = [0., 30000.];
+    let found = search::Search::new(&datas, "top.state == 5 && top.valid", &range).unwrap();
+    assert_eq!(found.matches(), [10000., 25000.]);
+    assert_eq!(found.next(10000., true), Some(25000.));
+    assert_eq!(found.next(10000., false), None);
+    assert_eq!(found.next(30000., false), Some(25000.));
+    // Holding at the start of range counts as a match
+    let found = search::Search::new(&datas, "top.ready", &range).unwrap();
+    assert_eq!(found.matches(), [0., 15000.]);
+    assert!(search::Search::new(&datas, "top.nope", &range).is_err());
+}
+
+#[test]
+fn analog_measurements() {
+    let path = temp_file("measure.csv", "time_ns,v\n0,1.0\n10,3.0\n20,-1.0\n30,-1.0\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let stats = measure::analog(&datas, 0, &[0., 30000.]).unwrap();
+    assert_eq!((stats.min, stats.max, stats.pk_pk), (-1., 3., 4.));
+    // Straight lines between samples
+    assert!((stats.mean - 2. / 3.).abs() < 1e-9);
+    assert!((stats.rms - (23f64 / 9.).sqrt()).abs() < 1e-9);
+    assert!((stats.integral - 2e-8).abs() < 1e-15);
+    // Window part way between samples
+    let stats = measure::analog(&datas, 0, &[5000., 15000.]).unwrap();
+    assert_eq!((stats.min, stats.max), (1., 3.));
+    assert!((stats.mean - 2.25).abs() < 1e-9);
+
+    // Held values
+    let path = temp_file("measure.vcd", "$timescale 1ns $end $var real 64 ! v $end $enddefinitions $end
+#0 r1 ! #10 r3 ! #20 r-1 ! #30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let stats = measure::analog(&datas, 0, &[0., 30000.]).unwrap();
+    assert_eq!((stats.min, stats.max), (-1., 3.));
+    assert!((stats.mean - 1.).abs() < 1e-9);
+    assert!((stats.rms - (11f64 / 3.).sqrt()).abs() < 1e-9);
+    let stats = measure::analog(&datas, 0, &[5000., 20000.]).unwrap();
+    assert_eq!((stats.min, stats.max), (1., 3.));
+    assert!((stats.mean - 7. / 3.).abs() < 1e-9);
+
+    let mut chart = Chart::new();
+    chart.set_range(&[0., 30000.], &TIMESCALE);
+    assert_eq!(chart.measure_range(), [0., 30000.]);
+    chart.cursor = Some(20000.);
+    chart.cursor2 = Some(10000.);
+    assert_eq!(chart.measure_range(), [10000., 20000.]);
+}
+
+#[test]
+fn digital_measurements() {
+    let path = temp_file("timing.csv", "time_ns,clk\n0,0\n10,1\n13,0\n20,1\n24,0\n30,1\n33,0\n40,0\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let stats = measure::digital(&datas, 0, &[0., 40000.]).unwrap();
+    assert_eq!(stats.edges, 6);
+    assert_eq!((stats.period_avg, stats.period_min, stats.period_max), (Some(10000.), Some(10000.), Some(10000.)));
+    assert!((stats.frequency.unwrap() - 1e8).abs() < 1e-3);
+    assert!((stats.duty.unwrap() - 0.35).abs() < 1e-9);
+    assert_eq!((stats.high_min, stats.high_max), (Some(3000.), Some(4000.)));
+    assert_eq!((stats.low_min, stats.low_max), (Some(6000.), Some(7000.)));
+    // A single pulse has no period
+    let stats = measure::digital(&datas, 0, &[5000., 15000.]).unwrap();
+    assert_eq!((stats.edges, stats.period_avg, stats.high_max), (2, None, Some(3000.)));
+    assert_eq!(measure::count_edges(&datas, 0, &[15000., 35000.]).unwrap(), 4);
+    assert!(measure::analog(&datas, 0, &[0., 40000.]).is_err());
+}
+
+#[test]
+fn clock_jitter() {
+    let path = temp_file("jitter.csv", "time_ns,clk,v\n0,0,0.0\n10,1,2.0\n15,0,2.0\n20,1,0.0\n25,0,1.0\n32,1,2.0\n36,0,2.0\n40,1,0.0\n45,0,0.0\n50,1,0.0\n55,0,0.0\n60,0,0.0\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let jit = jitter::analyse(&datas, 0, &[0., 60000.], None).unwrap();
+    assert_eq!(jit.edges, vec![10000., 20000., 32000., 40000., 50000.]);
+    assert_eq!(jit.periods, vec![10000., 12000., 8000., 10000.]);
+    assert_eq!(jit.c2c, vec![2000., -4000., 2000.]);
+    assert!((jit.ideal_period - 10000.).abs() < 1e-6);
+    let expected = [-400., -400., 1600., -400., -400.];
+    assert!(jit.tie.iter().zip(expected).all(|(tie, exp)| (tie - exp).abs() < 1e-6));
+    let hist = jit.histogram(4);
+    assert_eq!((hist.start, hist.bin_width, hist.counts), (8000., 1000., vec![1, 0, 2, 1]));
+
+    let path = std::env::temp_dir().join("wavygravy_jitter_out.csv");
+    jit.write_csv(TIMESCALE, &path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(text.lines().next(), Some("edge,time_ps,period_ps,c2c_ps,tie_ps"));
+    assert_eq!(text.lines().nth(2), Some("1,20000,12000,2000,-400"));
+
+    // Analog rising crossings of the midpoint
+    let edges = jitter::edge_times(&datas, 1, &[0., 60000.], None).unwrap();
+    assert_eq!(edges, vec![5000., 25000.]);
+    // Window starting between samples
+    let edges = jitter::edge_times(&datas, 1, &[2000., 60000.], Some(1.)).unwrap();
+    assert_eq!(edges, vec![5000., 25000.]);
+    assert!(jitter::analyse(&datas, 0, &[0., 15000.], None).is_err());
+
+    // Held VCD real crosses at the change
+    let path = temp_file("jitter.vcd", "$timescale 1ns $end $var real 64 ! v $end $enddefinitions $end
+#0 r0 ! #10 r2 ! #20 r0 ! #30 r2 ! #40\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let edges = jitter::edge_times(&datas, 0, &[5000., 40000.], Some(1.)).unwrap();
+    assert_eq!(edges, vec![10000., 30000.]);
+}
+
+#[test]
+fn eye_diagram() {
+    // 10ns bits with 2ns edges, rails drifting slightly so every point is a change, and a
+    // clock rising mid-bit
+    let bits = [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0];
+    let mut csv = "time_ns,clk,v\n".to_string();
+    for (k, &bit) in bits.iter().enumerate() {
+        let t = 10. * k as f64;
+        let level = bit as f64;
+        csv += &format!("{},0,{:.4}\n{},1,{:.4}\n{},0,{:.4}\n", t + 1., level, t + 5., level + 0.0005, t + 9., level + 0.001);
+    }
+    let path = temp_file("eye.csv", &csv);
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let range = [1000., 119000.];
+
+    for folding in [None, Some(eye::Folding::Period(10000.)), Some(eye::Folding::Clock(0))] {
+        let eye = eye::eye(&datas, 1, &range, folding, eye::BINS).unwrap();
+        assert!((eye.ui - 10000.).abs() < 0.01, "{folding:?}");
+        assert!((eye.height() - 62. * 1.001 / 64.).abs() < 1e-6, "{folding:?}");
+        assert!((eye.width() - 126. * 10000. / 128.).abs() < 0.01, "{folding:?}");
+    }
+    assert_eq!(eye::Folding::parse(&datas, "10ns").unwrap(), eye::Folding::Period(10000.));
+    assert_eq!(eye::Folding::parse(&datas, "clk").unwrap(), eye::Folding::Clock(0));
+    assert!(eye::Folding::parse(&datas, "nope").is_err());
+    assert!(eye::eye(&datas, 0, &range, None, eye::BINS).is_err());
+}
+
+#[test]
+fn fft_spectrum() {
+    // 1MHz sine of amplitude 0.5 sampled every 10ns
+    let mut csv = "time_ns,v\n".to_string();
+    for n in 0..=1000 {
+        let t = 10. * n as f64;
+        csv += &format!("{t},{:.6}\n", 0.5 * (2. * std::f64::consts::PI * t * 1e-3).sin() + 1e-6);
+    }
+    let path = temp_file("spectrum.csv", &csv);
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let mut window = spectrum::Window::Hann;
+    for _ in 0..3 {
+        let spec = spectrum::spectrum(&datas, 0, &[0., 1e7], window).unwrap();
+        assert_eq!(spec.db.len(), spectrum::POINTS / 2 + 1);
+        assert!((spec.bin_hz - 1e5).abs() < 1e-6);
+        let (hz, db) = spec.peak().unwrap();
+        assert!((hz - 1e6).abs() < 1e-3, "{window}");
+        assert!((db - 20. * 0.5f64.log10()).abs() < 0.05, "{window} {db}");
+        window = window.next();
+    }
+    assert_eq!(window, spectrum::Window::Hann);
+    assert_eq!("flattop".parse(), Ok(spectrum::Window::FlatTop));
+    assert!(spectrum::spectrum(&datas, 0, &[10., 10.], window).is_err());
+
+    // Sine at the resampling rate, 8 points per period: averaged out rather than aliased to DC
+    let mut csv = "time_ps,v\n".to_string();
+    for n in 0..=8 * spectrum::POINTS {
+        csv += &format!("{},{:.6}\n", 125 * n, (std::f64::consts::PI * n as f64 / 4.).cos());
+    }
+    let path = temp_file("spectrum_dense.csv", &csv);
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let spec = spectrum::spectrum(&datas, 0, &[0., 1000. * spectrum::POINTS as f64], window).unwrap();
+    assert!(spec.db[0] < -60., "{}", spec.db[0]);
+}
+
+#[test]
+fn xy_trajectory() {
+    // Quadrature pair sampled at different times, and a step between two points
+    let path = temp_file("xy.csv", "time_ns,i,q\n0,1.0,0.0\n10,0.0,1.0\n20,-1.0,0.0\n30,0.0,-1.0\n40,1.0,0.0\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let traj = xy::trajectory(&datas, 0, 1, &[0., 40000.]).unwrap();
+    assert_eq!(traj.points.len(), 5);
+    assert_eq!(traj.points[1], (10000., 0., 1.));
+    assert_eq!((traj.x_range, traj.y_range), ([-1., 1.], [-1., 1.]));
+    assert_eq!(traj.point_at(15000.), Some((-0.5, 0.5)));
+    assert_eq!(traj.point_at(50000.), None);
+    // Window part way between points
+    let traj = xy::trajectory(&datas, 0, 1, &[5000., 25000.]).unwrap();
+    assert_eq!(traj.points.len(), 4);
+    assert_eq!(traj.points[0], (5000., 0.5, 0.5));
+    assert_eq!(traj.points[2], (20000., -1., 0.));
+    assert_eq!(traj.points[3], (25000., -0.5, -0.5));
+    assert!(xy::trajectory(&datas, 0, 0, &[0., 40000.]).is_ok());
+}
+
+#[test]
+fn ring_buffer() {
+    let buf = Rc::new(RefCell::new(RingBuf::new(3, false)));
+    let sig = RingSig::<f32>::new(buf.clone(), "v".to_string());
+    assert_eq!(buf.borrow().get_range(), None);
+    assert_eq!(Sampler::<f32>::get_yscale(&sig), 1.);
+    for (t, val) in [(0., -4.), (10., 1.), (20., 2.), (30., 1.)] {
+        buf.borrow_mut().push(t, val);
+    }
+    // Oldest sample dropped, along with its peak
+    assert_eq!(buf.borrow().get_range(), Some((10., 30.)));
+    assert_eq!(Sampler::<f32>::get_yscale(&sig), 2.);
+    assert_eq!(sig.get_value_at(25., TIMESCALE), 2.);
+    assert_eq!(sig.get_value_at(0., TIMESCALE), 1.);
+    // Last value extended to end of range
+    let samples : Vec<(f32, TimeRel)> = sig.iter_range(&[10., 40.]).unwrap().collect();
+    assert_eq!(samples, vec![(2., 20.), (1., 30.), (1., 40.)]);
+    // Decaying signal, peak moving with the oldest sample
+    for (n, val) in [-8., 7., 6., 5., 4.].into_iter().enumerate() {
+        buf.borrow_mut().push(40. + 10. * n as f64, val);
+    }
+    assert_eq!(Sampler::<f32>::get_yscale(&sig), 6.);
+    buf.borrow_mut().push(90., 3.);
+    assert_eq!(Sampler::<f32>::get_yscale(&sig), 5.);
+
+    let buf = Rc::new(RefCell::new(RingBuf::new(3, true)));
+    let sig = RingSig::<bool>::new(buf.clone(), "d".to_string());
+    for (t, val) in [(0., 0.), (10., 1.), (20., 1.), (30., 0.), (40., 0.), (50., 1.)] {
+        buf.borrow_mut().push(t, val);
+    }
+    // Repeated values not stored, so the change at 10 is still buffered
+    assert_eq!(buf.borrow().get_range(), Some((10., 50.)));
+    let samples : Vec<(bool, TimeRel)> = sig.iter_range(&[0., 60.]).unwrap().collect();
+    assert_eq!(samples, vec![(true, 10.), (false, 30.), (true, 50.)]);
+    assert!(!sig.get_value_at(35., TIMESCALE));
+}
+
+#[cfg(unix)]
+#[test]
+fn live_stream() {
+    use wavefmt::{FieldInfo, FieldType, stream::{write_msg, StreamMsg}};
+    let path = std::env::temp_dir().join(format!("wavygravy_{}_stream.sock", std::process::id()));
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.listen(&format!("unix:{}", path.display()), 4).unwrap();
+
+    // Local generator: a clock and a ramp, 1ns per batch
+    let mut conn = std::os::unix::net::UnixStream::connect(&path).unwrap();
+    let fields = vec![
+        FieldInfo { name: "clk".to_string(), ftype: FieldType::Digital },
+        FieldInfo { name: "ramp".to_string(), ftype: FieldType::Analog },
+    ];
+    write_msg(&mut conn, &StreamMsg::Declare { timescale: 1e-9, fields }).unwrap();
+    for n in 0..10 {
+        let values = vec![(0, (n % 2) as f32), (1, n as f32)];
+        write_msg(&mut conn, &StreamMsg::Batch { time: n as f64, values }).unwrap();
+    }
+    // Batches going back in time, and signals with an invalid timescale, are dropped
+    write_msg(&mut conn, &StreamMsg::Batch { time: 5., values: vec![(1, 100.)] }).unwrap();
+    write_msg(&mut conn, &StreamMsg::Batch { time: f64::NAN, values: vec![(1, 100.)] }).unwrap();
+    let fields = vec![FieldInfo { name: "bad".to_string(), ftype: FieldType::Analog }];
+    write_msg(&mut conn, &StreamMsg::Declare { timescale: 0., fields }).unwrap();
+    write_msg(&mut conn, &StreamMsg::Batch { time: 10., values: vec![(0, 0.), (1, 10.)] }).unwrap();
+    drop(conn);
+
+    // Keeps the last 4 samples once all are received
+    for _ in 0..100 {
+        datas.poll().unwrap();
+        if datas.get_range() == (7000., 10000.) { break; }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(datas.get_range(), (7000., 10000.));
+    assert_eq!(datas.get_num_signals(), 2);
+    let ramp = datas.get_ana_sampler(0).unwrap().borrow();
+    assert_eq!(ramp.get_label(), "ramp");
+    assert_eq!(ramp.get_value_at(7500., TIMESCALE), 7.);
+    assert_eq!(ramp.get_yscale(), 10.);
+    let clk = datas.get_dig_sampler(0).unwrap().borrow();
+    assert!(clk.get_value_at(9000., TIMESCALE));
+    assert!(!clk.get_value_at(8500., TIMESCALE));
+    assert!(!clk.get_value_at(10000., TIMESCALE));
+}
+
+#[test]
+fn csv_export() {
+    let path = temp_file("csv_export.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $var real 64 % v $end\n$upscope $end\n$enddefinitions $end\n#0\n0!\nr0.5 %\n#5\n1!\n#10\n0!\nr2.25 %\n#20\n1!\n#30\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let out = std::env::temp_dir().join(format!("wavygravy_{}_export.csv", std::process::id()));
+
+    export::export_csv(&datas, &[0, 1], &[2000., 25000.], export::CsvMode::Transitions, &out).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(),
+        "time_ps,top.clk,top.v\n2000,0,0.5\n5000,1,0.5\n10000,0,2.25\n20000,1,2.25\n");
+    export::export_csv(&datas, &[1, 0], &[0., 25000.], export::CsvMode::Interval(8000.), &out).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(),
+        "time_ps,top.v,top.clk\n0,0.5,0\n8000,0.5,1\n16000,2.25,0\n24000,2.25,1\n");
+    export::export_csv(&datas, &[1], &[0., 25000.], export::CsvMode::Clock(0), &out).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "time_ps,top.v\n5000,0.5\n20000,2.25\n");
+    // Interval mode limited to MAX_ROWS rows
+    let step = 25000. / export::MAX_ROWS as f64;
+    assert!(export::export_csv(&datas, &[0], &[0., 25000.], export::CsvMode::Interval(step / 2.), &out).is_err());
+    std::fs::remove_file(out).unwrap();
+}
+
+#[test]
+fn wave_diff() {
+    let header = |only: &str| format!("$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $var real 64 % v $end\n$var wire 1 \" {only} $end\n$upscope $end\n$enddefinitions $end\n");
+    let golden = temp_file("diff_a.vcd", &(header("a_only") + "#0\n0!\nr0.5 %\n0\"\n#5\n1!\n#10\n0!\n#20\n1!\n#30\n"));
+    let other = temp_file("diff_b.vcd", &(header("b_only") + "#0\n1!\nr0.55 %\n0\"\n#5\n1!\n#10\n0!\n#20\n0!\nr1.0 %\n#30\n"));
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(golden.clone(), FileType::TryAny).unwrap();
+    datas.load_wave(other.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(golden).unwrap();
+    std::fs::remove_file(other).unwrap();
+
+    // Spans clipped to the range at both ends, analog values within tolerance match
+    let wdiff = diff::compare(&datas, [0, 1], &[2000., 25000.], 0.1).unwrap();
+    assert_eq!(wdiff.unpaired, vec!["top.a_only", "top.b_only"]);
+    assert_eq!(wdiff.diffs.len(), 2);
+    assert_eq!((wdiff.diffs[0].name.as_str(), wdiff.diffs[0].sigs), ("top.clk", [0, 3]));
+    assert_eq!(wdiff.diffs[0].spans, vec![[2000., 5000.], [20000., 25000.]]);
+    assert_eq!((wdiff.diffs[1].name.as_str(), wdiff.diffs[1].sigs), ("top.v", [1, 4]));
+    assert_eq!(wdiff.diffs[1].spans, vec![[20000., 25000.]]);
+    assert_eq!(wdiff.mismatches(), vec![("top.clk", [2000., 5000.]), ("top.clk", [20000., 25000.]),
+        ("top.v", [20000., 25000.])]);
+
+    // Without tolerance
+    let wdiff = diff::compare(&datas, [0, 1], &[2000., 25000.], 0.).unwrap();
+    assert_eq!(wdiff.diffs[1].spans, vec![[2000., 25000.]]);
+    // Same file
+    let wdiff = diff::compare(&datas, [0, 0], &[0., 30000.], 0.).unwrap();
+    assert!(wdiff.diffs.is_empty() && wdiff.unpaired.is_empty());
+}
+
+#[test]
+fn wavedrom_export() {
+    let path = temp_file("wavedrom.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $var wire 8 # data [7:0] $end\n$var wire 1 $ en $end\n$var real 64 % v $end\n$upscope $end\n$enddefinitions $end\n\
+        #0\n0!\nb0 #\n0$\nr0.5 %\n#5\n1!\n#10\n0!\nb1011 #\n1$\n#15\n1!\n#20\n0!\n#25\n1!\n#27\nb0 #\n#30\n0!\n#35\n1!\n#40\n");
+    let mut datas = DataStore::new(TIMESCALE);
+    datas.load_wave(path.clone(), FileType::TryAny).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(export::find_clock(&datas, None), Some(0));
+    assert_eq!(export::find_clock(&datas, Some("top.en")), Some(2));
+
+    // Analog signals left out, one cell per rising edge
+    let out = std::env::temp_dir().join(format!("wavygravy_{}_export.json", std::process::id()));
+    export::export_wavedrom(&datas, 0, &[0, 1, 2, 3], &[0., 40000.], &out).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"signal\": [\n\
+        \x20 {\"name\": \"top.clk\", \"wave\": \"p...\"},\n\
+        \x20 {\"name\": \"top.data\", \"wave\": \"==.=\", \"data\": [\"0x0\", \"0xb\", \"0x0\"]},\n\
+        \x20 {\"name\": \"top.en\", \"wave\": \"01..\"}\n]}\n");
+    // Re-read with 10ns cells
+    let mut reread = DataStore::new(TIMESCALE);
+    reread.load_wave(out.clone(), FileType::TryAny).unwrap();
+    assert_eq!(reread.get_num_signals(), 3);
+    assert_eq!(reread.get_range(), (0., 40000.));
+
+    // Edges within the range only
+    export::export_wavedrom(&datas, 0, &[2], &[12000., 30000.], &out).unwrap();
+    assert!(std::fs::read_to_string(&out).unwrap().contains("{\"name\": \"top.en\", \"wave\": \"1.\"}"));
+    assert!(export::export_wavedrom(&datas, 0, &[2], &[36000., 40000.], &out).is_err());
+    std::fs::remove_file(out).unwrap();
+}
+
+#[test]
+fn export_signals() {
+    let mut chart = Chart::new();
+    assert_eq!(chart.export_signals(3), [0, 1, 2]);
+    // Members of a collapsed group are exported with it
+    chart.group_signal(2, "bus");
+    chart.group_signal(0, "bus");
+    chart.groups[0].collapsed = true;
+    assert_eq!(chart.export_signals(3), [0, 2, 1]);
+    chart.selected = Some(1);
+    assert_eq!(chart.export_signals(3), [1]);
+}
+
+/// Run a headless command, with arguments as given to the binary
+fn run_cli(args: &[&str]) -> Result<()> {
+    let args = Args::try_parse_from(std::iter::once("wavygravy_bin").chain(args.iter().copied()))?;
+    cli::run(args.command.unwrap())
+}
+
+#[test]
+fn cli_commands() {
+    let path = temp_file("cli.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $var real 64 % v $end\n$upscope $end\n$enddefinitions $end\n#0\n0!\nr0.5 %\n#5\n1!\n#10\n0!\nr2.25 %\n#20\n1!\n#30\n");
+    let other = temp_file("cli_other.vcd", "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n\
+        $upscope $end\n$enddefinitions $end\n#0\n0!\n#5\n1!\n#10\n0!\n#30\n");
+    let out = std::env::temp_dir().join(format!("wavygravy_{}_cli.csv", std::process::id()));
+    let (wave, other_wave, out_csv) = (path.to_str().unwrap(), other.to_str().unwrap(), out.to_str().unwrap());
+
+    run_cli(&["export-csv", wave, "-o", out_csv, "-s", "top.v", "--interval", "10ns", "--to", "20ns"]).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "time_ps,top.v\n0,0.5\n10000,2.25\n20000,2.25\n");
+    assert!(run_cli(&["export-csv", wave, "-o", out_csv, "--interval", "0"]).is_err());
+    assert!(run_cli(&["export-csv", wave, "-o", out_csv, "--clock", "top.v"]).is_err());
+    run_cli(&["range", wave, "--json"]).unwrap();
+    run_cli(&["spectrum", "top.v", wave, "--json"]).unwrap();
+    assert!(run_cli(&["value", "top.x", "1ns", wave]).is_err());
+    assert!(run_cli(&["range", wave, "--wavedrom-cell", "10"]).is_ok());
+    assert!(run_cli(&["range", wave, "--wavedrom-cell", "fast"]).is_err());
+    // Commands finding nothing fail
+    assert!(run_cli(&["search", "top.clk && top.v > 3", wave]).is_err());
+    assert!(run_cli(&["diff", wave, other_wave]).is_err());
+    assert!(run_cli(&["diff", wave, wave]).is_ok());
+    assert!(run_cli(&["diff", wave]).is_err());
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(other).unwrap();
+    std::fs::remove_file(out).unwrap();
+
+    // JSON has no NaN or infinities
+    assert_eq!(cli::json_num(-2.5), "-2.5");
+    assert_eq!(cli::json_num(f64::NEG_INFINITY), "null");
+    assert_eq!(cli::json_num(f64::NAN), "null");
+}
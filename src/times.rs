@@ -9,6 +9,36 @@ pub enum TimeUnit {
     S,
 }
 
+impl TimeUnit {
+    /// Length of one unit in seconds
+    pub fn secs(&self) -> f64 {
+        match self {
+            TimeUnit::Fs => 1e-15,
+            TimeUnit::Ps => 1e-12,
+            TimeUnit::Ns => 1e-9,
+            TimeUnit::Us => 1e-6,
+            TimeUnit::Ms => 1e-3,
+            TimeUnit::S  => 1.,
+        }
+    }
+}
+
+impl std::str::FromStr for TimeUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "fs" => Ok(TimeUnit::Fs),
+            "ps" => Ok(TimeUnit::Ps),
+            "ns" => Ok(TimeUnit::Ns),
+            "us" => Ok(TimeUnit::Us),
+            "ms" => Ok(TimeUnit::Ms),
+            "s"  => Ok(TimeUnit::S),
+            _ => Err(()),
+        }
+    }
+}
+
 impl std::fmt::Display for TimeUnit {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let s = match self {
@@ -48,6 +78,11 @@ impl Default for TimeScale {
 }
 
 impl TimeScale {
+    /// Length of this timescale in seconds
+    pub fn secs(&self) -> f64 {
+        self.time * self.unit.secs()
+    }
+
     pub fn scale_factor(&self) -> TimeRel {
         match self.unit {
             TimeUnit::Fs => self.time * 1000_000_000_000_000.0,
@@ -79,3 +114,23 @@ pub fn fmt_time_unit(ts: TimeScale) -> String {
     format!("{} {}", buf.as_str().to_string(), ts.unit)
 }
 
+/// Format frequency (in Hz) with an SI prefix, e.g. `12.5 MHz`
+pub fn fmt_freq(hz: f64) -> String {
+    const PREFIXES : [(f64, &str); 5] = [(1e12, "T"), (1e9, "G"), (1e6, "M"), (1e3, "k"), (1., "")];
+    let (scale, prefix) = PREFIXES.iter().find(|(scale, _)| hz.abs() >= *scale).unwrap_or(&(1., ""));
+    format!("{} {prefix}Hz", (hz / scale * 1000.).round() / 1000.)
+}
+
+/// Parse time such as `150ns` or `2.5 us` into global time units, a plain number is
+/// already in global time units
+pub fn parse_time(s: &str, timescale: &TimeScale) -> Option<TimeRel> {
+    let s = s.trim();
+    let split = s.rfind(|c: char| c.is_ascii_digit() || c == '.').map(|pos| pos + 1).unwrap_or(0);
+    let val : f64 = s[..split].parse().ok()?;
+    let unit = s[split..].trim();
+    if unit.is_empty() {
+        return Some(val);
+    }
+    let unit : TimeUnit = unit.parse().ok()?;
+    Some(val * wavefmt::time_ratio(unit.secs(), timescale.secs()))
+}
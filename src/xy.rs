@@ -0,0 +1,120 @@
+//! XY plots of one analog signal against another, for Lissajous figures and constellations
+use std::io::{Error, ErrorKind};
+use vello::{
+    kurbo::{Affine, BezPath, Circle, Line, Rect},
+    peniko::{Brush, Color, Fill, Stroke},
+};
+use crate::{DataStore, DrawSink, Result, TimeRel, RULE_HEIGHT, SCROLL_WIDTH};
+use crate::datastore::SigType;
+use crate::measure::PANEL_BG_COL;
+
+/// Most points taken over a window, sampled uniformly when the signals have more
+pub const MAX_POINTS : usize = 20000;
+/// Number of steps the trajectory fades over with persistence
+const FADE_STEPS : usize = 16;
+const AXIS_COL : Brush = Brush::Solid(Color::rgb8(90, 90, 120));
+
+/// Path of (x, y) values through a time window
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    /// Time, x and y of each point in order
+    pub points: Vec<(TimeRel, f64, f64)>,
+    pub x_range: [f64; 2],
+    pub y_range: [f64; 2],
+}
+
+impl Trajectory {
+    /// Point of the trajectory at time `t` (interpolated), if within it
+    pub fn point_at(&self, t: TimeRel) -> Option<(f64, f64)> {
+        let idx = self.points.partition_point(|p| p.0 < t);
+        let next = self.points.get(idx)?;
+        if next.0 == t { return Some((next.1, next.2)); }
+        let prev = self.points.get(idx.checked_sub(1)?)?;
+        let frac = (t - prev.0) / (next.0 - prev.0);
+        Some((prev.1 + frac * (next.1 - prev.1), prev.2 + frac * (next.2 - prev.2)))
+    }
+}
+
+/// Sample analog signals `x_sig` and `y_sig` at the times either changes within `range`,
+/// interpolating each between its own points
+pub fn trajectory(datas: &DataStore, x_sig: usize, y_sig: usize, range: &[TimeRel; 2]) -> Result<Trajectory> {
+    for sig in [x_sig, y_sig] {
+        if datas.get_signal_type_idx(sig).0 != SigType::Analog {
+            let msg = format!("Signal is not analog: {}", datas.get_signal_field(sig).name);
+            return Err(Box::new(Error::new(ErrorKind::InvalidInput, msg)));
+        }
+    }
+    let (x_initial, x_trace) = crate::export::read_trace(datas, x_sig, range)?;
+    let (y_initial, y_trace) = crate::export::read_trace(datas, y_sig, range)?;
+    let mut times : Vec<TimeRel> = std::iter::once(range[0])
+        .chain(x_trace.iter().chain(&y_trace).map(|p| p.0))
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times.dedup();
+    if times.len() > MAX_POINTS {
+        let step = (range[1] - range[0]) / (MAX_POINTS - 1) as f64;
+        times = (0..MAX_POINTS).map(|n| range[0] + step * n as f64).collect();
+    }
+    let xs = crate::export::resample((range[0], x_initial), &x_trace, &times);
+    let ys = crate::export::resample((range[0], y_initial), &y_trace, &times);
+    let limits = |vals: &[f64]| [vals.iter().copied().fold(f64::INFINITY, f64::min), vals.iter().copied().fold(f64::NEG_INFINITY, f64::max)];
+    let (x_range, y_range) = (limits(&xs), limits(&ys));
+    let points = times.into_iter().zip(xs).zip(ys).map(|((t, x), y)| (t, x, y)).collect();
+    Ok(Trajectory { points, x_range, y_range })
+}
+
+/// Draw titled XY plot with readout lines at the top right of the waveform window, older
+/// parts of the trajectory faded with `persistence`, and the point at `cursor` highlighted
+#[allow(clippy::too_many_arguments)]
+pub fn draw(sb: &mut impl DrawSink, title: &str, traj: Option<&Trajectory>, lines: &[String], cursor: Option<TimeRel>, persistence: bool, width: f64, height: f64) {
+    let label_height = (RULE_HEIGHT - 4.) as f32;
+    let line_height = RULE_HEIGHT - 4.;
+    let text_height = line_height * (lines.len() + 1) as f64 + 8.;
+    let size = (width - SCROLL_WIDTH).min(height - 2. * RULE_HEIGHT - text_height) * 0.6;
+    let panel = Rect::new(width - SCROLL_WIDTH - size - 16., RULE_HEIGHT + 8., width - SCROLL_WIDTH - 8., RULE_HEIGHT + text_height + size + 16.);
+    sb.fill(Fill::NonZero, Affine::IDENTITY, &PANEL_BG_COL, None, &panel);
+    let text = std::iter::once(title).chain(lines.iter().map(|l| l.as_str()));
+    for (num, line) in text.enumerate() {
+        sb.text(
+            label_height,
+            Some(&Brush::Solid(if num == 0 { Color::YELLOW } else { Color::WHITE })),
+            Affine::translate((panel.x0 + 4., panel.y0 + 4. + line_height * (num + 1) as f64 - 2.)),
+            line,
+        );
+    }
+    let Some(traj) = traj.filter(|t| !t.points.is_empty()) else { return };
+    if size <= 0. { return; }
+    let plot = Rect::new(panel.x0 + 4., panel.y0 + text_height, panel.x0 + 4. + size, panel.y0 + text_height + size);
+    let span = |range: [f64; 2]| if range[1] > range[0] { range[1] - range[0] } else { 1. };
+    let to_plot = |x: f64, y: f64| (
+        plot.x0 + plot.width() * (x - traj.x_range[0]) / span(traj.x_range),
+        plot.y1 - plot.height() * (y - traj.y_range[0]) / span(traj.y_range),
+    );
+    // Zero axes, when within the plot
+    if traj.x_range[0] < 0. && traj.x_range[1] > 0. {
+        let (x, _) = to_plot(0., 0.);
+        sb.stroke(&Stroke::new(1.0), Affine::IDENTITY, &AXIS_COL, None, &Line::new((x, plot.y0), (x, plot.y1)));
+    }
+    if traj.y_range[0] < 0. && traj.y_range[1] > 0. {
+        let (_, y) = to_plot(0., 0.);
+        sb.stroke(&Stroke::new(1.0), Affine::IDENTITY, &AXIS_COL, None, &Line::new((plot.x0, y), (plot.x1, y)));
+    }
+    // Trajectory in chunks, the oldest dimmest with persistence
+    let chunks = if persistence { FADE_STEPS } else { 1 };
+    let chunk_len = traj.points.len().div_ceil(chunks);
+    let count = traj.points.len().div_ceil(chunk_len);
+    for num in 0..count {
+        // From the end of the previous chunk, so the chunks join
+        let from = (num * chunk_len).saturating_sub(1);
+        let to = ((num + 1) * chunk_len).min(traj.points.len());
+        let mut path = BezPath::new();
+        for (idx, &(_, x, y)) in traj.points[from..to].iter().enumerate() {
+            if idx == 0 { path.move_to(to_plot(x, y)); } else { path.line_to(to_plot(x, y)); }
+        }
+        let colour = Color::rgba8(120, 255, 120, (255 * (num + 1) / count) as u8);
+        sb.stroke(&Stroke::new(1.0), Affine::IDENTITY, &Brush::Solid(colour), None, &path);
+    }
+    if let Some((x, y)) = cursor.and_then(|t| traj.point_at(t)) {
+        sb.fill(Fill::NonZero, Affine::IDENTITY, &Brush::Solid(Color::YELLOW), None, &Circle::new(to_plot(x, y), 4.));
+    }
+}
@@ -0,0 +1,222 @@
+use std::io::{Error, ErrorKind};
+use crate::{time_ratio, ChangeIndex, Checkpoint, FieldInfo, FieldType, IndexedWave, Result, WaveFile, WaveInput};
+
+/// Comma (or semicolon/tab) separated values reader. The first column is the timestamp,
+/// its unit is taken from the header (e.g. `time_ns`, `Time (us)`), defaulting to seconds.
+/// Column types are guessed from the first data row: 0/1 are digital, other integers a
+/// bus, anything else analog. Without a data row, all columns are analog.
+#[derive(Debug)]
+pub struct CsvFile {
+    input: WaveInput,
+    buf: Vec<u8>,
+    sep: char,
+    header: Option<Vec<String>>,
+    fields: Vec<FieldInfo>,
+    tick: f64,      // Seconds per timestamp unit
+    time_unit: f64, // Seconds per output time unit
+    rows: usize,
+    index: ChangeIndex,
+    /// State before parsing the unterminated row at the end of the input, undone when
+    /// more input arrives
+    partial: Option<(usize, Checkpoint)>,
+}
+
+impl CsvFile {
+    pub fn new(input: WaveInput) -> Self {
+        Self {
+            input,
+            buf: Vec::new(),
+            sep: ',',
+            header: None,
+            fields: Vec::new(),
+            tick: 1.,
+            time_unit: 1e-12,
+            rows: 0,
+            index: ChangeIndex::new(),
+            partial: None,
+        }
+    }
+
+    /// Set output time unit (in seconds), must be called before `check_format`
+    pub fn set_time_unit(&mut self, secs: f64) {
+        self.time_unit = secs;
+    }
+
+    /// Read next chunk of input and parse all complete lines, return bytes read.
+    /// Set `wait` to block until stream data arrives.
+    fn read_and_parse(&mut self, wait: bool) -> Result<usize> {
+        let n = self.input.read_chunk(&mut self.buf, wait)?;
+        if n > 0 {
+            self.undo_partial();
+        }
+        if let Some(pos) = self.buf.iter().rposition(|&b| b == b'\n') {
+            let chunk : Vec<u8> = self.buf.drain(..=pos).collect();
+            let text = String::from_utf8_lossy(&chunk);
+            for line in text.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') { continue; }
+                self.parse_line(line)?;
+            }
+        }
+        Ok(n)
+    }
+
+    /// At the end of the input, parse the row it ends with, which may be the last one or
+    /// be continued by data appended later. Return true if it was parsed.
+    fn parse_partial(&mut self) -> bool {
+        if self.fields.is_empty() || !self.input.at_end() || self.partial.is_some() {
+            return false;
+        }
+        let text = String::from_utf8_lossy(&self.buf).into_owned();
+        let line = text.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return false;
+        }
+        self.partial = Some((self.rows, self.index.checkpoint()));
+        if self.parse_line(line).is_err() {
+            // Incomplete, e.g. a number cut short at "1e"
+            self.undo_partial();
+            return false;
+        }
+        true
+    }
+
+    fn undo_partial(&mut self) {
+        if let Some((rows, cp)) = self.partial.take() {
+            self.rows = rows;
+            self.index.restore(cp);
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
+        if self.header.is_none() {
+            self.sep = [',', ';', '\t'].into_iter().find(|&c| line.contains(c)).unwrap_or(',');
+            let names : Vec<String> = line.split(self.sep).map(|s| s.trim().trim_matches('"').to_string()).collect();
+            self.tick = parse_time_unit(&names[0]);
+            self.header = Some(names);
+            return Ok(());
+        }
+        let cells : Vec<&str> = line.split(self.sep).map(|s| s.trim()).collect();
+        if self.fields.is_empty() {
+            self.add_fields(&cells);
+        }
+        let ticks = parse_number(cells[0]).ok_or_else(|| invalid(line))?;
+        let t = ticks * time_ratio(self.tick, self.time_unit);
+        self.index.extend_range(t);
+        for (field, cell) in cells.iter().enumerate().skip(1).take(self.fields.len() - 1) {
+            let val = parse_number(cell).ok_or_else(|| invalid(line))?;
+            self.index.push(field, t, val);
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Create fields from header names, using first data row to pick field types
+    fn add_fields(&mut self, cells: &[&str]) {
+        let names = self.header.as_ref().unwrap();
+        for (col, name) in names.iter().enumerate() {
+            let ftype = match cells.get(col) {
+                _ if col == 0 => FieldType::Timestamp,
+                Some(&"0") | Some(&"1") => FieldType::Digital,
+                Some(cell) if cell.starts_with("0x") || cell.parse::<i64>().is_ok() => FieldType::DigiBus(64),
+                _ => FieldType::Analog,
+            };
+            self.index.add_field();
+            self.fields.push(FieldInfo { name: name.clone(), ftype });
+        }
+    }
+}
+
+fn invalid(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Unexpected CSV row '{line}'"))
+}
+
+fn parse_number(cell: &str) -> Option<f64> {
+    match cell.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|v| v as f64),
+        None => cell.parse().ok(),
+    }
+}
+
+/// Seconds per timestamp unit from time column name, e.g. `time_ns` or `Time (us)`
+fn parse_time_unit(name: &str) -> f64 {
+    let unit : String = name.to_ascii_lowercase()
+        .trim_start_matches("time")
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .collect();
+    match unit.as_str() {
+        "ms" => 1e-3,
+        "us" => 1e-6,
+        "ns" => 1e-9,
+        "ps" => 1e-12,
+        "fs" => 1e-15,
+        _ => 1.,
+    }
+}
+
+impl WaveFile for CsvFile {
+    /// Read the header and first data row
+    fn check_format(&mut self) -> Result<bool> {
+        while self.fields.is_empty() {
+            if self.read_and_parse(true)? == 0 { break; }
+        }
+        if self.fields.is_empty() && self.header.is_some() {
+            // Header only, rows may still be appended
+            self.add_fields(&[]);
+        }
+        Ok(!self.fields.is_empty())
+    }
+
+    fn get_record_size(&self) -> Option<usize> { None }
+
+    fn get_range(&self) -> (f64, f64) {
+        self.index.get_range()
+    }
+
+    fn get_timescale(&self) -> Option<f64> { Some(self.tick) }
+
+    fn get_num_fields(&self) -> usize {
+        self.fields.len()
+    }
+
+    fn get_field_info(&self, field: usize) -> &FieldInfo {
+        &self.fields[field]
+    }
+
+    fn get_num_rows(&self) -> Option<usize> { Some(self.rows) }
+
+    fn prepare_iter_range(&mut self, range: &[f64; 2]) -> Result<[usize; 2]> {
+        Ok(self.index.prepare_iter_range(range))
+    }
+
+    fn poll(&mut self) -> Result<bool> {
+        let mut added = false;
+        while self.read_and_parse(false)? > 0 {
+            added = true;
+        }
+        added |= self.parse_partial();
+        Ok(added)
+    }
+
+    fn read_to_end(&mut self) -> Result<()> {
+        self.undo_partial();
+        while self.read_and_parse(true)? > 0 {}
+        if !self.buf.is_empty() {
+            // Terminate last line
+            self.buf.push(b'\n');
+            self.read_and_parse(false)?;
+        }
+        Ok(())
+    }
+}
+
+impl IndexedWave for CsvFile {
+    fn get_index(&self) -> &ChangeIndex {
+        &self.index
+    }
+
+    fn get_index_mut(&mut self) -> &mut ChangeIndex {
+        &mut self.index
+    }
+}
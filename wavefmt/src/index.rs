@@ -0,0 +1,140 @@
+/// In-memory list of value changes for each field, built up as records are read
+#[derive(Debug, Default)]
+pub struct ChangeIndex {
+    changes: Vec<Vec<(f64, f64)>>,
+    limits: Vec<(f64, f64)>,
+    range: Option<(f64, f64)>,
+    /// Start time and position of the iteration of each field
+    iter_starts: Vec<f64>,
+    cursors: Vec<Option<usize>>,
+}
+
+/// Saved state of a `ChangeIndex`, to undo changes recorded after it
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    lens: Vec<usize>,
+    limits: Vec<(f64, f64)>,
+    range: Option<(f64, f64)>,
+}
+
+impl ChangeIndex {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add storage for a new field, return its index
+    pub fn add_field(&mut self) -> usize {
+        self.changes.push(Vec::new());
+        self.limits.push((f64::MAX, f64::MIN));
+        self.iter_starts.push(0.);
+        self.cursors.push(None);
+        self.changes.len() - 1
+    }
+
+    pub fn get_num_fields(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Record value `val` of `field` at time `t`, repeats of the current value are dropped.
+    /// Return true if a change was recorded
+    pub fn push(&mut self, field: usize, t: f64, val: f64) -> bool {
+        self.extend_range(t);
+        let changes = &mut self.changes[field];
+        if let Some(&(_, last)) = changes.last() {
+            if last == val { return false; }
+        }
+        changes.push((t, val));
+        let limits = &mut self.limits[field];
+        if val < limits.0 { limits.0 = val; }
+        if val > limits.1 { limits.1 = val; }
+        true
+    }
+
+    /// Save the recorded changes and range, see `restore`
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            lens: self.changes.iter().map(|c| c.len()).collect(),
+            limits: self.limits.clone(),
+            range: self.range,
+        }
+    }
+
+    /// Drop changes recorded since `checkpoint`, for fields that existed then
+    pub fn restore(&mut self, cp: Checkpoint) {
+        for (changes, len) in self.changes.iter_mut().zip(cp.lens) {
+            changes.truncate(len);
+        }
+        for (limits, saved) in self.limits.iter_mut().zip(cp.limits) {
+            *limits = saved;
+        }
+        self.range = cp.range;
+    }
+
+    /// Move end of time range to `t` (time advances even without value changes)
+    pub fn extend_range(&mut self, t: f64) {
+        let range = self.range.get_or_insert((t, t));
+        if t < range.0 { range.0 = t; }
+        if t > range.1 { range.1 = t; }
+    }
+
+    /// Return time range covered by all recorded changes
+    pub fn get_range(&self) -> (f64, f64) {
+        self.range.unwrap_or((0., 0.))
+    }
+
+    pub fn get_changes(&self, field: usize) -> &[(f64, f64)] {
+        &self.changes[field]
+    }
+
+    /// Return (min, max) value seen for `field`
+    pub fn get_limits(&self, field: usize) -> (f64, f64) {
+        let (min, max) = self.limits[field];
+        if min > max { (0., 0.) } else { (min, max) }
+    }
+
+    /// Index of first change of `field` after time `t`
+    pub fn first_after(&self, field: usize, t: f64) -> usize {
+        self.changes[field].partition_point(|&(ct, _)| ct <= t)
+    }
+
+    /// Value of `field` at time `t`, the initial value is used before the first change
+    pub fn value_at(&self, field: usize, t: f64) -> f64 {
+        let changes = &self.changes[field];
+        match self.first_after(field, t) {
+            0 => changes.first().map(|c| c.1).unwrap_or(0.),
+            idx => changes[idx - 1].1,
+        }
+    }
+
+    /// Reset iteration of all fields to start after `range[0]`, return (start, end) change
+    /// numbers (counted across all fields) for this time range
+    pub fn prepare_iter_range(&mut self, range: &[f64; 2]) -> [usize; 2] {
+        for field in 0..self.changes.len() {
+            self.prepare_field_iter(field, range);
+        }
+        let mut bounds = [0, 0];
+        for field in 0..self.changes.len() {
+            bounds[0] += self.first_after(field, range[0]);
+            bounds[1] += self.first_after(field, range[1]);
+        }
+        bounds
+    }
+
+    /// Reset iteration of `field` alone to start after `range[0]`, leaving other fields
+    /// where they are
+    pub fn prepare_field_iter(&mut self, field: usize, range: &[f64; 2]) {
+        self.iter_starts[field] = range[0];
+        self.cursors[field] = None;
+    }
+
+    /// Return next change of `field` since `prepare_iter_range` or `prepare_field_iter`
+    pub fn next_change(&mut self, field: usize) -> Option<(f64, f64)> {
+        let pos = match self.cursors[field] {
+            Some(pos) => pos,
+            None => self.first_after(field, self.iter_starts[field]),
+        };
+        let change = self.changes[field].get(pos).copied();
+        self.cursors[field] = Some(pos + 1);
+        change
+    }
+}
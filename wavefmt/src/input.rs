@@ -0,0 +1,118 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+/// Maximum bytes returned by a single `read_chunk` call
+const CHUNK_SIZE : usize = 1 << 20;
+
+#[derive(Debug)]
+enum Source {
+    File(File),
+    /// Chunks of a stream (e.g. stdin) read on another thread
+    Stream(Receiver<Vec<u8>>),
+}
+
+/// Byte source for wave readers. Reads never seek, so a file that is still being written
+/// returns newly appended data on later reads.
+#[derive(Debug)]
+pub struct WaveInput {
+    source: Source,
+    prefix: Vec<u8>,
+    /// Last read found no more data in a file, or the stream was closed
+    at_end: bool,
+}
+
+impl WaveInput {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            source: Source::File(File::open(path)?),
+            prefix: Vec::new(),
+            at_end: false,
+        })
+    }
+
+    /// Read from standard input
+    pub fn stdin() -> Self {
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin().lock();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                match stdin.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if tx.send(buf[..n].to_vec()).is_err() { break; }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        });
+        Self::from_channel(rx)
+    }
+
+    /// Read chunks sent from another thread, input ends when the sender is dropped
+    pub fn from_channel(rx: Receiver<Vec<u8>>) -> Self {
+        Self {
+            source: Source::Stream(rx),
+            prefix: Vec::new(),
+            at_end: false,
+        }
+    }
+
+    /// Return up to `len` bytes from the start of the input without consuming them,
+    /// waiting for stream data to arrive
+    pub fn peek(&mut self, len: usize) -> io::Result<&[u8]> {
+        let mut prefix = std::mem::take(&mut self.prefix);
+        while prefix.len() < len {
+            if self.fill(&mut prefix, true)? == 0 { break; }
+        }
+        self.prefix = prefix;
+        Ok(&self.prefix[..len.min(self.prefix.len())])
+    }
+
+    /// Append the next chunk of available data to `buf`, return number of bytes added
+    /// (0 when no more data is available yet). Set `wait` to block until stream data
+    /// arrives.
+    pub fn read_chunk(&mut self, buf: &mut Vec<u8>, wait: bool) -> io::Result<usize> {
+        if !self.prefix.is_empty() {
+            let n = self.prefix.len();
+            buf.append(&mut self.prefix);
+            return Ok(n);
+        }
+        self.fill(buf, wait)
+    }
+
+    /// True when the last read reached the end of a file (which may grow later), or the
+    /// stream was closed
+    pub fn at_end(&self) -> bool {
+        self.at_end
+    }
+
+    fn fill(&mut self, buf: &mut Vec<u8>, wait: bool) -> io::Result<usize> {
+        match &mut self.source {
+            Source::File(file) => {
+                let start = buf.len();
+                buf.resize(start + CHUNK_SIZE, 0);
+                let res = file.read(&mut buf[start..]);
+                let n = *res.as_ref().unwrap_or(&0);
+                buf.truncate(start + n);
+                self.at_end = matches!(res, Ok(0));
+                res
+            }
+            Source::Stream(rx) => {
+                let data = if wait {
+                    rx.recv().map_err(|_| TryRecvError::Disconnected)
+                } else {
+                    rx.try_recv()
+                };
+                self.at_end = data == Err(TryRecvError::Disconnected);
+                let n = data.as_ref().map(|d| d.len()).unwrap_or(0);
+                buf.extend(data.unwrap_or_default());
+                Ok(n)
+            }
+        }
+    }
+}
@@ -2,6 +2,16 @@ use std::error;
 
 #[cfg(test)]
 mod tests;
+mod index;
+pub use index::{ChangeIndex, Checkpoint};
+mod input;
+pub use input::WaveInput;
+mod vcd;
+pub use vcd::{VcdFile, VcdWriter};
+mod csv;
+pub use csv::CsvFile;
+pub mod stream;
+pub mod wavedrom;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
@@ -23,7 +33,38 @@ pub enum NumType {
     Integer,
 }
 
-#[derive(Debug)]
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Vcd,
+    Csv,
+    WaveDrom,
+}
+
+/// Guess wave format from the first bytes of a file or stream
+pub fn detect_format(prefix: &[u8]) -> Option<Format> {
+    let text = String::from_utf8_lossy(prefix);
+    let line = text.lines().map(|l| l.trim()).find(|l| !l.is_empty())?;
+    if line.starts_with('$') {
+        Some(Format::Vcd)
+    } else if line.starts_with('{') {
+        Some(Format::WaveDrom)
+    } else if line.contains([',', ';', '\t']) {
+        Some(Format::Csv)
+    } else {
+        None
+    }
+}
+
+/// Multiplier to convert a time in `tick` seconds to `unit` seconds, rounded when the
+/// units are an integer multiple of each other
+pub fn time_ratio(tick: f64, unit: f64) -> f64 {
+    let ratio = tick / unit;
+    let rounded = ratio.round();
+    if ratio >= 1. && (ratio - rounded).abs() < 1e-6 * ratio { rounded } else { ratio }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct FieldInfo {
     pub name: String,
     pub ftype: FieldType,
@@ -39,6 +80,9 @@ pub trait WaveFile {
     /// Return time range covered by this waveform
     fn get_range(&self) -> (f64, f64);
 
+    /// Return time resolution (in seconds) of the source file, if it has one
+    fn get_timescale(&self) -> Option<f64> { None }
+
     /// Return number of fields, requires check_format is called first to read file header
     fn get_num_fields(&self) -> usize;
 
@@ -51,5 +95,17 @@ pub trait WaveFile {
     /// Prepare to display a range of waveform data, return (start, end) record number for this
     /// time range
     fn prepare_iter_range(&mut self, range: &[f64; 2]) -> Result<[usize; 2]>;
+
+    /// Read any data appended since the last call, return true if there was new data
+    fn poll(&mut self) -> Result<bool> { Ok(false) }
+
+    /// Read all remaining input, waiting for streams to be closed
+    fn read_to_end(&mut self) -> Result<()> { self.poll().map(|_| ()) }
 }
 
+/// Wave file that keeps its value changes in memory, in a `ChangeIndex`
+pub trait IndexedWave: WaveFile {
+    fn get_index(&self) -> &ChangeIndex;
+
+    fn get_index_mut(&mut self) -> &mut ChangeIndex;
+}
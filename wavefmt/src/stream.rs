@@ -0,0 +1,133 @@
+//! Framed protocol for streaming live samples, all values little endian:
+//!
+//! ```text
+//! frame   = len:u32 kind:u8 payload        (len counts payload bytes only)
+//! declare = kind 1: timescale:f64 count:u16 { type:u8 name_len:u16 name:utf8 }*
+//! batch   = kind 2: time:f64 count:u16 { sig:u16 value:f32 }*
+//! ```
+//!
+//! A declaration gives the seconds per time unit and adds signals (type 0 digital,
+//! 1 analog), numbered in order of declaration. Value batches set signals at a time.
+use std::io::{self, Error, ErrorKind, Read, Write};
+use crate::{FieldInfo, FieldType};
+
+const KIND_DECLARE : u8 = 1;
+const KIND_BATCH : u8 = 2;
+
+/// Largest accepted frame payload
+const MAX_FRAME : usize = 16 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamMsg {
+    Declare { timescale: f64, fields: Vec<FieldInfo> },
+    Batch { time: f64, values: Vec<(u16, f32)> },
+}
+
+/// Read next message, return None at end of stream
+pub fn read_msg(r: &mut impl Read) -> io::Result<Option<StreamMsg>> {
+    let mut hdr = [0u8; 5];
+    match r.read_exact(&mut hdr) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]) as usize;
+    if len > MAX_FRAME {
+        return Err(invalid("frame too large"));
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    let mut p = Payload { data: &payload };
+    let msg = match hdr[4] {
+        KIND_DECLARE => {
+            let timescale = p.f64()?;
+            let count = p.u16()?;
+            let mut fields = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let ftype = match p.u8()? {
+                    0 => FieldType::Digital,
+                    1 => FieldType::Analog,
+                    _ => return Err(invalid("unknown signal type")),
+                };
+                let name_len = p.u16()? as usize;
+                let name = String::from_utf8_lossy(p.take(name_len)?).into_owned();
+                fields.push(FieldInfo { name, ftype });
+            }
+            StreamMsg::Declare { timescale, fields }
+        }
+        KIND_BATCH => {
+            let time = p.f64()?;
+            let count = p.u16()?;
+            let mut values = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                values.push((p.u16()?, p.f32()?));
+            }
+            StreamMsg::Batch { time, values }
+        }
+        _ => return Err(invalid("unknown frame kind")),
+    };
+    Ok(Some(msg))
+}
+
+pub fn write_msg(w: &mut impl Write, msg: &StreamMsg) -> io::Result<()> {
+    let mut payload : Vec<u8> = Vec::new();
+    let kind = match msg {
+        StreamMsg::Declare { timescale, fields } => {
+            payload.extend_from_slice(&timescale.to_le_bytes());
+            payload.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+            for fi in fields {
+                payload.push(if fi.ftype == FieldType::Digital { 0 } else { 1 });
+                payload.extend_from_slice(&(fi.name.len() as u16).to_le_bytes());
+                payload.extend_from_slice(fi.name.as_bytes());
+            }
+            KIND_DECLARE
+        }
+        StreamMsg::Batch { time, values } => {
+            payload.extend_from_slice(&time.to_le_bytes());
+            payload.extend_from_slice(&(values.len() as u16).to_le_bytes());
+            for (sig, val) in values {
+                payload.extend_from_slice(&sig.to_le_bytes());
+                payload.extend_from_slice(&val.to_le_bytes());
+            }
+            KIND_BATCH
+        }
+    };
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(&[kind])?;
+    w.write_all(&payload)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Stream: {msg}"))
+}
+
+struct Payload<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Payload<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(invalid("truncated frame"));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
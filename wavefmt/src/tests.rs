@@ -0,0 +1,266 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::*;
+
+const VCD_HEADER : &str = "$timescale 1ns $end
+$scope module top $end
+$var wire 1 ! clk $end
+$var wire 8 # data [7:0] $end
+$var real 64 $ vout $end
+$upscope $end
+$enddefinitions $end
+";
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("wavefmt_{}_{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn open_vcd(path: &Path) -> VcdFile {
+    let mut vcd = VcdFile::new(WaveInput::open(path).unwrap());
+    vcd.set_time_unit(1e-12);
+    assert!(vcd.check_format().unwrap());
+    vcd
+}
+
+#[test]
+fn detect() {
+    assert_eq!(detect_format(b"\n$date today $end"), Some(Format::Vcd));
+    assert_eq!(detect_format(b"time,a,b\n0,1,2\n"), Some(Format::Csv));
+    assert_eq!(detect_format(b"{ signal: [] }"), Some(Format::WaveDrom));
+    assert_eq!(detect_format(b"hello"), None);
+}
+
+#[test]
+fn vcd_read() {
+    let path = temp_file("read.vcd", &format!("{VCD_HEADER}#0\n$dumpvars 0! b101 # r1.5 $ $end\n#5\n1!\n#10\n0!\nb11 #\n#15\n"));
+    let mut vcd = open_vcd(&path);
+    vcd.poll().unwrap();
+    assert_eq!(vcd.get_num_fields(), 3);
+    assert_eq!(vcd.get_field_info(0).name, "top.clk");
+    assert_eq!(vcd.get_field_info(1).ftype, FieldType::DigiBus(8));
+    assert_eq!(vcd.get_field_info(2).ftype, FieldType::Analog);
+    assert_eq!(vcd.get_range(), (0., 15000.));
+    let index = vcd.get_index();
+    assert_eq!(index.get_changes(0), &[(0., 0.), (5000., 1.), (10000., 0.)]);
+    assert_eq!(index.value_at(1, 9999.), 5.);
+    assert_eq!(index.value_at(1, 10000.), 3.);
+    assert_eq!(index.value_at(2, 12000.), 1.5);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn vcd_follow() {
+    let path = temp_file("follow.vcd", &format!("{VCD_HEADER}#0\n0!\n#10\n1"));
+    let mut vcd = open_vcd(&path);
+    vcd.poll().unwrap();
+    // Partial token "1" is held back until complete
+    assert_eq!(vcd.get_index().get_changes(0).len(), 1);
+    assert!(!vcd.poll().unwrap());
+
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"!\n#20\n0!\n#30\n").unwrap();
+    assert!(vcd.poll().unwrap());
+    assert_eq!(vcd.get_index().get_changes(0), &[(0., 0.), (10000., 1.), (20000., 0.)]);
+    assert_eq!(vcd.get_range(), (0., 30000.));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn vcd_follow_last_token() {
+    let path = temp_file("follow_last.vcd", &format!("{VCD_HEADER}#0\n0!\n#10\n1!"));
+    let mut vcd = open_vcd(&path);
+    // Last token is complete at the end of the file
+    assert!(vcd.poll().unwrap());
+    assert_eq!(vcd.get_index().get_changes(0), &[(0., 0.), (10000., 1.)]);
+
+    // ...or continued by appended data
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"\n#12").unwrap();
+    assert!(vcd.poll().unwrap());
+    assert_eq!(vcd.get_range(), (0., 12000.));
+    file.write_all(b"5\n0!\n").unwrap();
+    assert!(vcd.poll().unwrap());
+    assert_eq!(vcd.get_index().get_changes(0), &[(0., 0.), (10000., 1.), (125000., 0.)]);
+    assert_eq!(vcd.get_range(), (0., 125000.));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn csv_read() {
+    let path = temp_file("read.csv", "time_us,en,count,vout\n0,0,4,0.5\n1,1,4,0.75\n2,1,5,1.0\n");
+    let mut csv = CsvFile::new(WaveInput::open(&path).unwrap());
+    csv.set_time_unit(1e-9);
+    assert!(csv.check_format().unwrap());
+    csv.poll().unwrap();
+    assert_eq!(csv.get_num_fields(), 4);
+    assert_eq!(csv.get_num_rows(), Some(3));
+    assert_eq!(csv.get_field_info(0).ftype, FieldType::Timestamp);
+    assert_eq!(csv.get_field_info(1).ftype, FieldType::Digital);
+    assert_eq!(csv.get_field_info(2).ftype, FieldType::DigiBus(64));
+    assert_eq!(csv.get_field_info(3).ftype, FieldType::Analog);
+    assert_eq!(csv.get_range(), (0., 2000.));
+    assert_eq!(csv.get_index().get_changes(2), &[(0., 4.), (2000., 5.)]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn index_iter() {
+    let mut index = ChangeIndex::new();
+    index.add_field();
+    for (t, v) in [(0., 0.), (10., 1.), (20., 0.), (30., 1.)] {
+        index.push(0, t, v);
+    }
+    assert_eq!(index.prepare_iter_range(&[10., 25.]), [2, 3]);
+    assert_eq!(index.next_change(0), Some((20., 0.)));
+    assert_eq!(index.next_change(0), Some((30., 1.)));
+    assert_eq!(index.next_change(0), None);
+}
+
+#[test]
+fn index_field_iters() {
+    let mut index = ChangeIndex::new();
+    index.add_field();
+    index.add_field();
+    for (t, v) in [(0., 0.), (10., 1.), (20., 0.)] {
+        index.push(0, t, v);
+        index.push(1, t, 5. - v);
+    }
+    index.prepare_field_iter(0, &[0., 30.]);
+    assert_eq!(index.next_change(0), Some((10., 1.)));
+    // Starting another field leaves the first where it was
+    index.prepare_field_iter(1, &[5., 30.]);
+    assert_eq!(index.next_change(1), Some((10., 4.)));
+    assert_eq!(index.next_change(0), Some((20., 0.)));
+    assert_eq!(index.next_change(1), Some((20., 5.)));
+}
+
+#[test]
+fn stream_roundtrip() {
+    use stream::{read_msg, write_msg, StreamMsg};
+    let msgs = [
+        StreamMsg::Declare {
+            timescale: 1e-9,
+            fields: vec![
+                FieldInfo { name: "clk".to_string(), ftype: FieldType::Digital },
+                FieldInfo { name: "vout".to_string(), ftype: FieldType::Analog },
+            ],
+        },
+        StreamMsg::Batch { time: 10., values: vec![(0, 1.), (1, 0.25)] },
+    ];
+    let mut buf : Vec<u8> = Vec::new();
+    for msg in msgs.iter() {
+        write_msg(&mut buf, msg).unwrap();
+    }
+    let mut rd = buf.as_slice();
+    assert_eq!(read_msg(&mut rd).unwrap().as_ref(), Some(&msgs[0]));
+    assert_eq!(read_msg(&mut rd).unwrap().as_ref(), Some(&msgs[1]));
+    assert_eq!(read_msg(&mut rd).unwrap(), None);
+}
+
+#[test]
+fn vcd_stream() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let data = format!("{VCD_HEADER}#0\n0!\n#10\n1!\n#20\n");
+    // Split inside tokens to check partial tokens are held back
+    for chunk in data.as_bytes().chunks(7) {
+        tx.send(chunk.to_vec()).unwrap();
+    }
+    let mut input = WaveInput::from_channel(rx);
+    assert_eq!(detect_format(input.peek(64).unwrap()), Some(Format::Vcd));
+    let mut vcd = VcdFile::new(input);
+    assert!(vcd.check_format().unwrap());
+    vcd.poll().unwrap();
+    assert_eq!(vcd.get_index().get_changes(0), &[(0., 0.), (10000., 1.)]);
+    tx.send(b"0!\n#30\n".to_vec()).unwrap();
+    assert!(vcd.poll().unwrap());
+    assert_eq!(vcd.get_index().get_changes(0).len(), 3);
+    assert_eq!(vcd.get_range(), (0., 30000.));
+}
+
+#[test]
+fn vcd_write() {
+    let fields = [
+        FieldInfo { name: "top.cpu.clk".to_string(), ftype: FieldType::Digital },
+        FieldInfo { name: "top.vout".to_string(), ftype: FieldType::Analog },
+        FieldInfo { name: "top.cpu.data".to_string(), ftype: FieldType::DigiBus(8) },
+    ];
+    let mut buf : Vec<u8> = Vec::new();
+    let mut vcd = VcdWriter::new(&mut buf);
+    assert_eq!(vcd.write_header(2e-9, &fields).unwrap(), 1e-9);
+    vcd.write_initial(100, &[0., 0.5, 3.]).unwrap();
+    vcd.write_change(105, 0, 1.).unwrap();
+    vcd.write_change(105, 2, 200.).unwrap();
+    vcd.finish(110).unwrap();
+    drop(vcd);
+
+    let path = temp_file("write.vcd", &String::from_utf8(buf).unwrap());
+    let mut rd = open_vcd(&path);
+    rd.poll().unwrap();
+    let names : Vec<&str> = (0..3).map(|f| rd.get_field_info(f).name.as_str()).collect();
+    assert_eq!(names, ["top.vout", "top.cpu.clk", "top.cpu.data"]);
+    assert_eq!(rd.get_field_info(2).ftype, FieldType::DigiBus(8));
+    assert_eq!(rd.get_timescale(), Some(1e-9));
+    assert_eq!(rd.get_range(), (100000., 110000.));
+    assert_eq!(rd.get_index().get_changes(1), &[(100000., 0.), (105000., 1.)]);
+    assert_eq!(rd.get_index().get_changes(2), &[(100000., 3.), (105000., 200.)]);
+    assert_eq!(rd.get_index().value_at(0, 105000.), 0.5);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn csv_unterminated() {
+    let path = temp_file("unterm.csv", "time_ns,en\n0,0\n10,1");
+    let mut csv = CsvFile::new(WaveInput::open(&path).unwrap());
+    csv.set_time_unit(1e-12);
+    assert!(csv.check_format().unwrap());
+    csv.read_to_end().unwrap();
+    assert_eq!(csv.get_num_rows(), Some(2));
+    assert_eq!(csv.get_index().get_changes(1), &[(0., 0.), (10000., 1.)]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn csv_follow() {
+    let path = temp_file("follow.csv", "time_ns,vout\n");
+    let mut csv = CsvFile::new(WaveInput::open(&path).unwrap());
+    csv.set_time_unit(1e-12);
+    // Header only, columns default to analog
+    assert!(csv.check_format().unwrap());
+    assert_eq!(csv.get_field_info(1).ftype, FieldType::Analog);
+    assert!(!csv.poll().unwrap());
+
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(b"0,0.5\n10,0.2").unwrap();
+    assert!(csv.poll().unwrap());
+    assert_eq!(csv.get_num_rows(), Some(2));
+    assert_eq!(csv.get_index().get_changes(1), &[(0., 0.5), (10000., 0.2)]);
+    file.write_all(b"5\n").unwrap();
+    assert!(csv.poll().unwrap());
+    assert_eq!(csv.get_num_rows(), Some(2));
+    assert_eq!(csv.get_index().get_changes(1), &[(0., 0.5), (10000., 0.25)]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn wavedrom_read() {
+    use wavedrom::*;
+    let text = "{ signal: [
+        { name: 'clk', wave: 'p..' },
+        {},
+        ['bus', { name: \"data\", wave: 'x=.=', data: ['0x10', 'idle'] }, // comment
+          { name: 'en', wave: '01.0', phase: 0.5 },],
+    ]}";
+    let lanes = read_wavejson(text).unwrap();
+    let names : Vec<&str> = lanes.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["clk", "bus.data", "bus.en"]);
+    assert_eq!(lanes[0].changes(), (vec![(0., 1.), (0.5, 0.), (1., 1.), (1.5, 0.), (2., 1.), (2.5, 0.)], 3.));
+    assert!(lanes[1].is_bus());
+    assert_eq!(lanes[1].changes(), (vec![(0., 0.), (1., 16.), (3., 1.)], 4.));
+    assert_eq!(lanes[2].changes(), (vec![(-0.5, 0.), (0.5, 1.), (2.5, 0.)], 3.5));
+
+    let mut buf : Vec<u8> = Vec::new();
+    write_wavejson(&mut buf, &lanes).unwrap();
+    assert_eq!(read_wavejson(&String::from_utf8(buf).unwrap()).unwrap(), lanes);
+}
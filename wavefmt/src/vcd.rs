@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::io::{self, Error, ErrorKind, Write};
+use crate::{time_ratio, ChangeIndex, Checkpoint, FieldInfo, FieldType, IndexedWave, Result, WaveFile, WaveInput};
+
+#[derive(Debug, Clone)]
+enum Pending {
+    None,
+    /// Skip tokens up to `$end`
+    Skip,
+    Timescale(String),
+    Scope(Vec<String>),
+    Var(Vec<String>),
+    /// Vector or real value waiting for its identifier code
+    Value(f64),
+}
+
+/// Value Change Dump (IEEE 1364) reader, parses the input incrementally into a `ChangeIndex`
+#[derive(Debug)]
+pub struct VcdFile {
+    input: WaveInput,
+    buf: Vec<u8>,
+    pending: Pending,
+    header_done: bool,
+    fields: Vec<FieldInfo>,
+    ids: HashMap<String, Vec<usize>>,
+    scope: Vec<String>,
+    tick: f64,      // Seconds per VCD time step
+    time_unit: f64, // Seconds per output time unit
+    time: f64,      // Current time (output units)
+    index: ChangeIndex,
+    /// State before parsing the unterminated token at the end of the input, undone when
+    /// more input arrives
+    partial: Option<(Pending, f64, Checkpoint)>,
+}
+
+impl VcdFile {
+    pub fn new(input: WaveInput) -> Self {
+        Self {
+            input,
+            buf: Vec::new(),
+            pending: Pending::None,
+            header_done: false,
+            fields: Vec::new(),
+            ids: HashMap::new(),
+            scope: Vec::new(),
+            tick: 1e-12,
+            time_unit: 1e-12,
+            time: 0.,
+            index: ChangeIndex::new(),
+            partial: None,
+        }
+    }
+
+    /// Set output time unit (in seconds), must be called before `check_format`
+    pub fn set_time_unit(&mut self, secs: f64) {
+        self.time_unit = secs;
+    }
+
+    /// Read next chunk of input and parse all complete tokens, return bytes read.
+    /// Set `wait` to block until stream data arrives.
+    fn read_and_parse(&mut self, wait: bool) -> Result<usize> {
+        let n = self.input.read_chunk(&mut self.buf, wait)?;
+        if n > 0 {
+            self.undo_partial();
+        }
+        // A token is only complete once followed by whitespace
+        if let Some(pos) = self.buf.iter().rposition(|b| b.is_ascii_whitespace()) {
+            let chunk : Vec<u8> = self.buf.drain(..=pos).collect();
+            let text = String::from_utf8_lossy(&chunk);
+            for tok in text.split_ascii_whitespace() {
+                self.parse_token(tok)?;
+            }
+        }
+        Ok(n)
+    }
+
+    /// At the end of the input, parse the token it ends with, which may be the last one or
+    /// be continued by data appended later. Return true if it was parsed.
+    fn parse_partial(&mut self) -> bool {
+        if !self.header_done || !self.input.at_end() || self.buf.is_empty() || self.partial.is_some() {
+            return false;
+        }
+        self.partial = Some((self.pending.clone(), self.time, self.index.checkpoint()));
+        let text = String::from_utf8_lossy(&self.buf).into_owned();
+        if text.split_ascii_whitespace().try_for_each(|tok| self.parse_token(tok)).is_err() {
+            // Incomplete, e.g. an identifier code cut short
+            self.undo_partial();
+            return false;
+        }
+        true
+    }
+
+    fn undo_partial(&mut self) {
+        if let Some((pending, time, cp)) = self.partial.take() {
+            self.pending = pending;
+            self.time = time;
+            self.index.restore(cp);
+        }
+    }
+
+    fn parse_token(&mut self, tok: &str) -> Result<()> {
+        match std::mem::replace(&mut self.pending, Pending::None) {
+            Pending::None => self.parse_command(tok)?,
+            Pending::Skip => {
+                if tok != "$end" { self.pending = Pending::Skip; }
+            }
+            Pending::Timescale(mut ts) => {
+                if tok == "$end" {
+                    self.tick = parse_timescale(&ts)?;
+                } else {
+                    ts.push_str(tok);
+                    self.pending = Pending::Timescale(ts);
+                }
+            }
+            Pending::Scope(mut toks) => {
+                if tok == "$end" {
+                    // $scope <type> <name> $end
+                    let name = toks.pop().unwrap_or_default();
+                    self.scope.push(name);
+                } else {
+                    toks.push(tok.to_string());
+                    self.pending = Pending::Scope(toks);
+                }
+            }
+            Pending::Var(mut toks) => {
+                if tok == "$end" {
+                    self.add_var(&toks)?;
+                } else {
+                    toks.push(tok.to_string());
+                    self.pending = Pending::Var(toks);
+                }
+            }
+            Pending::Value(val) => self.set_value(tok, val)?,
+        }
+        Ok(())
+    }
+
+    fn parse_command(&mut self, tok: &str) -> Result<()> {
+        match tok.as_bytes()[0] {
+            b'$' => {
+                self.pending = match tok {
+                    "$timescale" => Pending::Timescale(String::new()),
+                    "$scope" => Pending::Scope(Vec::new()),
+                    "$upscope" => {
+                        self.scope.pop();
+                        Pending::Skip
+                    }
+                    "$var" => Pending::Var(Vec::new()),
+                    "$enddefinitions" => {
+                        self.header_done = true;
+                        Pending::Skip
+                    }
+                    "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => Pending::None,
+                    // $date, $version, $comment etc.
+                    _ => Pending::Skip,
+                };
+            }
+            b'#' => {
+                let ticks : f64 = tok[1..].parse().map_err(|_| invalid(tok))?;
+                self.time = ticks * time_ratio(self.tick, self.time_unit);
+                self.index.extend_range(self.time);
+            }
+            b'0' | b'1' | b'x' | b'X' | b'z' | b'Z' => {
+                let val = if tok.as_bytes()[0] == b'1' { 1. } else { 0. };
+                self.set_value(&tok[1..], val)?;
+            }
+            b'b' | b'B' => {
+                // Unknown (x/z) bits are read as 0
+                let val = tok[1..].bytes().fold(0., |acc, b| 2. * acc + if b == b'1' { 1. } else { 0. });
+                self.pending = Pending::Value(val);
+            }
+            b'r' | b'R' => {
+                let val : f64 = tok[1..].parse().map_err(|_| invalid(tok))?;
+                self.pending = Pending::Value(val);
+            }
+            _ => return Err(Box::new(invalid(tok))),
+        }
+        Ok(())
+    }
+
+    /// Add variable from `$var <type> <size> <id> <reference> [range] $end`
+    fn add_var(&mut self, toks: &[String]) -> Result<()> {
+        if toks.len() < 4 {
+            return Err(Box::new(invalid(&toks.join(" "))));
+        }
+        let size : usize = toks[1].parse().map_err(|_| invalid(&toks[1]))?;
+        let ftype = match toks[0].as_str() {
+            "real" | "realtime" => FieldType::Analog,
+            _ if size == 1 => FieldType::Digital,
+            _ => FieldType::DigiBus(size),
+        };
+        let mut name = self.scope.join(".");
+        if !name.is_empty() { name.push('.'); }
+        name.push_str(&toks[3]);
+        let field = self.index.add_field();
+        self.fields.push(FieldInfo { name, ftype });
+        self.ids.entry(toks[2].clone()).or_default().push(field);
+        Ok(())
+    }
+
+    fn set_value(&mut self, id: &str, val: f64) -> Result<()> {
+        let fields = self.ids.get(id).ok_or_else(|| invalid(id))?;
+        for &field in fields {
+            self.index.push(field, self.time, val);
+        }
+        Ok(())
+    }
+}
+
+fn invalid(tok: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Unexpected VCD token '{tok}'"))
+}
+
+/// Convert timescale (e.g. "10ps") to seconds
+fn parse_timescale(ts: &str) -> Result<f64> {
+    let split = ts.find(|c: char| !c.is_ascii_digit()).unwrap_or(ts.len());
+    let (num, unit) = ts.split_at(split);
+    let num : f64 = num.parse().map_err(|_| invalid(ts))?;
+    let unit = match unit {
+        "s" => 1.,
+        "ms" => 1e-3,
+        "us" => 1e-6,
+        "ns" => 1e-9,
+        "ps" => 1e-12,
+        "fs" => 1e-15,
+        _ => return Err(Box::new(invalid(ts))),
+    };
+    Ok(num * unit)
+}
+
+/// Value Change Dump writer. Variables are nested in scopes taken from their dotted names.
+#[derive(Debug)]
+pub struct VcdWriter<W: Write> {
+    w: W,
+    vars: Vec<(String, FieldType)>,
+    time: Option<u64>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            w,
+            vars: Vec::new(),
+            time: None,
+        }
+    }
+
+    /// Write header declaring `fields`, return the timescale used (in seconds), which is
+    /// `timescale` rounded down to a VCD unit (1, 10 or 100 s/ms/us/ns/ps/fs)
+    pub fn write_header(&mut self, timescale: f64, fields: &[FieldInfo]) -> io::Result<f64> {
+        let (timescale, ts_text) = vcd_timescale(timescale);
+        writeln!(self.w, "$version wavefmt {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(self.w, "$timescale {ts_text} $end")?;
+
+        // Group variables by scope, keeping their order within each scope
+        let mut order : Vec<usize> = (0..fields.len()).collect();
+        let scope_of = |fi: &FieldInfo| -> Vec<String> {
+            let mut path : Vec<String> = fi.name.split('.').map(|s| s.to_string()).collect();
+            path.pop();
+            path
+        };
+        order.sort_by_key(|&idx| scope_of(&fields[idx]));
+        self.vars = fields.iter().enumerate()
+            .map(|(idx, fi)| (vcd_id(idx), fi.ftype))
+            .collect();
+
+        let mut scope : Vec<String> = Vec::new();
+        for idx in order {
+            let fi = &fields[idx];
+            let path = scope_of(fi);
+            let common = scope.iter().zip(path.iter()).take_while(|(a, b)| a == b).count();
+            for _ in common..scope.len() {
+                writeln!(self.w, "$upscope $end")?;
+            }
+            for name in &path[common..] {
+                writeln!(self.w, "$scope module {name} $end")?;
+            }
+            scope = path;
+            let name = fi.name.rsplit('.').next().unwrap_or_default();
+            let id = &self.vars[idx].0;
+            match fi.ftype {
+                FieldType::DigiBus(width) => writeln!(self.w, "$var wire {width} {id} {name} [{}:0] $end", width - 1)?,
+                FieldType::Analog => writeln!(self.w, "$var real 64 {id} {name} $end")?,
+                _ => writeln!(self.w, "$var wire 1 {id} {name} $end")?,
+            }
+        }
+        for _ in 0..scope.len() {
+            writeln!(self.w, "$upscope $end")?;
+        }
+        writeln!(self.w, "$enddefinitions $end")?;
+        Ok(timescale)
+    }
+
+    /// Write initial value of every variable at time `ticks`
+    pub fn write_initial(&mut self, ticks: u64, values: &[f64]) -> io::Result<()> {
+        self.write_time(ticks)?;
+        writeln!(self.w, "$dumpvars")?;
+        for (var, &val) in values.iter().enumerate() {
+            self.write_value(var, val)?;
+        }
+        writeln!(self.w, "$end")
+    }
+
+    /// Write change of variable `var` at time `ticks`, which must not go backwards
+    pub fn write_change(&mut self, ticks: u64, var: usize, val: f64) -> io::Result<()> {
+        self.write_time(ticks)?;
+        self.write_value(var, val)
+    }
+
+    /// Write final timestamp and flush
+    pub fn finish(&mut self, ticks: u64) -> io::Result<()> {
+        self.write_time(ticks)?;
+        self.w.flush()
+    }
+
+    fn write_time(&mut self, ticks: u64) -> io::Result<()> {
+        if self.time != Some(ticks) {
+            writeln!(self.w, "#{ticks}")?;
+            self.time = Some(ticks);
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, var: usize, val: f64) -> io::Result<()> {
+        let (id, ftype) = &self.vars[var];
+        match ftype {
+            FieldType::DigiBus(_) => writeln!(self.w, "b{:b} {id}", val as u64),
+            FieldType::Analog => writeln!(self.w, "r{val} {id}"),
+            _ => writeln!(self.w, "{}{id}", if val != 0. { 1 } else { 0 }),
+        }
+    }
+}
+
+/// Identifier code for variable number `idx`, using printable ASCII characters
+fn vcd_id(mut idx: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (idx % 94) as u8) as char);
+        idx /= 94;
+        if idx == 0 { break; }
+        idx -= 1;
+    }
+    id
+}
+
+/// Round `secs` down to a VCD timescale, return it in seconds and as text
+fn vcd_timescale(secs: f64) -> (f64, String) {
+    const UNITS : [(&str, f64); 6] = [("s", 1.), ("ms", 1e-3), ("us", 1e-6), ("ns", 1e-9), ("ps", 1e-12), ("fs", 1e-15)];
+    for (name, unit) in UNITS {
+        for mult in [100., 10., 1.] {
+            if secs >= mult * unit * (1. - 1e-9) {
+                return (mult * unit, format!("{mult}{name}"));
+            }
+        }
+    }
+    (1e-15, "1fs".to_string())
+}
+
+impl WaveFile for VcdFile {
+    /// Read the header, up to `$enddefinitions`
+    fn check_format(&mut self) -> Result<bool> {
+        while !self.header_done {
+            if self.read_and_parse(true)? == 0 { break; }
+        }
+        Ok(self.header_done)
+    }
+
+    fn get_record_size(&self) -> Option<usize> { None }
+
+    fn get_range(&self) -> (f64, f64) {
+        self.index.get_range()
+    }
+
+    fn get_timescale(&self) -> Option<f64> { Some(self.tick) }
+
+    fn get_num_fields(&self) -> usize {
+        self.fields.len()
+    }
+
+    fn get_field_info(&self, field: usize) -> &FieldInfo {
+        &self.fields[field]
+    }
+
+    fn get_num_rows(&self) -> Option<usize> { None }
+
+    fn prepare_iter_range(&mut self, range: &[f64; 2]) -> Result<[usize; 2]> {
+        Ok(self.index.prepare_iter_range(range))
+    }
+
+    fn poll(&mut self) -> Result<bool> {
+        let mut added = false;
+        while self.read_and_parse(false)? > 0 {
+            added = true;
+        }
+        added |= self.parse_partial();
+        Ok(added)
+    }
+
+    fn read_to_end(&mut self) -> Result<()> {
+        self.undo_partial();
+        while self.read_and_parse(true)? > 0 {}
+        if !self.buf.is_empty() {
+            // Terminate last token
+            self.buf.push(b' ');
+            self.read_and_parse(false)?;
+        }
+        Ok(())
+    }
+}
+
+impl IndexedWave for VcdFile {
+    fn get_index(&self) -> &ChangeIndex {
+        &self.index
+    }
+
+    fn get_index_mut(&mut self) -> &mut ChangeIndex {
+        &mut self.index
+    }
+}
@@ -0,0 +1,315 @@
+//! WaveDrom timing diagrams (WaveJSON). Reading accepts the relaxed syntax WaveDrom itself
+//! allows (unquoted keys, single quoted strings, comments, trailing commas), groups are
+//! flattened into dotted lane names.
+use std::io::{self, Error, ErrorKind, Write};
+use crate::Result;
+
+/// Signal lane of a timing diagram
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveLane {
+    pub name: String,
+    /// One character per cell, e.g. `p...` or `01.=.`
+    pub wave: String,
+    /// Labels of bus (`=`, `2`-`9`) cells
+    pub data: Vec<String>,
+    /// Cell length multiplier
+    pub period: f64,
+    /// Shift left (in cells)
+    pub phase: f64,
+}
+
+impl WaveLane {
+    pub fn new(name: &str, wave: String, data: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            wave,
+            data,
+            period: 1.,
+            phase: 0.,
+        }
+    }
+
+    /// Check if lane holds bus values
+    pub fn is_bus(&self) -> bool {
+        self.wave.contains(|c: char| c == '=' || ('2'..='9').contains(&c))
+    }
+
+    /// Return value changes as (time in cells, value), and the end time of the lane.
+    /// Bus values are their label when it is a (decimal or 0x hex) number, otherwise
+    /// the label number. Unknown and high impedance levels read as 0.
+    pub fn changes(&self) -> (Vec<(f64, f64)>, f64) {
+        let mut changes : Vec<(f64, f64)> = Vec::new();
+        let mut push = |t: f64, val: f64| {
+            if changes.last().map(|c| c.1) != Some(val) {
+                changes.push((t, val));
+            }
+        };
+        let mut clock : Option<bool> = None; // Rising (true) or falling clock being repeated
+        let mut data = 0;
+        let mut cells = 0;
+        for (cell, c) in self.wave.chars().enumerate() {
+            let t = cell as f64 * self.period - self.phase;
+            let half = t + 0.5 * self.period;
+            cells = cell + 1;
+            match c {
+                '.' | '|' => {
+                    if let Some(rise) = clock {
+                        push(t, if rise { 1. } else { 0. });
+                        push(half, if rise { 0. } else { 1. });
+                    }
+                    continue;
+                }
+                'p' | 'P' | 'n' | 'N' => {
+                    let rise = c == 'p' || c == 'P';
+                    push(t, if rise { 1. } else { 0. });
+                    push(half, if rise { 0. } else { 1. });
+                    clock = Some(rise);
+                    continue;
+                }
+                '=' | '2'..='9' => {
+                    let label = self.data.get(data).map(|s| s.trim()).unwrap_or_default();
+                    push(t, parse_label(label).unwrap_or(data as f64));
+                    data += 1;
+                }
+                '1' | 'h' | 'H' | 'u' => push(t, 1.),
+                _ => push(t, 0.),
+            }
+            clock = None;
+        }
+        (changes, cells as f64 * self.period - self.phase)
+    }
+}
+
+fn parse_label(label: &str) -> Option<f64> {
+    if let Some(hex) = label.strip_prefix("0x") {
+        return u64::from_str_radix(hex, 16).ok().map(|v| v as f64);
+    }
+    label.parse().ok()
+}
+
+/// Read lanes of a WaveJSON diagram
+pub fn read_wavejson(text: &str) -> Result<Vec<WaveLane>> {
+    let mut parser = Parser { text: text.as_bytes(), pos: 0 };
+    let root = parser.value()?;
+    let Some(Value::Arr(signal)) = root.get("signal") else {
+        return Err(Box::new(invalid("no signal array")));
+    };
+    let mut lanes = Vec::new();
+    add_lanes(&mut lanes, "", signal);
+    Ok(lanes)
+}
+
+fn add_lanes(lanes: &mut Vec<WaveLane>, scope: &str, items: &[Value]) {
+    for item in items {
+        match item {
+            Value::Obj(_) => {
+                let (Some(Value::Str(name)), Some(Value::Str(wave))) = (item.get("name"), item.get("wave")) else {
+                    continue; // Spacer
+                };
+                let data = match item.get("data") {
+                    Some(Value::Arr(data)) => data.iter().map(|v| v.to_label()).collect(),
+                    Some(Value::Str(data)) => data.split_whitespace().map(|s| s.to_string()).collect(),
+                    _ => Vec::new(),
+                };
+                let mut lane = WaveLane::new(&format!("{scope}{name}"), wave.clone(), data);
+                if let Some(Value::Num(period)) = item.get("period") { lane.period = *period; }
+                if let Some(Value::Num(phase)) = item.get("phase") { lane.phase = *phase; }
+                lanes.push(lane);
+            }
+            Value::Arr(group) => {
+                // Group: optional name followed by lanes
+                let (scope, group) = match group.first() {
+                    Some(Value::Str(name)) => (format!("{scope}{name}."), &group[1..]),
+                    _ => (scope.to_string(), &group[..]),
+                };
+                add_lanes(lanes, &scope, group);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write lanes as a WaveJSON diagram
+pub fn write_wavejson(w: &mut impl Write, lanes: &[WaveLane]) -> io::Result<()> {
+    writeln!(w, "{{\"signal\": [")?;
+    for (n, lane) in lanes.iter().enumerate() {
+        write!(w, "  {{\"name\": {}, \"wave\": {}", quote(&lane.name), quote(&lane.wave))?;
+        if !lane.data.is_empty() {
+            let data : Vec<String> = lane.data.iter().map(|s| quote(s)).collect();
+            write!(w, ", \"data\": [{}]", data.join(", "))?;
+        }
+        if lane.period != 1. { write!(w, ", \"period\": {}", lane.period)?; }
+        if lane.phase != 0. { write!(w, ", \"phase\": {}", lane.phase)?; }
+        writeln!(w, "}}{}", if n + 1 < lanes.len() { "," } else { "" })?;
+    }
+    writeln!(w, "]}}")
+}
+
+/// Quote a string for JSON output
+pub fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("WaveJSON: {msg}"))
+}
+
+#[derive(Debug)]
+enum Value {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Value>),
+    Obj(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Obj(items) => items.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn to_label(&self) -> String {
+        match self {
+            Value::Str(s) => s.clone(),
+            Value::Num(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Relaxed JSON parser
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        loop {
+            while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let rest = &self.text[self.pos..];
+            if rest.starts_with(b"//") {
+                self.pos += rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+            } else if rest.starts_with(b"/*") {
+                self.pos += rest.windows(2).position(|w| w == b"*/").map(|p| p + 2).unwrap_or(rest.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_space();
+        self.text.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while self.peek() != Some(b'}') {
+                    let key = match self.peek() {
+                        Some(b'"') | Some(b'\'') => self.string()?,
+                        _ => self.word(),
+                    };
+                    if key.is_empty() || self.peek() != Some(b':') {
+                        return Err(invalid("expected key"));
+                    }
+                    self.pos += 1;
+                    items.push((key, self.value()?));
+                    self.separator(b'}')?;
+                }
+                self.pos += 1;
+                Ok(Value::Obj(items))
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while self.peek() != Some(b']') {
+                    items.push(self.value()?);
+                    self.separator(b']')?;
+                }
+                self.pos += 1;
+                Ok(Value::Arr(items))
+            }
+            Some(b'"') | Some(b'\'') => Ok(Value::Str(self.string()?)),
+            Some(_) => {
+                let word = self.word();
+                match word.as_str() {
+                    "null" => Ok(Value::Null),
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => word.parse().map(Value::Num).map_err(|_| invalid("unexpected value")),
+                }
+            }
+            None => Err(invalid("unexpected end")),
+        }
+    }
+
+    /// Skip comma after an item, unless at the closing bracket
+    fn separator(&mut self, close: u8) -> io::Result<()> {
+        match self.peek() {
+            Some(b',') => { self.pos += 1; Ok(()) }
+            Some(c) if c == close => Ok(()),
+            _ => Err(invalid("expected ',' or closing bracket")),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self.pos < self.text.len() && (self.text[self.pos].is_ascii_alphanumeric() || b"_$+-.".contains(&self.text[self.pos])) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.text[start..self.pos]).into_owned()
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let quote = self.text[self.pos];
+        self.pos += 1;
+        let mut out : Vec<u8> = Vec::new();
+        loop {
+            let Some(&c) = self.text.get(self.pos) else { return Err(invalid("unterminated string")) };
+            self.pos += 1;
+            match c {
+                c if c == quote => break,
+                b'\\' => {
+                    let Some(&e) = self.text.get(self.pos) else { return Err(invalid("unterminated string")) };
+                    self.pos += 1;
+                    match e {
+                        b'n' => out.push(b'\n'),
+                        b't' => out.push(b'\t'),
+                        b'u' => {
+                            let hex = self.text.get(self.pos..self.pos + 4).ok_or_else(|| invalid("bad escape"))?;
+                            let code = u32::from_str_radix(&String::from_utf8_lossy(hex), 16).map_err(|_| invalid("bad escape"))?;
+                            let mut buf = [0u8; 4];
+                            out.extend_from_slice(char::from_u32(code).unwrap_or('?').encode_utf8(&mut buf).as_bytes());
+                            self.pos += 4;
+                        }
+                        e => out.push(e),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+}